@@ -1,9 +1,30 @@
 use clap::{Parser, Subcommand, Args, ValueEnum};
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use crate::todo::Priority;
+use serde::Deserialize;
 
 /// CLI structure to parse command line arguments
 #[derive(Parser)]
 #[command(author, version, about = "A simple and flexible command-line todo application built with Rust.", long_about = None)]
 pub struct Cli {
+    /// Use the todo list stored in this file instead of the one in the data directory
+    #[arg(long, global = true, value_name = "PATH")]
+    pub file: Option<PathBuf>,
+
+    /// Use the global todo list even inside a project with a .todo.json
+    #[arg(long, short, global = true, conflicts_with = "file")]
+    pub global: bool,
+
+    /// When to color the output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto, value_name = "WHEN")]
+    pub color: ColorChoice,
+
+    /// The format todos, backups and views are listed in [default: text, or the format saved with a view]
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<OutputFormat>,
+
     #[command(subcommand)]
     pub pattern: Option<Pattern>,
 }
@@ -18,6 +39,10 @@ pub enum Pattern {
     /// Add a new todo
     #[command(alias = "a")]
     Add { 
+        /// The ID of the todo to add the new todo(s) to as subtasks
+        #[arg(long, short, value_name = "TODO_ID")]
+        parent: Option<usize>,
+
         /// The description of the todo(s), separated by '::' for multiple items
         #[arg(value_name = "TODO_DESCRIPTION", num_args(1..))]
         args: Vec<String> 
@@ -38,14 +63,24 @@ pub enum Pattern {
     /// Filters the todo list by the specified query string
     #[command(alias = "f")]
     Filter {
-        /// Query string to filter todos by
+        /// Query string to filter todos by, e.g. `#work and not done` or `age>7d or (starred "release notes")`
         #[arg(value_name = "QUERY")]
         query: Vec<String>,
+        /// Match words, phrases and regular expressions case-sensitively
+        #[arg(short = 'c', long, conflicts_with = "ignore_case")]
+        case_sensitive: bool,
+        /// Ignore case when matching words, phrases and regular expressions
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
     },
     
     /// Mark a todo as done
     #[command(alias = "d")]
     Done { 
+        /// Also mark all subtasks as done
+        #[arg(long, short)]
+        cascade: bool,
+
         /// The ID of the todo to mark as done
         #[arg(value_name = "TODO_ID")]
         args: Vec<usize> 
@@ -67,9 +102,37 @@ pub enum Pattern {
         args: Vec<usize>
     },
 
+    /// Set the priority of a todo
+    #[command(alias = "p")]
+    Priority {
+        /// The priority to set
+        #[arg(value_name = "PRIORITY")]
+        level: Priority,
+
+        /// The ID of the todo to set the priority of
+        #[arg(value_name = "TODO_ID", num_args(1..))]
+        args: Vec<usize>,
+    },
+
+    /// Make a todo recurring or stop its recurrence
+    #[command(alias = "R")]
+    Recur {
+        /// The recurrence rule (daily, weekly, mon,thu, monthly, 15th, 3d) or 'none' to stop it
+        #[arg(value_name = "RULE")]
+        rule: String,
+
+        /// The ID of the todo to set the recurrence of
+        #[arg(value_name = "TODO_ID", num_args(1..))]
+        args: Vec<usize>,
+    },
+
     /// Remove a todo
     #[command(alias = "r")]
     Rm { 
+        /// Also remove all subtasks
+        #[arg(long, short)]
+        cascade: bool,
+
         /// The ID of the todo to emove
         #[arg(value_name = "TODO_ID")]
         args: Vec<usize> 
@@ -93,6 +156,118 @@ pub enum Pattern {
         #[command(subcommand)]
         name: Option<BackupAction>,
     },
+
+    /// Manage boards of todos
+    #[command(alias = "B")]
+    Board {
+        /// The optional board action
+        #[command(subcommand)]
+        action: Option<BoardAction>,
+    },
+
+    /// Manage tags of todos
+    #[command(alias = "t")]
+    Tag {
+        /// The optional tag action
+        #[command(subcommand)]
+        action: Option<TagAction>,
+    },
+
+    /// Search todos by fuzzy matching their description, tags and notes, best matches first
+    #[command(alias = "F")]
+    Search {
+        /// The words to search for, in any order
+        #[arg(value_name = "QUERY", required = true)]
+        query: Vec<String>,
+
+        /// The number of results to show
+        #[arg(long, short = 'n', value_name = "N", default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Run a saved filter query, or manage the saved views
+    #[command(alias = "v", args_conflicts_with_subcommands = true)]
+    View {
+        /// The optional view action
+        #[command(subcommand)]
+        action: Option<ViewAction>,
+
+        /// The name of the view to run, lists all views if not given
+        #[arg(value_name = "VIEW")]
+        name: Option<String>,
+    },
+
+    /// Show a single todo with its notes and details
+    #[command(alias = "i")]
+    Show {
+        /// The ID of the todo to show
+        #[arg(value_name = "TODO_ID")]
+        id: usize,
+    },
+
+    /// Manage the notes of a todo
+    #[command(alias = "n")]
+    Note {
+        /// The note action
+        #[command(subcommand)]
+        action: NoteAction,
+    },
+
+    /// Undo the last changes to the todo list
+    #[command(alias = "z")]
+    Undo {
+        /// The number of changes to undo
+        #[arg(value_name = "STEPS", default_value_t = 1)]
+        steps: usize,
+    },
+
+    /// Redo the last undone changes
+    #[command(alias = "Z")]
+    Redo {
+        /// The number of changes to redo
+        #[arg(value_name = "STEPS", default_value_t = 1)]
+        steps: usize,
+    },
+
+    /// Show the recent changes that can be undone or redone
+    #[command(alias = "H")]
+    History,
+
+    /// Create a project todo list (.todo.json) in the current directory
+    Init,
+
+    /// Manage the configuration file
+    Config {
+        /// The configuration action
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// An alias or a command defined in config.lua, followed by its arguments
+    #[command(external_subcommand)]
+    Custom(Vec<String>),
+}
+
+impl Pattern {
+    /// Whether the command only reads the todo list, so it needs neither to be journaled nor saved.
+    ///
+    /// Views, backups and the configuration are not part of the todo list, so managing them only reads it.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Pattern::List
+                | Pattern::Filter { .. }
+                | Pattern::Search { .. }
+                | Pattern::Show { .. }
+                | Pattern::View { .. }
+                | Pattern::History
+                | Pattern::Init
+                | Pattern::Config { .. }
+                | Pattern::Backup { name: None | Some(BackupAction::List | BackupAction::Create | BackupAction::Open { .. } | BackupAction::Delete(_)) }
+                | Pattern::Board { action: None | Some(BoardAction::List) }
+                | Pattern::Tag { action: None | Some(TagAction::List) }
+        )
+    }
 }
 
 /// Enum representing different backup actions
@@ -131,6 +306,244 @@ pub enum BackupAction {
     Delete(DeleteOptions), 
 }
 
+/// Enum representing different board actions
+#[derive(Subcommand)]
+pub enum BoardAction {
+    /// List all boards (default action)
+    #[command(alias = "l")]
+    List,
+
+    /// Create a new board
+    #[command(alias = "c")]
+    Create {
+        /// The name of the new board
+        #[arg(value_name = "BOARD")]
+        name: String,
+    },
+
+    /// Rename an existing board
+    #[command(alias = "n")]
+    Rename {
+        /// The current name of the board
+        #[arg(value_name = "BOARD")]
+        name: String,
+
+        /// The new name of the board
+        #[arg(value_name = "NEW_NAME")]
+        new_name: String,
+    },
+
+    /// Delete a board and all of its todos
+    #[command(alias = "D")]
+    Delete {
+        /// The name of the board to delete
+        #[arg(value_name = "BOARD")]
+        name: String,
+    },
+
+    /// Switch the active board
+    #[command(alias = "s")]
+    Switch {
+        /// The name of the board to switch to
+        #[arg(value_name = "BOARD")]
+        name: String,
+    },
+
+    /// Move todos from the active board to another board
+    #[command(alias = "m")]
+    Move {
+        /// The name of the board to move the todos to
+        #[arg(value_name = "BOARD")]
+        board: String,
+
+        /// The ID of the todo to move
+        #[arg(value_name = "TODO_ID")]
+        args: Vec<usize>,
+    },
+}
+
+/// Enum representing different tag actions
+#[derive(Subcommand)]
+pub enum TagAction {
+    /// List all tags with their number of todos (default action)
+    #[command(alias = "l")]
+    List,
+
+    /// Add a tag to todos
+    #[command(alias = "a")]
+    Add {
+        /// The tag to add, with or without a leading '#'
+        #[arg(value_name = "TAG")]
+        tag: String,
+
+        /// The ID of the todo to tag
+        #[arg(value_name = "TODO_ID", num_args(1..))]
+        args: Vec<usize>,
+    },
+
+    /// Remove a tag from todos
+    #[command(alias = "r")]
+    Remove {
+        /// The tag to remove, with or without a leading '#'
+        #[arg(value_name = "TAG")]
+        tag: String,
+
+        /// The ID of the todo to untag
+        #[arg(value_name = "TODO_ID", num_args(1..))]
+        args: Vec<usize>,
+    },
+}
+
+/// Enum representing different note actions
+#[derive(Subcommand)]
+pub enum NoteAction {
+    /// Append a line to the notes of a todo
+    #[command(alias = "a")]
+    Add {
+        /// The ID of the todo to add the note to
+        #[arg(value_name = "TODO_ID")]
+        id: usize,
+
+        /// The text of the note
+        #[arg(value_name = "TEXT", num_args(1..))]
+        text: Vec<String>,
+    },
+
+    /// Replace the notes of a todo, clearing them if no text is given
+    #[command(alias = "s")]
+    Set {
+        /// The ID of the todo to replace the notes of
+        #[arg(value_name = "TODO_ID")]
+        id: usize,
+
+        /// The new text of the notes
+        #[arg(value_name = "TEXT")]
+        text: Vec<String>,
+    },
+
+    /// Open the notes of a todo in $EDITOR
+    #[command(alias = "e")]
+    Edit {
+        /// The ID of the todo to edit the notes of
+        #[arg(value_name = "TODO_ID")]
+        id: usize,
+    },
+}
+
+/// Enum representing different view actions
+#[derive(Subcommand)]
+pub enum ViewAction {
+    /// List all views with the number of todos they show (default action)
+    #[command(alias = "l")]
+    List,
+
+    /// Save a filter query as a view, replacing a view of the same name
+    #[command(alias = "s")]
+    Save {
+        /// The name of the view
+        #[arg(value_name = "VIEW")]
+        name: String,
+
+        /// The filter query of the view, e.g. `not done and age>14d`
+        #[arg(value_name = "QUERY", required = true)]
+        query: Vec<String>,
+
+        /// The order the view lists its todos in, the order of the board if not given
+        #[arg(long, value_name = "SORT_BY")]
+        sort: Option<SortBy>,
+    },
+
+    /// Delete a view
+    #[command(alias = "D")]
+    Delete {
+        /// The name of the view to delete
+        #[arg(value_name = "VIEW")]
+        name: String,
+    },
+}
+
+/// Enum representing different configuration actions
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Show the effective settings and where their values come from
+    Show,
+
+    /// Print the effective value of a setting
+    #[command(alias = "g")]
+    Get {
+        /// The key of the setting
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
+
+    /// Change a setting in the configuration file, keeping the rest of the file as it is
+    #[command(alias = "s")]
+    Set {
+        /// The key of the setting
+        #[arg(value_name = "KEY")]
+        key: String,
+
+        /// The new value, e.g. `true`, `10`, `sqlite` or `["home", "work"]`
+        #[arg(value_name = "VALUE")]
+        value: String,
+    },
+
+    /// Print the path of the configuration file in use
+    #[command(alias = "p")]
+    Path,
+
+    /// Validate the configuration file and report unknown keys and invalid values
+    #[command(alias = "c")]
+    Check,
+
+    /// Create a configuration file with the default settings
+    Init {
+        /// The format of the configuration file, given without `--format`, which is the global output format
+        #[arg(id = "config_format", value_name = "FORMAT", value_enum, default_value_t = ConfigFormat::Lua)]
+        format: ConfigFormat,
+
+        /// Overwrite an existing configuration file of the same format
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Enum representing the formats of the configuration file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    /// A Lua script defining a `Config` table (config.lua)
+    Lua,
+
+    /// A TOML document (config.toml)
+    Toml,
+}
+
+/// Enum representing the formats todos, backups and views are listed in
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Colored text for humans
+    #[default]
+    Text,
+    /// A JSON array of objects
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// Enum representing when the output is colored
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color the output of a terminal, unless `NO_COLOR` is set or `TERM` is `dumb`
+    Auto,
+    /// Always color the output
+    Always,
+    /// Never color the output
+    Never,
+}
+
 ///Struct representing delete options
 #[derive(Args)]
 pub struct DeleteOptions {
@@ -156,7 +569,8 @@ pub enum DeleteOption {
 }
 
 /// Enum representing sorting criteria for todos
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SortBy {
     /// Sort by ID
     Id,
@@ -164,6 +578,181 @@ pub enum SortBy {
     /// Sort by creation date
     Date,
 
+    /// Sort by due date, todos without a due date last
+    Due,
+
+    /// Sort by priority, highest first
+    Priority,
+
     /// Sort by completion status (default action)
     Done,
 }
+
+/// Replace an alias with the command it stands for.
+///
+/// Aliases are only looked up for commands that are not built in, and may stand for other aliases. The arguments given
+/// after an alias are appended to the command it stands for, which is split into arguments like a shell does. Options
+/// like `--format` set by the alias or given after it apply along with the options given before it, and take
+/// precedence over them. As when given directly, `--file` and `--global` cannot be combined.
+///
+/// # Arguments
+///
+/// `cli` - The parsed command line.
+/// `aliases` - The command lines by alias name, e.g. `today` for `filter due:today`.
+///
+/// # Returns
+///
+/// `Result<Cli>` - The command line with all aliases replaced, or an error if an alias does not stand for a valid
+/// command or refers to itself.
+pub fn expand_aliases(mut cli: Cli, aliases: &BTreeMap<String, String>) -> Result<Cli> {
+    let mut expanded: Vec<String> = Vec::new();
+    while let Some(Pattern::Custom(args)) = &cli.pattern {
+        let name = args[0].clone();
+        let Some(command) = aliases.get(&name) else {
+            break;
+        };
+        if expanded.contains(&name) {
+            return Err(anyhow!("The alias '{}' refers to itself", name));
+        }
+
+        let command = split_command_line(command)
+            .with_context(|| format!("The alias '{}' does not stand for a valid command", name))?;
+        let command_line: Vec<String> = std::iter::once("todo".to_string())
+            .chain(command)
+            .chain(args[1..].iter().cloned())
+            .collect();
+        let expansion = Cli::try_parse_from(command_line)
+            .with_context(|| format!("The alias '{}' does not stand for a valid command", name))?;
+        cli = Cli {
+            file: expansion.file.or(cli.file),
+            global: expansion.global || cli.global,
+            color: if expansion.color == ColorChoice::Auto { cli.color } else { expansion.color },
+            format: expansion.format.or(cli.format),
+            pattern: expansion.pattern,
+        };
+        // The options are merged after parsing, so their conflict is not checked by clap
+        if cli.file.is_some() && cli.global {
+            return Err(anyhow!("The alias '{}' combines --file and --global: the argument '--file <PATH>' cannot be used with '--global'", name));
+        }
+        expanded.push(name);
+    }
+    Ok(cli)
+}
+
+/// Helper function to split a command line into arguments like a POSIX shell.
+///
+/// Arguments are separated by whitespace. Single quotes keep everything up to the closing quote, double quotes keep
+/// everything but the escaped `\"` and `\\`, and a backslash outside of quotes keeps the next character.
+///
+/// # Returns
+///
+/// `Result<Vec<String>>` - The arguments, or an error if a quote is not closed.
+fn split_command_line(command: &str) -> Result<Vec<String>> {
+    let unclosed = || anyhow!("Unclosed quote in '{}'", command);
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or_else(unclosed)? {
+                        '\'' => break,
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or_else(unclosed)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(unclosed)? {
+                            c @ ('"' | '\\') => arg.push(c),
+                            c => arg.extend(['\\', c]),
+                        },
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => arg.get_or_insert_with(String::new).extend(chars.next()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+    use std::collections::BTreeMap;
+    use crate::cli::{expand_aliases, split_command_line, Cli, ColorChoice, ConfigAction, ConfigFormat, OutputFormat, Pattern};
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, command)| (name.to_string(), command.to_string())).collect()
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("todo").chain(args.iter().copied())).expect("Failed to parse the command line")
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+
+        let cli = cli(&["config", "init", "toml"]);
+        assert!(matches!(cli.pattern, Some(Pattern::Config { action: ConfigAction::Init { format: ConfigFormat::Toml, force: false } })));
+        assert_eq!(cli.format, None);
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = aliases(&[("today", "filter due:today"), ("td", "today"), ("loop", "again"), ("again", "loop")]);
+
+        let expanded = expand_aliases(cli(&["td", "#work"]), &aliases).unwrap();
+        assert!(matches!(expanded.pattern, Some(Pattern::Filter { query, .. }) if query == vec!["due:today", "#work"]));
+
+        let expanded = expand_aliases(cli(&["standup"]), &aliases).unwrap();
+        assert!(matches!(expanded.pattern, Some(Pattern::Custom(args)) if args == vec!["standup"]));
+        assert!(matches!(expand_aliases(cli(&["list"]), &aliases).unwrap().pattern, Some(Pattern::List)));
+
+        let error = expand_aliases(cli(&["loop"]), &aliases).err().expect("The alias loop should be reported");
+        assert_eq!(error.to_string(), "The alias 'loop' refers to itself");
+    }
+
+    #[test]
+    fn test_expand_aliases_keeps_options_and_quotes() {
+        let aliases = aliases(&[("week", "filter 'due:this week' \"#big project\" --format json"), ("today", "filter due:today"), ("broken", "filter 'due:today")]);
+
+        let expanded = expand_aliases(cli(&["--color", "never", "--global", "week", "#work"]), &aliases).unwrap();
+        assert!(matches!(expanded.pattern, Some(Pattern::Filter { query, .. }) if query == vec!["due:this week", "#big project", "#work"]));
+        assert_eq!(expanded.format, Some(OutputFormat::Json));
+        assert_eq!(expanded.color, ColorChoice::Never);
+        assert!(expanded.global);
+
+        let expanded = expand_aliases(cli(&["--format", "json", "today", "--format", "csv"]), &aliases).unwrap();
+        assert_eq!(expanded.format, Some(OutputFormat::Csv));
+
+        let error = expand_aliases(cli(&["broken"]), &aliases).err().expect("The unclosed quote should be reported");
+        assert_eq!(format!("{:#}", error), "The alias 'broken' does not stand for a valid command: Unclosed quote in 'filter 'due:today'");
+    }
+
+    #[test]
+    fn test_expand_aliases_rejects_file_with_global() {
+        let aliases = aliases(&[("mine", "list --global"), ("here", "list --file todos.json")]);
+        for args in [&["--file", "todos.json", "mine"][..], &["--global", "here"], &["mine", "--file", "todos.json"]] {
+            let error = expand_aliases(cli(args), &aliases).err().expect("Combining --file and --global should fail");
+            assert!(format!("{:#}", error).contains("cannot be used with"), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(split_command_line("  add  \"Buy \\\"oat\\\" milk\" it\\'s '' a\\ b ").unwrap(), vec!["add", "Buy \"oat\" milk", "it's", "", "a b"]);
+        assert!(split_command_line("add \"Buy milk").is_err());
+    }
+}
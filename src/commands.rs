@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Result};
+use rlua::{Function, Lua, Table, Value};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use crate::hooks::{apply_table, lua_error_message, todo_to_table};
+use crate::todo::Todo;
+
+/// Trait giving the commands defined in `config.lua` access to the todo list
+pub trait ScriptApi {
+    /// The name of the active board
+    fn board_name(&self) -> &str;
+
+    /// The todos of the active board
+    fn todos(&self) -> &[Todo];
+
+    /// Get a todo of the active board to change it
+    fn todo_mut(&mut self, id: usize) -> Option<&mut Todo>;
+
+    /// Add todos from a description with inline tokens, as `todo add` does, returning the IDs of the new todos
+    fn add_from_description(&mut self, description: &str) -> Result<Vec<usize>>;
+
+    /// Mark a todo as done, as `todo done` does
+    fn mark_done(&mut self, id: usize) -> Result<()>;
+
+    /// Remove a todo, as `todo rm` does
+    fn remove_todo(&mut self, id: usize) -> Result<()>;
+
+    /// Render the todos of the active board with the given IDs
+    fn render(&self, ids: &[usize]);
+}
+
+/// Struct holding the commands a Lua configuration defines in its global `Commands` table.
+///
+/// A command is a function called as `Commands.name(list, args)`, where `args` is the list of arguments given after
+/// the name of the command and `list` gives access to the todos of the active board:
+///
+/// * `list.board` - The name of the active board.
+/// * `list.todos()` - The todos, as the tables passed to hooks.
+/// * `list.get(id)` - The todo with an ID, or `nil`.
+/// * `list.add(description)` - Add todos like `todo add`, returning their IDs.
+/// * `list.update(todo)` - Save the changes to a todo table.
+/// * `list.done(id)` - Mark a todo as done.
+/// * `list.remove(id)` - Remove a todo.
+/// * `list.show(todos)` - Print a list of todos, given as todo tables or IDs.
+#[derive(Clone, Default)]
+pub struct LuaCommands {
+    lua: Option<Rc<Lua>>,
+}
+
+impl fmt::Debug for LuaCommands {
+    /// Formats the commands as their names
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl LuaCommands {
+    /// Keep the commands defined by a Lua configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `lua` - The Lua state the configuration was run in.
+    ///
+    /// # Returns
+    ///
+    /// `Result<LuaCommands>` - The commands, or an error if `Commands` is not a table of functions.
+    pub fn new(lua: Rc<Lua>) -> Result<Self> {
+        match lua.globals().get::<_, Value>("Commands")? {
+            Value::Nil => {}
+            Value::Table(commands) => {
+                for pair in commands.pairs::<Value, Value>() {
+                    match pair? {
+                        (Value::String(_), Value::Function(_)) => {}
+                        (Value::String(name), other) => {
+                            return Err(anyhow!("The command '{}' must be a function, found {}", name.to_str()?, other.type_name()));
+                        }
+                        (other, _) => return Err(anyhow!("Command names must be strings, found {}", other.type_name())),
+                    }
+                }
+            }
+            other => return Err(anyhow!("The global `Commands` must be a table, found {}", other.type_name())),
+        }
+        Ok(LuaCommands { lua: Some(lua) })
+    }
+
+    /// The names of the commands, sorted alphabetically
+    pub fn names(&self) -> Vec<String> {
+        let commands = self.lua.as_ref()
+            .and_then(|lua| lua.globals().get::<_, Option<Table>>("Commands").ok().flatten());
+        let mut names: Vec<String> = match commands {
+            Some(commands) => commands.pairs::<String, Function>().filter_map(|pair| pair.ok()).map(|(name, _)| name).collect(),
+            None => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+
+    /// Helper function to get the function of a command, if it is defined
+    fn function(&self, name: &str) -> Option<Function<'_>> {
+        let commands: Table = self.lua.as_ref()?.globals().get::<_, Option<Table>>("Commands").ok()??;
+        commands.get::<_, Option<Function>>(name).ok().flatten()
+    }
+
+    /// Check if a command is defined
+    pub fn contains(&self, name: &str) -> bool {
+        self.function(name).is_some()
+    }
+
+    /// Run a command.
+    ///
+    /// # Arguments
+    ///
+    /// `name` - The name of the command.
+    /// `args` - The arguments given after the name of the command.
+    /// `api` - The todo list the command works on.
+    ///
+    /// # Returns
+    ///
+    /// `Result<()>` - Returns `Ok(())` if the command succeeds, or an error if it is not defined or raises an error.
+    pub fn run(&self, name: &str, args: Vec<String>, api: &mut dyn ScriptApi) -> Result<()> {
+        let (Some(lua), Some(function)) = (self.lua.as_ref(), self.function(name)) else {
+            return Err(anyhow!("Unknown command '{}'", name));
+        };
+
+        let api = RefCell::new(api);
+        lua.scope(|scope| {
+            let list = lua.create_table()?;
+            list.set("board", api.borrow().board_name())?;
+
+            list.set("todos", scope.create_function(|lua, ()| {
+                let api = api.borrow();
+                let todos = api.todos().iter()
+                    .map(|todo| todo_to_table(lua, todo, api.board_name()))
+                    .collect::<rlua::Result<Vec<_>>>()?;
+                lua.create_sequence_from(todos)
+            })?)?;
+
+            list.set("get", scope.create_function(|lua, id: usize| {
+                let api = api.borrow();
+                api.todos().iter()
+                    .find(|todo| todo.id == id)
+                    .map(|todo| todo_to_table(lua, todo, api.board_name()))
+                    .transpose()
+            })?)?;
+
+            list.set("add", scope.create_function(|_, description: String| {
+                api.borrow_mut().add_from_description(&description).map_err(rlua::Error::external)
+            })?)?;
+
+            list.set("update", scope.create_function(|_, view: Table| {
+                let id: usize = view.get("id")?;
+                let mut api = api.borrow_mut();
+                let todo = api.todo_mut(id)
+                    .ok_or_else(|| rlua::Error::external(anyhow!("Todo item with ID {} not found", id)))?;
+                apply_table(&view, todo).map_err(rlua::Error::external)
+            })?)?;
+
+            list.set("done", scope.create_function(|_, id: usize| {
+                api.borrow_mut().mark_done(id).map_err(rlua::Error::external)
+            })?)?;
+
+            list.set("remove", scope.create_function(|_, id: usize| {
+                api.borrow_mut().remove_todo(id).map_err(rlua::Error::external)
+            })?)?;
+
+            list.set("show", scope.create_function(|_, todos: Vec<Value>| {
+                let ids = todos.iter()
+                    .map(|todo| match todo {
+                        Value::Table(todo) => todo.get::<_, usize>("id"),
+                        Value::Integer(id) => usize::try_from(*id).map_err(rlua::Error::external),
+                        other => Err(rlua::Error::external(anyhow!("Expected a todo or an ID, found {}", other.type_name()))),
+                    })
+                    .collect::<rlua::Result<Vec<_>>>()?;
+                api.borrow().render(&ids);
+                Ok(())
+            })?)?;
+
+            function.call::<_, ()>((list, lua.create_sequence_from(args)?))
+        })
+        .map_err(|e| anyhow!("The command '{}' failed: {}", name, lua_error_message(&e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Result};
+    use rlua::Lua;
+    use std::rc::Rc;
+    use crate::commands::{LuaCommands, ScriptApi};
+    use crate::todo::{Priority, Todo};
+
+    #[derive(Default)]
+    struct FakeList {
+        todos: Vec<Todo>,
+        shown: Vec<usize>,
+    }
+
+    impl ScriptApi for FakeList {
+        fn board_name(&self) -> &str {
+            "default"
+        }
+
+        fn todos(&self) -> &[Todo] {
+            &self.todos
+        }
+
+        fn todo_mut(&mut self, id: usize) -> Option<&mut Todo> {
+            self.todos.iter_mut().find(|todo| todo.id == id)
+        }
+
+        fn add_from_description(&mut self, description: &str) -> Result<Vec<usize>> {
+            let id = self.todos.len() + 1;
+            self.todos.push(Todo::new(id, description));
+            Ok(vec![id])
+        }
+
+        fn mark_done(&mut self, id: usize) -> Result<()> {
+            let todo = self.todo_mut(id).ok_or_else(|| anyhow!("Todo item with ID {} not found", id))?;
+            todo.is_complete = true;
+            Ok(())
+        }
+
+        fn remove_todo(&mut self, id: usize) -> Result<()> {
+            self.todos.retain(|todo| todo.id != id);
+            Ok(())
+        }
+
+        fn render(&self, ids: &[usize]) {
+            assert_eq!(ids, self.shown.as_slice());
+        }
+    }
+
+    fn commands(source: &str) -> LuaCommands {
+        let lua = Lua::new();
+        lua.load(source).exec().expect("Failed to run the commands");
+        LuaCommands::new(Rc::new(lua)).expect("Failed to keep the commands")
+    }
+
+    #[test]
+    fn test_command_uses_the_todo_list() {
+        let commands = commands(r#"
+            Commands = {}
+            function Commands.plan(list, args)
+                for _, description in ipairs(args) do
+                    list.add(description)
+                end
+                local first = list.get(1)
+                first.priority = "high"
+                list.update(first)
+                list.done(2)
+                list.remove(3)
+                local open = {}
+                for _, todo in ipairs(list.todos()) do
+                    if not todo.is_complete then table.insert(open, todo) end
+                end
+                list.show(open)
+            end
+            function Commands.fail(list, args) error("Nothing to do", 0) end
+        "#);
+        assert_eq!(commands.names(), vec!["fail".to_string(), "plan".to_string()]);
+        assert!(commands.contains("plan"));
+        assert!(!commands.contains("other"));
+
+        let mut list = FakeList { shown: vec![1], ..FakeList::default() };
+        let args = vec!["Write report".to_string(), "Send mail".to_string(), "Call Bob".to_string()];
+        commands.run("plan", args, &mut list).unwrap();
+        assert_eq!(list.todos.len(), 2);
+        assert_eq!(list.todos[0].priority, Priority::High);
+        assert!(list.todos[1].is_complete);
+
+        let error = commands.run("fail", Vec::new(), &mut list).expect_err("The command should fail");
+        assert_eq!(error.to_string(), "The command 'fail' failed: Nothing to do");
+        let error = commands.run("plan", vec!["Again".to_string()], &mut FakeList::default()).expect_err("Todo 2 does not exist");
+        assert_eq!(error.to_string(), "The command 'plan' failed: Todo item with ID 2 not found");
+    }
+
+    #[test]
+    fn test_commands_must_be_functions() {
+        let lua = Lua::new();
+        lua.load("Commands = { standup = 'filter' }").exec().unwrap();
+        let error = LuaCommands::new(Rc::new(lua)).expect_err("The command should be rejected");
+        assert_eq!(error.to_string(), "The command 'standup' must be a function, found string");
+    }
+}
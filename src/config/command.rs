@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use crate::cli::{ConfigAction, ConfigFormat};
+use crate::config::lua::set_lua_value;
+use crate::config::toml::{parse_toml_value, set_toml_value};
+use crate::config::{
+    check_option, config_file, env_override, find_config_file, generate_default_config, read_config_file,
+    read_config_source, Config, ConfigOption, ConfigSource, ConfigValue,
+};
+use crate::render::render_config;
+use crate::utils::{get_config_file_path, write_file_atomically};
+
+/// Handle the configuration commands, which run without loading the configuration or the todo list.
+///
+/// # Arguments
+///
+/// `action` - The configuration action to perform.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the action succeeds, or an error if it fails.
+pub fn handle_config(action: ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Show => show_config(),
+        ConfigAction::Get { key } => get_config(&key),
+        ConfigAction::Set { key, value } => set_config(&key, &value),
+        ConfigAction::Path => {
+            let (config_path, _) = config_file()?;
+            println!("{}", config_path.display());
+            Ok(())
+        }
+        ConfigAction::Check => check_config(),
+        ConfigAction::Init { format, force } => init_config(format, force),
+    }
+}
+
+/// Helper function to load the effective settings with the source of their values.
+///
+/// # Returns
+///
+/// `Result<(PathBuf, Vec<ConfigOption>)>` - The path to the configuration file and the settings, or an error if the
+/// configuration cannot be loaded.
+fn load_options() -> Result<(PathBuf, Vec<ConfigOption>)> {
+    let (config_path, format) = config_file()?;
+    let values = read_config_file(&config_path, format)
+        .with_context(|| format!("Failed to load configuration from {:?}", config_path))?;
+    let file_keys: BTreeSet<String> = values.values.keys().cloned().collect();
+    let config = Config::from_values(values)
+        .with_context(|| format!("Failed to load configuration from {:?}", config_path))?;
+
+    let options = config.to_values()
+        .into_iter()
+        .map(|(key, value)| match env_override(key) {
+            Some(value) => ConfigOption { key, value: Some(value), source: ConfigSource::Env },
+            None if file_keys.contains(key) => ConfigOption { key, value, source: ConfigSource::File },
+            None => ConfigOption { key, value, source: ConfigSource::Default },
+        })
+        .collect();
+    Ok((config_path, options))
+}
+
+/// Show the effective settings and where their values come from.
+fn show_config() -> Result<()> {
+    let (config_path, options) = load_options()?;
+    render_config(&config_path, &options);
+    Ok(())
+}
+
+/// Print the effective value of a setting, printing strings without quotes so they can be used in scripts.
+///
+/// # Arguments
+///
+/// `key` - The key of the setting.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the setting exists, or an error if it is unknown or the configuration cannot be loaded.
+fn get_config(key: &str) -> Result<()> {
+    let (_, options) = load_options()?;
+    let option = options.into_iter()
+        .find(|option| option.key == key)
+        .ok_or_else(|| anyhow!("Unknown configuration key '{}'", key))?;
+
+    match option.value {
+        Some(ConfigValue::String(value)) => println!("{}", value),
+        Some(value) => println!("{}", value.to_toml()),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Change a setting in the configuration file, keeping the rest of the file as it is.
+///
+/// # Arguments
+///
+/// `key` - The key of the setting.
+/// `value` - The new value as given on the command line.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the file was changed, or an error if the key or value is invalid or the file
+/// cannot be changed.
+fn set_config(key: &str, value: &str) -> Result<()> {
+    let value = parse_toml_value(value);
+    let config_path = set_config_value(key, &value)?;
+    println!("Set {} = {} in {:?}", key, value.to_toml(), config_path);
+    Ok(())
+}
+
+/// Change a setting in the configuration file, keeping the rest of the file as it is.
+///
+/// The value is checked against the type of the setting before the file is changed, and the changed file is read
+/// back to make sure it has the new value.
+///
+/// # Arguments
+///
+/// `key` - The key of the setting.
+/// `value` - The new value.
+///
+/// # Returns
+///
+/// `Result<PathBuf>` - The path to the changed configuration file, or an error if the key or value is invalid or the
+/// file cannot be changed.
+pub fn set_config_value(key: &str, value: &ConfigValue) -> Result<PathBuf> {
+    check_option(key, value.clone(), None)?;
+
+    let (config_path, format) = config_file()?;
+    let source = fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read configuration file {:?}", config_path))?;
+    let updated = match format {
+        ConfigFormat::Lua => set_lua_value(&source, key, value),
+        ConfigFormat::Toml => set_toml_value(&source, key, value),
+    }.with_context(|| format!("Failed to update configuration file {:?}", config_path))?;
+
+    let values = read_config_source(&updated, &config_path, format)
+        .with_context(|| format!("Failed to update configuration file {:?}", config_path))?;
+    if values.values.get(key) != Some(value) {
+        return Err(anyhow!("Failed to update '{}' in {:?}", key, config_path));
+    }
+
+    write_file_atomically(&config_path, updated.as_bytes(), None)
+        .with_context(|| format!("Failed to write configuration file {:?}", config_path))?;
+    Ok(config_path)
+}
+
+/// Validate the configuration file, reporting every unknown key and invalid value with its line.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the configuration file is valid, or an error listing its problems.
+fn check_config() -> Result<()> {
+    let (config_path, format) = config_file()?;
+    let values = read_config_file(&config_path, format)
+        .with_context(|| format!("Failed to load configuration from {:?}", config_path))?;
+
+    let mut options: Vec<(String, ConfigValue)> = values.values.into_iter().collect();
+    options.sort_by_key(|(key, _)| values.lines.get(key).copied().unwrap_or(usize::MAX));
+    let problems: Vec<String> = options.into_iter()
+        .filter_map(|(key, value)| check_option(&key, value, values.lines.get(&key).copied()).err())
+        .map(|error| format!("{:#}", error))
+        .collect();
+
+    if problems.is_empty() {
+        println!("{:?} is valid", config_path);
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Found {} problem(s) in {:?}:\n  {}",
+        problems.len(),
+        config_path,
+        problems.join("\n  ")
+    ))
+}
+
+/// Create a configuration file with the default settings.
+///
+/// # Arguments
+///
+/// `format` - The format of the configuration file.
+/// `force` - Whether to overwrite an existing configuration file of the same format.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the file was created, or an error if it already exists or cannot be written.
+fn init_config(format: ConfigFormat, force: bool) -> Result<()> {
+    let config_path = get_config_file_path(format)?;
+    if config_path.exists() && !force {
+        return Err(anyhow!("The configuration file {:?} already exists. Use --force to overwrite it", config_path));
+    }
+
+    fs::write(&config_path, generate_default_config(format))
+        .with_context(|| format!("Failed to write configuration file {:?}", config_path))?;
+    println!("Created the configuration file {:?}", config_path);
+
+    if let Some((active_path, _)) = find_config_file()? {
+        if active_path != config_path {
+            println!("Note: {:?} takes precedence and is loaded instead", active_path);
+        }
+    }
+    Ok(())
+}
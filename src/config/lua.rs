@@ -0,0 +1,315 @@
+use anyhow::{anyhow, Context, Result};
+use rlua::{Lua, Table, Value};
+use std::collections::BTreeMap;
+use crate::config::{ConfigValue, ConfigValues};
+use crate::commands::LuaCommands;
+use crate::hooks::Hooks;
+use std::rc::Rc;
+
+/// Read the options of a Lua configuration, which is a script defining the global `Config` table and optionally the
+/// global hook functions and `Commands` table, which are kept along with the options.
+///
+/// # Arguments
+///
+/// `source` - The Lua script.
+/// `name` - The name of the script shown in Lua errors.
+///
+/// # Returns
+///
+/// `Result<ConfigValues>` - The options with their lines, or an error if the script fails or does not define a `Config`
+/// table of supported values.
+pub fn read_lua_config(source: &str, name: &str) -> Result<ConfigValues> {
+    let lua = Rc::new(Lua::new());
+    lua.load(source).set_name(name).exec()?;
+
+    let config: Option<Table> = lua.globals().get("Config")
+        .context("The global `Config` must be a table")?;
+    let config = config.ok_or_else(|| anyhow!("The configuration does not define a global `Config` table"))?;
+
+    let values = table_to_map(config)?;
+    // Later assignments replace the lines of earlier ones, as they replace their values
+    let lines = find_config_assignments(source).into_iter()
+        .filter(|(key, _)| values.contains_key(*key))
+        .map(|(key, offset)| (key.to_string(), source[..offset].matches('\n').count() + 1))
+        .collect();
+    Ok(ConfigValues { values, lines, hooks: Hooks::new(lua.clone())?, commands: LuaCommands::new(lua)? })
+}
+
+/// Helper function to find where an option of the `Config` table is assigned.
+///
+/// The last assignment is the one whose value is in effect.
+///
+/// # Returns
+///
+/// `Option<usize>` - The byte offset of the key, or `None` if it is not assigned.
+fn find_key_offset(source: &str, key: &str) -> Option<usize> {
+    find_config_assignments(source).into_iter()
+        .rev()
+        .find(|(assigned, _)| *assigned == key)
+        .map(|(_, offset)| offset)
+}
+
+/// Helper function to find the options assigned to the `Config` table, in the order they are assigned.
+///
+/// Lua does not keep the positions of table fields, so the script is scanned for the fields of a table constructor
+/// assigned to `Config`, as in `Config = { key = value }`, and for `Config.key = value` outside of any table. Fields of
+/// nested tables, strings and comments are skipped, so `aliases = { key = "..." }` does not assign `key`.
+///
+/// # Returns
+///
+/// `Vec<(&str, usize)>` - The assigned keys with their byte offsets.
+fn find_config_assignments(source: &str) -> Vec<(&str, usize)> {
+    let bytes = source.as_bytes();
+    let is_assignment = |at: usize| bytes.get(at) == Some(&b'=') && bytes.get(at + 1) != Some(&b'=');
+    let mut assignments = Vec::new();
+    // For each open table, whether it is the table assigned to `Config`
+    let mut tables = Vec::new();
+    let mut is_config_table_next = false;
+    // The last character outside of whitespace, strings and comments, where `a` stands for any word
+    let mut previous = b';';
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'-' if bytes.get(index + 1) == Some(&b'-') => {
+                index = skip_long_bracket(bytes, index + 2)
+                    .unwrap_or_else(|| source[index..].find('\n').map_or(bytes.len(), |end| index + end));
+            }
+            b'[' if skip_long_bracket(bytes, index).is_some() => {
+                index = skip_long_bracket(bytes, index).unwrap_or(bytes.len());
+                previous = b'"';
+            }
+            quote @ (b'"' | b'\'') => {
+                index += 1;
+                while index < bytes.len() && bytes[index] != quote && bytes[index] != b'\n' {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+                index += 1;
+                previous = b'"';
+            }
+            byte if byte.is_ascii_alphanumeric() || byte == b'_' => {
+                let start = index;
+                index = skip_word(bytes, index);
+                let word = &source[start..index];
+                let next = skip_whitespace(bytes, index);
+
+                if tables.as_slice() == [true] && matches!(previous, b'{' | b',' | b';') && is_assignment(next) {
+                    assignments.push((word, start));
+                } else if tables.is_empty() && word == "Config" && !matches!(previous, b'.' | b':') {
+                    if bytes.get(next) == Some(&b'.') {
+                        let key_start = skip_whitespace(bytes, next + 1);
+                        let key_end = skip_word(bytes, key_start);
+                        if key_end > key_start && is_assignment(skip_whitespace(bytes, key_end)) {
+                            assignments.push((&source[key_start..key_end], key_start));
+                        }
+                    } else if is_assignment(next) {
+                        is_config_table_next = bytes.get(skip_whitespace(bytes, next + 1)) == Some(&b'{');
+                    }
+                }
+                previous = b'a';
+            }
+            byte => {
+                match byte {
+                    b'{' => tables.push(std::mem::take(&mut is_config_table_next)),
+                    b'}' => { tables.pop(); }
+                    _ => {}
+                }
+                if !byte.is_ascii_whitespace() {
+                    previous = byte;
+                }
+                index += 1;
+            }
+        }
+    }
+    assignments
+}
+
+/// Helper function to skip a Lua long bracket like `[[...]]` or `[==[...]==]` starting at an offset.
+///
+/// # Returns
+///
+/// `Option<usize>` - The byte offset after the closing bracket, or `None` if no long bracket starts at the offset.
+fn skip_long_bracket(bytes: &[u8], start: usize) -> Option<usize> {
+    let level = bytes.get(start + 1..)?.iter().take_while(|byte| **byte == b'=').count();
+    if bytes.get(start) != Some(&b'[') || bytes.get(start + 1 + level) != Some(&b'[') {
+        return None;
+    }
+    let closing = [b"]".as_slice(), &b"=".repeat(level), b"]"].concat();
+    let content = start + level + 2;
+    Some(bytes[content..].windows(closing.len())
+        .position(|window| window == closing)
+        .map_or(bytes.len(), |end| content + end + closing.len()))
+}
+
+/// Helper function to find the end of the Lua name or number starting at an offset
+fn skip_word(bytes: &[u8], start: usize) -> usize {
+    start + bytes[start..].iter().take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_').count()
+}
+
+/// Helper function to find the first character after the whitespace starting at an offset
+fn skip_whitespace(bytes: &[u8], start: usize) -> usize {
+    start + bytes[start.min(bytes.len())..].iter().take_while(|byte| byte.is_ascii_whitespace()).count()
+}
+
+/// Set an option in a Lua configuration, keeping the rest of the script as it is.
+///
+/// The value of an assigned option is replaced where it is last assigned, other options are assigned at the end of the
+/// script with `Config.key = value`.
+///
+/// # Arguments
+///
+/// `source` - The Lua script.
+/// `key` - The key of the option.
+/// `value` - The new value of the option.
+///
+/// # Returns
+///
+/// `Result<String>` - The changed script, or an error if the current value cannot be located.
+pub fn set_lua_value(source: &str, key: &str, value: &ConfigValue) -> Result<String> {
+    let Some(offset) = find_key_offset(source, key) else {
+        let separator = if source.is_empty() || source.ends_with('\n') { "" } else { "\n" };
+        return Ok(format!("{}{}Config.{} = {}\n", source, separator, key, value.to_lua()));
+    };
+
+    let assignment = offset + source[offset..].find('=').unwrap_or_default() + 1;
+    let value_start = assignment + (source[assignment..].len() - source[assignment..].trim_start().len());
+    let value_end = find_value_end(source, value_start)
+        .ok_or_else(|| anyhow!("Failed to locate the value of '{}'", key))?;
+
+    Ok(format!("{}{}{}", &source[..value_start], value.to_lua(), &source[value_end..]))
+}
+
+/// Helper function to find the end of the Lua value starting at an offset.
+///
+/// The value ends at the first `,`, `;`, line break, comment or closing brace outside of strings and tables.
+///
+/// # Returns
+///
+/// `Option<usize>` - The byte offset after the value, or `None` if a string or table is not closed.
+fn find_value_end(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0;
+    let mut index = start;
+    let mut end = None;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            quote @ (b'"' | b'\'') => {
+                index += 1;
+                while *bytes.get(index)? != quote {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'-' if bytes.get(index + 1) == Some(&b'-') => {
+                if depth == 0 {
+                    end = Some(index);
+                    break;
+                }
+                index += source[index..].find('\n')?;
+            }
+            b'{' => depth += 1,
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(index + 1);
+                    break;
+                }
+            }
+            b'}' | b',' | b';' | b'\n' if depth == 0 => {
+                end = Some(index);
+                break;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    if depth > 0 {
+        return None;
+    }
+    Some(source[start..end.unwrap_or(bytes.len())].trim_end().len() + start)
+}
+
+/// Helper function to convert a Lua table with string keys into configuration options
+fn table_to_map(table: Table) -> Result<BTreeMap<String, ConfigValue>> {
+    let mut values = BTreeMap::new();
+    for pair in table.pairs::<Value, Value>() {
+        let (key, value) = pair?;
+        let key = match key {
+            Value::String(key) => key.to_str()?.to_string(),
+            other => return Err(anyhow!("Configuration keys must be strings, found {}", other.type_name())),
+        };
+        let value = lua_to_config_value(value).with_context(|| format!("Invalid value for '{}'", key))?;
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+/// Helper function to convert a Lua value into a configuration value.
+///
+/// Tables whose keys are exactly `1..=n` are sequences and become lists, all other tables must have string keys.
+fn lua_to_config_value(value: Value) -> Result<ConfigValue> {
+    match value {
+        Value::Boolean(value) => Ok(ConfigValue::Bool(value)),
+        Value::Integer(value) => Ok(ConfigValue::Integer(value)),
+        Value::Number(value) => Ok(ConfigValue::Float(value)),
+        Value::String(value) => Ok(ConfigValue::String(value.to_str()?.to_string())),
+        Value::Table(table) => {
+            let len = table.raw_len();
+            if len > 0 && table.clone().pairs::<Value, Value>().count() == len {
+                let items = table.sequence_values::<Value>()
+                    .map(|item| lua_to_config_value(item?))
+                    .collect::<Result<_>>()?;
+                Ok(ConfigValue::List(items))
+            } else {
+                table_to_map(table).map(ConfigValue::Table)
+            }
+        }
+        other => Err(anyhow!("expected a boolean, number, string or table, found {}", other.type_name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::lua::{read_lua_config, set_lua_value};
+    use crate::config::ConfigValue;
+
+    #[test]
+    fn test_set_lua_value_keeps_the_script() {
+        let source = "-- My settings\nConfig = {\n    lock_timeout = 5, -- seconds\n    tags = { \"a\", \"}\" },\n    storage = \"json\"\n}\n";
+
+        let updated = set_lua_value(source, "lock_timeout", &ConfigValue::Integer(10)).unwrap();
+        assert_eq!(updated, source.replace("lock_timeout = 5,", "lock_timeout = 10,"));
+
+        let updated = set_lua_value(&updated, "tags", &ConfigValue::List(vec![ConfigValue::String("b".to_string())])).unwrap();
+        assert!(updated.contains("    tags = { \"b\" },\n"));
+
+        let updated = set_lua_value(&updated, "storage", &ConfigValue::String("sqlite".to_string())).unwrap();
+        assert!(updated.contains("    storage = \"sqlite\"\n}"));
+
+        let updated = set_lua_value(&updated, "backup_on_reset", &ConfigValue::Bool(false)).unwrap();
+        assert!(updated.starts_with("-- My settings\n") && updated.ends_with("}\nConfig.backup_on_reset = false\n"));
+
+        let updated = set_lua_value(&format!("{}Config.lock_timeout = 3\n", updated), "lock_timeout", &ConfigValue::Integer(20)).unwrap();
+        assert!(updated.contains("lock_timeout = 10,") && updated.ends_with("Config.lock_timeout = 20\n"));
+
+        let values = read_lua_config(&updated, "config.lua").unwrap();
+        assert_eq!(values.values["lock_timeout"], ConfigValue::Integer(20));
+        assert_eq!(values.values["backup_on_reset"], ConfigValue::Bool(false));
+        assert_eq!(values.lines["backup_on_reset"], 7);
+        assert_eq!(values.lines["lock_timeout"], 8);
+    }
+
+    #[test]
+    fn test_set_lua_value_skips_nested_keys() {
+        let source = "Config = {\n    aliases = { theme = \"view theme\" },\n    theme = \"mono\",\n    hooks = { [[theme = 1]], 'theme = 2' },\n}\n";
+
+        let updated = set_lua_value(source, "theme", &ConfigValue::String("dark".to_string())).unwrap();
+        assert_eq!(updated, source.replace("theme = \"mono\"", "theme = \"dark\""));
+
+        let source = "Config = { aliases = { storage = \"x\" } } -- storage = 1\nlocal storage = 2\n";
+        let updated = set_lua_value(source, "storage", &ConfigValue::String("sqlite".to_string())).unwrap();
+        assert_eq!(updated, format!("{}Config.storage = \"sqlite\"\n", source));
+        assert_eq!(read_lua_config(&updated, "config.lua").unwrap().values["storage"], ConfigValue::String("sqlite".to_string()));
+    }
+}
@@ -0,0 +1,462 @@
+mod command;
+mod lua;
+mod toml;
+mod value;
+
+use serde::Deserialize;
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cli::ConfigFormat;
+use crate::commands::LuaCommands;
+use crate::hooks::Hooks;
+use std::collections::BTreeMap;
+use crate::storage::StorageBackend;
+use crate::theme::{GlyphSet, StyleOverrides, Theme, ThemeName};
+use crate::utils::get_config_file_path;
+use crate::view::View;
+use lua::read_lua_config;
+use toml::read_toml_config;
+
+pub use command::{handle_config, set_config_value};
+pub use value::{ConfigValue, ConfigValues, FromConfigValue};
+
+/// Formats of the configuration file in the order they are looked up, the first existing file is loaded.
+///
+/// `config.toml` comes first, as `config.lua` is created with the default settings on the first run.
+const CONFIG_FORMATS: [ConfigFormat; 2] = [ConfigFormat::Toml, ConfigFormat::Lua];
+
+/// Struct representing the configuration settings
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// Determines if a backup should be created on reset
+    pub backup_on_reset: bool,
+    /// Seconds to wait for another todo command to release the todo list
+    pub lock_timeout: u64,
+    /// Backend the todo list is stored in
+    pub storage: StorageBackend,
+    /// Directory the todo list is stored in instead of the application data directory
+    pub data_dir: Option<PathBuf>,
+    /// Command lines by the name of the alias standing for them
+    pub aliases: BTreeMap<String, String>,
+    /// Built-in theme the todo list is rendered with
+    pub theme: ThemeName,
+    /// Set of symbols the todo list is rendered with
+    pub glyph_set: GlyphSet,
+    /// Changes to the colors, glyphs and title of the theme
+    pub style: StyleOverrides,
+    /// Determines if words, phrases and regular expressions in filter queries match case-sensitively
+    pub filter_case_sensitive: bool,
+    /// Saved filter queries by their name
+    pub views: BTreeMap<String, View>,
+    /// Lua functions called on todo lifecycle events
+    #[serde(skip)]
+    pub hooks: Hooks,
+    /// Commands defined as Lua functions
+    #[serde(skip)]
+    pub commands: LuaCommands,
+}
+
+impl Default for Config {
+    /// Provides the default configuration settings
+    fn default() -> Self {
+        Config {
+            backup_on_reset: true,
+            lock_timeout: 5,
+            storage: StorageBackend::Json,
+            data_dir: None,
+            aliases: BTreeMap::new(),
+            theme: ThemeName::Default,
+            glyph_set: GlyphSet::Auto,
+            style: StyleOverrides::default(),
+            filter_case_sensitive: false,
+            views: BTreeMap::new(),
+            hooks: Hooks::default(),
+            commands: LuaCommands::default(),
+            // TODO: Verbose -> No auto list after every command
+        }
+    }
+}
+
+impl Config {
+    /// Build the configuration from the options read from a configuration file.
+    ///
+    /// Missing options keep their default value. Both configuration formats are read into the same options, so
+    /// unknown keys and values of the wrong type produce the same errors for either format.
+    ///
+    /// # Arguments
+    ///
+    /// `values` - The options read from the configuration file.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Config>` - The configuration, or an error naming the key and line of the first option with a value of
+    /// the wrong type or the first unknown key.
+    pub fn from_values(mut values: ConfigValues) -> Result<Config> {
+        let default = Config::default();
+        let config = Config {
+            backup_on_reset: get_config_value!(values, "backup_on_reset", default.backup_on_reset),
+            lock_timeout: get_config_value!(values, "lock_timeout", default.lock_timeout),
+            storage: get_config_value!(values, "storage", default.storage),
+            data_dir: get_config_value!(values, "data_dir", default.data_dir),
+            aliases: get_config_value!(values, "aliases", default.aliases),
+            theme: get_config_value!(values, "theme", default.theme),
+            glyph_set: get_config_value!(values, "glyph_set", default.glyph_set),
+            style: get_config_value!(values, "style", default.style),
+            filter_case_sensitive: get_config_value!(values, "filter_case_sensitive", default.filter_case_sensitive),
+            views: get_config_value!(values, "views", default.views),
+            hooks: std::mem::take(&mut values.hooks),
+            commands: std::mem::take(&mut values.commands),
+        };
+
+        values.ensure_empty()?;
+        Ok(config)
+    }
+
+    /// The settings of the configuration with their values, in the order they are documented.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<(&'static str, Option<ConfigValue>)>` - The key and value of every setting, where settings that are not set
+    /// have no value.
+    pub fn to_values(&self) -> Vec<(&'static str, Option<ConfigValue>)> {
+        vec![
+            ("backup_on_reset", Some(self.backup_on_reset.into())),
+            ("lock_timeout", Some(self.lock_timeout.into())),
+            ("storage", Some(self.storage.to_string().into())),
+            ("data_dir", self.data_dir.as_ref().map(|dir| dir.display().to_string().into())),
+            ("aliases", Some(self.aliases.clone().into())),
+            ("theme", Some(self.theme.to_string().into())),
+            ("glyph_set", Some(self.glyph_set.to_string().into())),
+            ("style", Some(self.style.clone().into())),
+            ("filter_case_sensitive", Some(self.filter_case_sensitive.into())),
+            ("views", Some(self.views.clone().into())),
+        ]
+    }
+
+    /// The theme the todo list is rendered with, which is the selected built-in theme and glyph set with the style
+    /// overrides
+    pub fn theme(&self) -> Theme {
+        Theme::new(self.theme, self.glyph_set, &self.style)
+    }
+
+    /// Check that a command which is not built in is defined in `config.lua`.
+    ///
+    /// # Returns
+    ///
+    /// `Result<()>` - Returns `Ok(())` if the command is defined, or an error naming the commands and aliases defined
+    /// in the configuration otherwise.
+    pub fn check_command(&self, name: &str) -> Result<()> {
+        if self.commands.contains(name) {
+            return Ok(());
+        }
+        let available = self.commands.names().into_iter()
+            .chain(self.aliases.keys().cloned())
+            .collect::<Vec<_>>();
+        if available.is_empty() {
+            return Err(anyhow!("Unknown command '{}'. Run todo --help to see all commands", name));
+        }
+        Err(anyhow!("Unknown command '{}'. Commands defined in the configuration: {}", name, available.join(", ")))
+    }
+}
+
+/// Enum representing where the effective value of a setting comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigSource {
+    /// The setting is not in the configuration file
+    Default,
+    /// The setting is in the configuration file
+    File,
+    /// The setting is overridden by an environment variable
+    Env,
+}
+
+impl fmt::Display for ConfigSource {
+    /// Formats the source as shown by `todo config show`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File => write!(f, "file"),
+            ConfigSource::Env => write!(f, "env"),
+        }
+    }
+}
+
+/// Struct representing the effective value of a setting
+#[derive(Debug)]
+pub struct ConfigOption {
+    /// The key of the setting
+    pub key: &'static str,
+    /// The effective value, or `None` if the setting is not set
+    pub value: Option<ConfigValue>,
+    /// Where the effective value comes from
+    pub source: ConfigSource,
+}
+
+/// Get the value of a setting that is overridden by an environment variable.
+///
+/// # Returns
+///
+/// `Option<ConfigValue>` - The value of the environment variable overriding the setting, or `None` if it is not set.
+fn env_override(key: &str) -> Option<ConfigValue> {
+    let variable = match key {
+        "data_dir" => "TODO_DIR",
+        _ => return None,
+    };
+    env::var(variable).ok().filter(|value| !value.is_empty()).map(ConfigValue::String)
+}
+
+/// Find the configuration file to load.
+///
+/// # Returns
+///
+/// `Result<Option<(PathBuf, ConfigFormat)>>` - The path and format of the first existing configuration file, or `None`
+/// if there is none.
+fn find_config_file() -> Result<Option<(PathBuf, ConfigFormat)>> {
+    for format in CONFIG_FORMATS {
+        let path = get_config_file_path(format)?;
+        if path.exists() {
+            return Ok(Some((path, format)));
+        }
+    }
+    Ok(None)
+}
+
+/// Get the configuration file in use, which is the first existing one.
+///
+/// If neither configuration file exists, it creates `config.lua` with default values.
+///
+/// # Returns
+///
+/// `Result<(PathBuf, ConfigFormat)>` - The path and format of the configuration file, or an error if the default
+/// configuration file cannot be written.
+fn config_file() -> Result<(PathBuf, ConfigFormat)> {
+    if let Some(found) = find_config_file()? {
+        return Ok(found);
+    }
+
+    let config_path = get_config_file_path(ConfigFormat::Lua)?;
+    fs::write(&config_path, generate_default_config(ConfigFormat::Lua))
+        .context("Failed to write default config.lua")?;
+    Ok((config_path, ConfigFormat::Lua))
+}
+
+/// Read the options of a configuration file.
+///
+/// # Arguments
+///
+/// `config_path` - The path to the configuration file.
+/// `format` - The format of the configuration file.
+///
+/// # Returns
+///
+/// `Result<ConfigValues>` - The options with their lines, or an error if the file cannot be read or parsed.
+fn read_config_file(config_path: &Path, format: ConfigFormat) -> Result<ConfigValues> {
+    let source = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read configuration file {:?}", config_path))?;
+    read_config_source(&source, config_path, format)
+}
+
+/// Read the options of the content of a configuration file.
+///
+/// # Arguments
+///
+/// `source` - The content of the configuration file.
+/// `config_path` - The path to the configuration file, used as the name of Lua scripts.
+/// `format` - The format of the configuration file.
+///
+/// # Returns
+///
+/// `Result<ConfigValues>` - The options with their lines, or an error if the content cannot be parsed.
+fn read_config_source(source: &str, config_path: &Path, format: ConfigFormat) -> Result<ConfigValues> {
+    match format {
+        ConfigFormat::Lua => read_lua_config(source, &config_path.display().to_string()),
+        ConfigFormat::Toml => read_toml_config(source),
+    }
+}
+
+/// Loads the configuration from `config.toml` or `config.lua`, whichever exists, preferring `config.toml`.
+/// 
+/// If neither configuration file exists, it creates `config.lua` with default values.
+/// 
+/// # Returns
+/// 
+/// `Result<Config>` - Returns a `Config` struct populated with the settings from the configuration file, 
+/// or an error if the file could not be loaded or parsed.
+///
+/// # Errors
+///
+/// This function will return an error if the configuration file cannot be read or parsed, or if it contains unknown
+/// keys or values of the wrong type.
+pub fn load_config() -> Result<Config> {
+    let (config_path, format) = config_file()?;
+    read_config_file(&config_path, format)
+        .and_then(Config::from_values)
+        .with_context(|| format!("Failed to load configuration from {:?}", config_path))
+}
+
+/// Check a single option of a configuration file.
+///
+/// # Arguments
+///
+/// `key` - The key of the option.
+/// `value` - The value of the option.
+/// `line` - The line the option is defined on, if known.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the option is a setting and its value has the right type, or an error naming the
+/// key and line otherwise.
+fn check_option(key: &str, value: ConfigValue, line: Option<usize>) -> Result<()> {
+    let mut values = ConfigValues::default();
+    values.values.insert(key.to_string(), value);
+    if let Some(line) = line {
+        values.lines.insert(key.to_string(), line);
+    }
+    Config::from_values(values).map(|_| ())
+}
+
+/// Generate a configuration file with the default settings.
+///
+/// # Arguments
+///
+/// `format` - The format of the configuration file.
+///
+/// # Returns
+///
+/// `String` - The content of the configuration file.
+fn generate_default_config(format: ConfigFormat) -> String {
+    let default_config = Config::default();
+    let storage = default_config.storage.to_string();
+    match format {
+        ConfigFormat::Lua => generate_lua_config!(
+            "backup_on_reset" => default_config.backup_on_reset,
+            "lock_timeout" => default_config.lock_timeout,
+            "storage" => storage,
+        ),
+        ConfigFormat::Toml => generate_toml_config!(
+            "backup_on_reset" => default_config.backup_on_reset,
+            "lock_timeout" => default_config.lock_timeout,
+            "storage" => storage,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::ConfigFormat;
+    use crate::config::lua::read_lua_config;
+    use crate::config::toml::read_toml_config;
+    use crate::config::{generate_default_config, Config, ConfigValue};
+    use crate::storage::StorageBackend;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_lua_and_toml_configs_are_equivalent() {
+        let lua = read_lua_config(
+            "Config = {\n    lock_timeout = 10,\n    storage = \"sqlite\",\n    data_dir = \"/tmp/todos\",\n}\n",
+            "config.lua",
+        ).unwrap();
+        let toml = read_toml_config("lock_timeout = 10\nstorage = \"sqlite\"\ndata_dir = \"/tmp/todos\"\n").unwrap();
+        assert_eq!(lua.values, toml.values);
+
+        let config = Config::from_values(toml).unwrap();
+        assert!(config.backup_on_reset);
+        assert_eq!(config.lock_timeout, 10);
+        assert_eq!(config.storage, StorageBackend::Sqlite);
+        assert_eq!(config.data_dir, Some(PathBuf::from("/tmp/todos")));
+    }
+
+    #[test]
+    fn test_default_configs_load_the_defaults() {
+        for (format, values) in [
+            (ConfigFormat::Lua, read_lua_config(&generate_default_config(ConfigFormat::Lua), "config.lua")),
+            (ConfigFormat::Toml, read_toml_config(&generate_default_config(ConfigFormat::Toml))),
+        ] {
+            let config = Config::from_values(values.unwrap()).unwrap();
+            let default = Config::default();
+            assert_eq!(config.backup_on_reset, default.backup_on_reset, "{:?}", format);
+            assert_eq!(config.lock_timeout, default.lock_timeout, "{:?}", format);
+            assert_eq!(config.storage, default.storage, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_config_errors_name_the_key_and_line() {
+        let error_of = |values| format!("{:#}", Config::from_values(values).expect_err("The configuration should be rejected"));
+
+        let lua = read_lua_config("Config = {\n    lock_timeout = \"soon\",\n}", "config.lua").unwrap();
+        let toml = read_toml_config("\nlock_timeout = \"soon\"").unwrap();
+        let expected = "Invalid value for 'lock_timeout' on line 2: expected a non-negative integer, found a string";
+        assert_eq!(error_of(lua), expected);
+        assert_eq!(error_of(toml), expected);
+
+        let lua = read_lua_config("Config = {\n    lock_timeout = \"soon\",\n    aliases = { lock_timeout = \"config show\" },\n}", "config.lua").unwrap();
+        assert_eq!(error_of(lua), expected);
+        let lua = read_lua_config("Config = {\n    theme = 5,\n    aliases = { theme = \"config get theme\" },\n}", "config.lua").unwrap();
+        assert!(error_of(lua).starts_with("Invalid value for 'theme' on line 2: "));
+
+        let lua = read_lua_config("Config = {}\nConfig.lock_timout = 5", "config.lua").unwrap();
+        let toml = read_toml_config("# Timeout\nlock_timout = 5").unwrap();
+        assert_eq!(error_of(lua), "Unknown configuration key 'lock_timout' on line 2");
+        assert_eq!(error_of(toml), "Unknown configuration key 'lock_timout' on line 2");
+
+        let lua = read_lua_config("Config = {\n    style = { colors = { tags = \"rosa\" } },\n}", "config.lua").unwrap();
+        let toml = read_toml_config("theme = \"mono\"\n\n[style.colors]\ntags = \"rosa\"").unwrap();
+        assert_eq!(error_of(lua), "Invalid value for 'style' on line 2: invalid color of 'tags': unknown color or attribute 'rosa'");
+        assert_eq!(error_of(toml), "Invalid value for 'style' on line 3: invalid color of 'tags': unknown color or attribute 'rosa'");
+    }
+
+    #[test]
+    fn test_check_command() {
+        let source = "Config = { aliases = { today = \"filter due:today\" } }\nCommands = { standup = function(list, args) end }";
+        let config = Config::from_values(read_lua_config(source, "config.lua").unwrap()).unwrap();
+
+        assert!(config.check_command("standup").is_ok());
+        let error = config.check_command("stnadup").expect_err("The misspelled command should be rejected");
+        assert_eq!(error.to_string(), "Unknown command 'stnadup'. Commands defined in the configuration: standup, today");
+        let error = Config::default().check_command("standup").expect_err("The command should be unknown");
+        assert_eq!(error.to_string(), "Unknown command 'standup'. Run todo --help to see all commands");
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let source = "Config = {\n    tags = { \"home\", \"work\" },\n    empty = {},\n    theme = { done = \"green\", width = 1.5 },\n}";
+        let mut lua = read_lua_config(source, "config.lua").unwrap();
+        let mut toml = read_toml_config("tags = [\"home\", \"work\"]\nempty = []\n\n[theme]\ndone = \"green\"\nwidth = 1.5\n").unwrap();
+
+        for values in [&mut lua, &mut toml] {
+            assert_eq!(values.take::<Vec<String>>("tags").unwrap(), Some(vec!["home".to_string(), "work".to_string()]));
+            assert_eq!(values.take::<Vec<String>>("empty").unwrap(), Some(Vec::new()));
+            let theme: BTreeMap<String, ConfigValue> = values.take("theme").unwrap().unwrap();
+            assert_eq!(theme["done"], ConfigValue::String("green".to_string()));
+            assert_eq!(theme["width"], ConfigValue::Float(1.5));
+            assert_eq!(values.take::<bool>("missing").unwrap(), None);
+        }
+
+        let mut values = read_toml_config("tags = [\"home\", 2]").unwrap();
+        let error = values.take::<Vec<String>>("tags").expect_err("A list of mixed types should be rejected");
+        assert_eq!(format!("{:#}", error), "Invalid value for 'tags' on line 1: invalid item 2: expected a string, found an integer");
+    }
+
+    #[test]
+    fn test_generated_values_round_trip() {
+        let theme = BTreeMap::from([("done".to_string(), "green"), ("in progress".to_string(), "say \"hi\"")]);
+        let lua = generate_lua_config!("tags" => vec!["a", "b"], "theme" => theme.clone(), "ratio" => 0.5);
+        let toml = generate_toml_config!("tags" => vec!["a", "b"], "theme" => theme, "ratio" => 0.5);
+
+        let lua = read_lua_config(&lua, "config.lua").unwrap();
+        let toml = read_toml_config(&toml).unwrap();
+        assert_eq!(lua.values, toml.values);
+        assert_eq!(lua.values["theme"], ConfigValue::Table(BTreeMap::from([
+            ("done".to_string(), ConfigValue::String("green".to_string())),
+            ("in progress".to_string(), ConfigValue::String("say \"hi\"".to_string())),
+        ])));
+        assert!(read_toml_config("lock_timeout = ").is_err());
+        assert_eq!(ConfigValue::Table(BTreeMap::new()).to_lua(), "{}");
+        assert_eq!(ConfigValue::List(Vec::new()).to_toml(), "[]");
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use toml_edit::{DocumentMut, ImDocument, Item, Value};
+use crate::config::{ConfigValue, ConfigValues};
+
+/// Read the options of a TOML configuration, whose top-level keys are the options.
+///
+/// # Arguments
+///
+/// `source` - The TOML document.
+///
+/// # Returns
+///
+/// `Result<ConfigValues>` - The options with their lines, or an error pointing at the line and column of invalid TOML.
+pub fn read_toml_config(source: &str) -> Result<ConfigValues> {
+    let document = ImDocument::parse(source)?;
+    let root = document.as_table();
+
+    let mut config = ConfigValues::default();
+    for (key, item) in root.iter() {
+        config.values.insert(key.to_string(), item_to_config_value(item)?);
+        if let Some(span) = root.key(key).and_then(|key| key.span()) {
+            config.lines.insert(key.to_string(), source[..span.start].matches('\n').count() + 1);
+        }
+    }
+    Ok(config)
+}
+
+/// Set an option in a TOML configuration, keeping the rest of the document, including comments, as it is.
+///
+/// # Arguments
+///
+/// `source` - The TOML document.
+/// `key` - The key of the option.
+/// `value` - The new value of the option.
+///
+/// # Returns
+///
+/// `Result<String>` - The changed document, or an error if the document is invalid.
+pub fn set_toml_value(source: &str, key: &str, value: &ConfigValue) -> Result<String> {
+    let mut document: DocumentMut = source.parse()?;
+    let mut new_value: Value = value.to_toml().parse()?;
+
+    if let Some(Item::Value(current)) = document.get(key) {
+        *new_value.decor_mut() = current.decor().clone();
+    }
+    document[key] = Item::Value(new_value);
+    Ok(document.to_string())
+}
+
+/// Parse a value given on the command line.
+///
+/// The value is read as a TOML value, so `true`, `10` and `["home", "work"]` keep their types. Anything else, like
+/// `sqlite` or `~/todos`, is taken as a string.
+///
+/// # Returns
+///
+/// `ConfigValue` - The parsed value.
+pub fn parse_toml_value(text: &str) -> ConfigValue {
+    match text.parse::<Value>() {
+        Ok(value) => value_to_config_value(&value),
+        Err(_) => ConfigValue::String(text.to_string()),
+    }
+}
+
+/// Helper function to convert a TOML item into a configuration value
+fn item_to_config_value(item: &Item) -> Result<ConfigValue> {
+    match item {
+        Item::Value(value) => Ok(value_to_config_value(value)),
+        Item::Table(table) => table.iter()
+            .map(|(key, item)| Ok((key.to_string(), item_to_config_value(item)?)))
+            .collect::<Result<_>>()
+            .map(ConfigValue::Table),
+        Item::ArrayOfTables(tables) => tables.iter()
+            .map(|table| item_to_config_value(&Item::Table(table.clone())))
+            .collect::<Result<_>>()
+            .map(ConfigValue::List),
+        Item::None => Err(anyhow!("Empty configuration item")),
+    }
+}
+
+/// Helper function to convert a TOML value into a configuration value.
+///
+/// Dates and times have no configuration type of their own and are read as strings.
+fn value_to_config_value(value: &Value) -> ConfigValue {
+    match value {
+        Value::String(value) => ConfigValue::String(value.value().clone()),
+        Value::Integer(value) => ConfigValue::Integer(*value.value()),
+        Value::Float(value) => ConfigValue::Float(*value.value()),
+        Value::Boolean(value) => ConfigValue::Bool(*value.value()),
+        Value::Datetime(value) => ConfigValue::String(value.value().to_string()),
+        Value::Array(array) => ConfigValue::List(array.iter().map(value_to_config_value).collect()),
+        Value::InlineTable(table) => ConfigValue::Table(
+            table.iter().map(|(key, value)| (key.to_string(), value_to_config_value(value))).collect()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::toml::{parse_toml_value, set_toml_value};
+    use crate::config::ConfigValue;
+
+    #[test]
+    fn test_set_toml_value_keeps_comments() {
+        let source = "# My settings\nlock_timeout = 5 # seconds\n\n[theme]\ndone = \"green\"\n";
+
+        let updated = set_toml_value(source, "lock_timeout", &ConfigValue::Integer(10)).unwrap();
+        assert_eq!(updated, "# My settings\nlock_timeout = 10 # seconds\n\n[theme]\ndone = \"green\"\n");
+
+        let updated = set_toml_value(&updated, "storage", &ConfigValue::String("sqlite".to_string())).unwrap();
+        assert!(updated.contains("storage = \"sqlite\"\n"));
+        assert!(updated.contains("[theme]\ndone = \"green\"\n"));
+    }
+
+    #[test]
+    fn test_parse_toml_value() {
+        assert_eq!(parse_toml_value("true"), ConfigValue::Bool(true));
+        assert_eq!(parse_toml_value("10"), ConfigValue::Integer(10));
+        assert_eq!(parse_toml_value("sqlite"), ConfigValue::String("sqlite".to_string()));
+        assert_eq!(parse_toml_value("~/todos"), ConfigValue::String("~/todos".to_string()));
+        assert_eq!(parse_toml_value("[\"a\"]"), ConfigValue::List(vec![ConfigValue::String("a".to_string())]));
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use crate::commands::LuaCommands;
+use crate::hooks::Hooks;
+use crate::storage::StorageBackend;
+use crate::theme::{GlyphSet, StyleOverrides, ThemeName};
+use crate::view::View;
+
+/// Enum representing a value read from a configuration file, independent of the format of the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<ConfigValue>),
+    Table(BTreeMap<String, ConfigValue>),
+}
+
+impl ConfigValue {
+    /// Format the value as Lua source
+    pub fn to_lua(&self) -> String {
+        match self {
+            ConfigValue::Table(table) => {
+                let fields: Vec<String> = table.iter()
+                    .map(|(key, value)| format!("{} = {}", lua_key(key), value.to_lua()))
+                    .collect();
+                braced(&fields)
+            }
+            ConfigValue::List(items) => {
+                let items: Vec<String> = items.iter().map(ConfigValue::to_lua).collect();
+                braced(&items)
+            }
+            scalar => scalar.to_toml(),
+        }
+    }
+
+    /// Format the value as TOML source, where tables are written inline
+    pub fn to_toml(&self) -> String {
+        match self {
+            ConfigValue::Bool(value) => value.to_string(),
+            ConfigValue::Integer(value) => value.to_string(),
+            ConfigValue::Float(value) => format!("{:?}", value),
+            ConfigValue::String(value) => quote(value),
+            ConfigValue::List(items) => {
+                let items: Vec<String> = items.iter().map(ConfigValue::to_toml).collect();
+                format!("[{}]", items.join(", "))
+            }
+            ConfigValue::Table(table) => {
+                let fields: Vec<String> = table.iter()
+                    .map(|(key, value)| format!("{} = {}", toml_key(key), value.to_toml()))
+                    .collect();
+                braced(&fields)
+            }
+        }
+    }
+
+    /// Describes the type of the value, as used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConfigValue::Bool(_) => "a boolean",
+            ConfigValue::Integer(_) => "an integer",
+            ConfigValue::Float(_) => "a number",
+            ConfigValue::String(_) => "a string",
+            ConfigValue::List(_) => "a list",
+            ConfigValue::Table(_) => "a table",
+        }
+    }
+}
+
+/// Helper function to format the fields of a table, or the items of a Lua list, in braces
+fn braced(items: &[String]) -> String {
+    if items.is_empty() {
+        return "{}".to_string();
+    }
+    format!("{{ {} }}", items.join(", "))
+}
+
+/// Helper function to quote a string, using the escapes Lua and TOML have in common
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Helper function to check if a key can be written without quotes in both Lua and TOML
+fn is_bare_key(key: &str) -> bool {
+    key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Helper function to format the key of a Lua table field
+fn lua_key(key: &str) -> String {
+    if is_bare_key(key) { key.to_string() } else { format!("[{}]", quote(key)) }
+}
+
+/// Helper function to format a TOML key
+fn toml_key(key: &str) -> String {
+    if is_bare_key(key) { key.to_string() } else { quote(key) }
+}
+
+macro_rules! impl_from_for_config_value {
+    ($($type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$type> for ConfigValue {
+                fn from(value: $type) -> Self {
+                    ConfigValue::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_for_config_value!(
+    bool => Bool,
+    i32 => Integer,
+    i64 => Integer,
+    f64 => Float,
+    String => String,
+    &str => String,
+);
+
+impl From<u64> for ConfigValue {
+    fn from(value: u64) -> Self {
+        ConfigValue::Integer(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl<T: Into<ConfigValue>> From<Vec<T>> for ConfigValue {
+    fn from(items: Vec<T>) -> Self {
+        ConfigValue::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<ConfigValue>> From<BTreeMap<String, T>> for ConfigValue {
+    fn from(table: BTreeMap<String, T>) -> Self {
+        ConfigValue::Table(table.into_iter().map(|(key, value)| (key, value.into())).collect())
+    }
+}
+
+/// Struct representing the options read from a configuration file, along with the lines they are defined on
+#[derive(Debug, Default)]
+pub struct ConfigValues {
+    /// The options by key
+    pub values: BTreeMap<String, ConfigValue>,
+    /// The line each option is defined on, for the options whose line is known
+    pub lines: BTreeMap<String, usize>,
+    /// The hooks defined by a Lua configuration
+    pub hooks: Hooks,
+    /// The commands defined by a Lua configuration
+    pub commands: LuaCommands,
+}
+
+impl ConfigValues {
+    /// Take an option out of the configuration, converting it to the type of the setting.
+    ///
+    /// # Arguments
+    ///
+    /// `key` - The key of the option.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<T>>` - The value of the option, `None` if it is not set, or an error naming the key and its line
+    /// if the value has the wrong type.
+    pub fn take<T: FromConfigValue>(&mut self, key: &str) -> Result<Option<T>> {
+        let Some(value) = self.values.remove(key) else {
+            return Ok(None);
+        };
+        T::from_config_value(value)
+            .map(Some)
+            .with_context(|| format!("Invalid value for '{}'{}", key, self.line_suffix(key)))
+    }
+
+    /// Fail if any option was not taken, as it is not a known setting.
+    ///
+    /// # Returns
+    ///
+    /// `Result<()>` - Returns `Ok(())` if all options were taken, or an error naming the first unknown key and its line.
+    pub fn ensure_empty(&self) -> Result<()> {
+        let unknown = self.values.keys()
+            .min_by_key(|key| self.lines.get(*key).copied().unwrap_or(usize::MAX));
+        match unknown {
+            Some(key) => Err(anyhow!("Unknown configuration key '{}'{}", key, self.line_suffix(key))),
+            None => Ok(()),
+        }
+    }
+
+    /// Helper function to describe the line of an option in error messages
+    fn line_suffix(&self, key: &str) -> String {
+        self.lines.get(key).map(|line| format!(" on line {}", line)).unwrap_or_default()
+    }
+}
+
+/// Trait for the types of configuration options, converting a value read from any configuration format
+pub trait FromConfigValue: Sized {
+    /// Convert a configuration value.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Self>` - The converted value, or an error describing the expected and the found value.
+    fn from_config_value(value: ConfigValue) -> Result<Self>;
+}
+
+/// Helper function to create the error for a value of the wrong type
+fn mismatch(expected: &str, found: &ConfigValue) -> anyhow::Error {
+    anyhow!("expected {}, found {}", expected, found.type_name())
+}
+
+impl FromConfigValue for ConfigValue {
+    /// Options of any type are kept as they are
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl FromConfigValue for bool {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Bool(value) => Ok(value),
+            other => Err(mismatch("a boolean", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for i64 {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Integer(value) => Ok(value),
+            other => Err(mismatch("an integer", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for u64 {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Integer(value) => {
+                u64::try_from(value).map_err(|_| anyhow!("expected a non-negative integer, found {}", value))
+            }
+            other => Err(mismatch("a non-negative integer", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for f64 {
+    /// Integers are accepted as numbers, as `5` is a valid number in both Lua and TOML
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Float(value) => Ok(value),
+            ConfigValue::Integer(value) => Ok(value as f64),
+            other => Err(mismatch("a number", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for String {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::String(value) => Ok(value),
+            other => Err(mismatch("a string", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for PathBuf {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        String::from_config_value(value).map(PathBuf::from)
+    }
+}
+
+impl FromConfigValue for StorageBackend {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        String::from_config_value(value)?.parse()
+    }
+}
+
+impl FromConfigValue for ThemeName {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        String::from_config_value(value)?.parse()
+    }
+}
+
+impl FromConfigValue for GlyphSet {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        String::from_config_value(value)?.parse()
+    }
+}
+
+impl FromConfigValue for StyleOverrides {
+    /// An empty Lua table `{}` is accepted as no overrides
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Table(table) => StyleOverrides::from_table(table),
+            ConfigValue::List(items) if items.is_empty() => Ok(StyleOverrides::default()),
+            other => Err(mismatch("a table", &other)),
+        }
+    }
+}
+
+impl FromConfigValue for View {
+    /// A string is accepted as a view of just the query, and the query is checked so it fails when it is loaded
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        let view = match value {
+            ConfigValue::String(query) => View { query, sort: None, format: None },
+            ConfigValue::Table(table) => View::from_table(table)?,
+            other => return Err(mismatch("a table or a string", &other)),
+        };
+        view.parse_query(false, Local::now().date_naive())?;
+        Ok(view)
+    }
+}
+
+impl<T: FromConfigValue> FromConfigValue for Vec<T> {
+    /// An empty Lua table `{}` is accepted as an empty list
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::List(items) => items.into_iter()
+                .enumerate()
+                .map(|(index, item)| T::from_config_value(item).with_context(|| format!("invalid item {}", index + 1)))
+                .collect(),
+            ConfigValue::Table(table) if table.is_empty() => Ok(Vec::new()),
+            other => Err(mismatch("a list", &other)),
+        }
+    }
+}
+
+impl<T: FromConfigValue> FromConfigValue for BTreeMap<String, T> {
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        match value {
+            ConfigValue::Table(table) => table.into_iter()
+                .map(|(key, value)| {
+                    let value = T::from_config_value(value).with_context(|| format!("invalid value for '{}'", key))?;
+                    Ok((key, value))
+                })
+                .collect(),
+            ConfigValue::List(items) if items.is_empty() => Ok(BTreeMap::new()),
+            other => Err(mismatch("a table", &other)),
+        }
+    }
+}
+
+impl<T: FromConfigValue> FromConfigValue for Option<T> {
+    /// Options that are unset by default become set once they are given
+    fn from_config_value(value: ConfigValue) -> Result<Self> {
+        T::from_config_value(value).map(Some)
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+/// Enum representing the due date conditions that can be used in filters
+#[derive(Debug, PartialEq)]
+pub enum DueFilter {
+    /// Due exactly today
+    Today,
+    /// Due before today and not yet complete
+    Overdue,
+    /// Due within the next seven days, including today
+    Week,
+    /// Due on a specific date
+    On(NaiveDate),
+}
+
+impl DueFilter {
+    /// Parses the value of a `due:` filter token.
+    ///
+    /// Besides `today`, `overdue` and `week`, every value accepted by `parse_due_date` matches
+    /// todos due on exactly that date.
+    pub fn parse(value: &str, today: NaiveDate) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "today" => Ok(DueFilter::Today),
+            "overdue" => Ok(DueFilter::Overdue),
+            "week" => Ok(DueFilter::Week),
+            _ => parse_due_date(value, today).map(DueFilter::On),
+        }
+    }
+
+    /// Checks whether a todo with the given due date and completion status matches the filter.
+    pub fn matches(&self, due: Option<NaiveDate>, is_complete: bool, today: NaiveDate) -> bool {
+        let Some(due) = due else {
+            return false;
+        };
+
+        match self {
+            DueFilter::Today => due == today,
+            DueFilter::Overdue => due < today && !is_complete,
+            DueFilter::Week => due >= today && due < today + Days::new(7),
+            DueFilter::On(date) => due == *date,
+        }
+    }
+}
+
+/// Parses a due date given in natural language relative to `today`.
+///
+/// Supported formats are:
+///
+/// * `today` and `tomorrow`
+/// * Weekday names such as `fri` or `friday`, resolving to the next occurrence after today
+/// * Relative offsets such as `+3d`, `+2w` or `+1m`
+/// * Absolute dates in the format `YYYY-MM-DD`
+///
+/// # Arguments
+///
+/// `value` - The due date as written by the user.
+/// `today` - The date relative values are resolved against.
+///
+/// # Returns
+///
+/// `Result<NaiveDate>` - The resolved due date, or an error if the value is not understood.
+pub fn parse_due_date(value: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let value = value.trim().to_lowercase();
+
+    let date = match value.as_str() {
+        "today" => Some(today),
+        "tomorrow" | "tmr" => today.succ_opt(),
+        _ => {
+            if let Some(offset) = value.strip_prefix('+') {
+                parse_offset(offset, today)
+            } else if let Some(weekday) = parse_weekday(&value) {
+                let days_ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+                let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+                today.checked_add_days(Days::new(days_ahead.into()))
+            } else {
+                NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok()
+            }
+        }
+    };
+
+    date.ok_or_else(|| anyhow!("Invalid due date '{}'. Use e.g. today, tomorrow, fri, +3d or 2026-11-03", value))
+}
+
+/// Parses a relative offset such as `3d`, `2w` or `1m` and applies it to `today`.
+fn parse_offset(offset: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let unit = offset.chars().last()?;
+    let amount: u32 = offset[..offset.len() - unit.len_utf8()].parse().ok()?;
+
+    match unit {
+        'd' => today.checked_add_days(Days::new(amount.into())),
+        'w' => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+        'm' => today.checked_add_months(Months::new(amount)),
+        _ => None,
+    }
+}
+
+/// Parses a full or abbreviated weekday name.
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    let weekday = match value {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::due::{parse_due_date, DueFilter};
+    use chrono::NaiveDate;
+
+    // 2026-10-16 is a Friday
+    fn friday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 16).unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_relative_due_dates() {
+        assert_eq!(parse_due_date("today", friday()).unwrap(), friday());
+        assert_eq!(parse_due_date("tomorrow", friday()).unwrap(), date(2026, 10, 17));
+        assert_eq!(parse_due_date("+3d", friday()).unwrap(), date(2026, 10, 19));
+        assert_eq!(parse_due_date("+2w", friday()).unwrap(), date(2026, 10, 30));
+        assert_eq!(parse_due_date("+1m", friday()).unwrap(), date(2026, 11, 16));
+    }
+
+    #[test]
+    fn test_parse_weekday_due_dates() {
+        assert_eq!(parse_due_date("mon", friday()).unwrap(), date(2026, 10, 19));
+        assert_eq!(parse_due_date("Friday", friday()).unwrap(), date(2026, 10, 23));
+    }
+
+    #[test]
+    fn test_parse_absolute_and_invalid_due_dates() {
+        assert_eq!(parse_due_date("2026-11-03", friday()).unwrap(), date(2026, 11, 3));
+        assert!(parse_due_date("someday", friday()).is_err());
+        assert!(parse_due_date("+3x", friday()).is_err());
+        assert!(parse_due_date("2026-13-01", friday()).is_err());
+    }
+
+    #[test]
+    fn test_due_filter_matches() {
+        let today = friday();
+        let overdue = DueFilter::parse("overdue", today).unwrap();
+        let week = DueFilter::parse("week", today).unwrap();
+
+        assert!(overdue.matches(Some(date(2026, 10, 15)), false, today));
+        assert!(!overdue.matches(Some(date(2026, 10, 15)), true, today));
+        assert!(week.matches(Some(date(2026, 10, 22)), false, today));
+        assert!(!week.matches(Some(date(2026, 10, 23)), false, today));
+        assert!(!DueFilter::Today.matches(None, false, today));
+    }
+}
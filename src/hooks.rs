@@ -0,0 +1,353 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use rlua::{Function, Lua, Table, Value};
+use std::fmt;
+use std::rc::Rc;
+use crate::due::parse_due_date;
+use crate::parse::normalize_tag;
+use crate::todo::{Priority, Todo};
+
+/// Enum representing the events of the todo lifecycle that call a hook
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    /// A todo is about to be added
+    Add,
+    /// A todo is about to be marked as done
+    Done,
+    /// A todo is about to be removed
+    Remove,
+    /// The todo list is about to be reset
+    Reset,
+}
+
+impl HookEvent {
+    /// All events, in the order they are documented
+    pub const ALL: [HookEvent; 4] = [HookEvent::Add, HookEvent::Done, HookEvent::Remove, HookEvent::Reset];
+
+    /// The name of the Lua function called on the event
+    pub fn function_name(&self) -> &'static str {
+        match self {
+            HookEvent::Add => "on_add",
+            HookEvent::Done => "on_done",
+            HookEvent::Remove => "on_remove",
+            HookEvent::Reset => "on_reset",
+        }
+    }
+}
+
+/// The name of the Lua function formatting the lines of the todo list
+const FORMAT_FUNCTION: &str = "format_todo";
+
+/// Struct holding the Lua functions a Lua configuration defines to be called on todo lifecycle events, and the
+/// function formatting the lines of the todo list.
+///
+/// Hooks are the global functions `on_add(todo)`, `on_done(todo)`, `on_remove(todo)` and `on_reset()`. A hook receives
+/// a table with the fields of the todo, and changes to its `description`, `tags`, `notes`, `due`, `priority` and
+/// `is_starred` fields are applied to the todo. A hook vetoes the action by raising an error with `error("message")`.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    lua: Option<Rc<Lua>>,
+}
+
+impl fmt::Debug for Hooks {
+    /// Formats the hooks as the names of the defined functions
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.defined().iter().map(|event| event.function_name())).finish()
+    }
+}
+
+impl Hooks {
+    /// Keep the hooks defined by a Lua configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `lua` - The Lua state the configuration was run in.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Hooks>` - The hooks, or an error if a hook is defined as something other than a function.
+    pub fn new(lua: Rc<Lua>) -> Result<Self> {
+        let names = HookEvent::ALL.iter().map(HookEvent::function_name).chain([FORMAT_FUNCTION]);
+        for name in names {
+            match lua.globals().get::<_, Value>(name)? {
+                Value::Nil | Value::Function(_) => {}
+                other => return Err(anyhow!("The hook '{}' must be a function, found {}", name, other.type_name())),
+            }
+        }
+        Ok(Hooks { lua: Some(lua) })
+    }
+
+    /// The events a hook is defined for
+    pub fn defined(&self) -> Vec<HookEvent> {
+        HookEvent::ALL.into_iter()
+            .filter(|event| self.function(*event).is_some())
+            .collect()
+    }
+
+    /// Helper function to get the function of an event, if it is defined
+    fn function(&self, event: HookEvent) -> Option<Function<'_>> {
+        self.lua.as_ref()?.globals().get::<_, Option<Function>>(event.function_name()).ok().flatten()
+    }
+
+    /// Call the hook of a todo event, applying the changes the hook makes to the todo.
+    ///
+    /// # Arguments
+    ///
+    /// `event` - The event, which must not be `HookEvent::Reset`.
+    /// `todo` - The todo the event happens to.
+    /// `board` - The name of the board the todo is on, which the hook can read.
+    ///
+    /// # Returns
+    ///
+    /// `Result<()>` - Returns `Ok(())` if there is no hook or it accepts the action, or an error with the message of the
+    /// hook if it vetoes the action or makes invalid changes.
+    pub fn on_todo(&self, event: HookEvent, todo: &mut Todo, board: &str) -> Result<()> {
+        let (Some(lua), Some(function)) = (self.lua.as_ref(), self.function(event)) else {
+            return Ok(());
+        };
+
+        let name = event.function_name();
+        let table = todo_to_table(lua, todo, board)
+            .with_context(|| format!("Failed to prepare the todo for the '{}' hook", name))?;
+        function.call::<_, ()>(table.clone())
+            .map_err(|e| veto_error(name, e))?;
+        apply_table(&table, todo)
+            .with_context(|| format!("The '{}' hook changed todo {} in an invalid way", name, todo.id))
+    }
+
+    /// Call the hook that runs before the todo list is reset.
+    ///
+    /// # Returns
+    ///
+    /// `Result<()>` - Returns `Ok(())` if there is no hook or it accepts the reset, or an error with the message of the
+    /// hook if it vetoes the reset.
+    pub fn on_reset(&self) -> Result<()> {
+        match self.function(HookEvent::Reset) {
+            Some(function) => function.call::<_, ()>(()).map_err(|e| veto_error(HookEvent::Reset.function_name(), e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Call the function formatting the line of a todo in the todo list.
+    ///
+    /// # Arguments
+    ///
+    /// `todo` - The todo to format.
+    /// `board` - The name of the board the todo is on.
+    /// `depth` - The depth of the todo in the tree of subtasks, which is `0` for top-level todos.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Option<String>>` - The line of the todo, `None` if there is no format function or it returns `nil`, or
+    /// an error if the function fails or returns something other than a string.
+    pub fn format_todo(&self, todo: &Todo, board: &str, depth: usize) -> Result<Option<String>> {
+        let Some(lua) = self.lua.as_ref() else {
+            return Ok(None);
+        };
+        let Some(function) = lua.globals().get::<_, Option<Function>>(FORMAT_FUNCTION).ok().flatten() else {
+            return Ok(None);
+        };
+
+        let table = todo_to_table(lua, todo, board)?;
+        table.set("depth", depth)?;
+        match function.call::<_, Value>(table) {
+            Ok(Value::Nil) => Ok(None),
+            Ok(Value::String(line)) => Ok(Some(line.to_str()?.to_string())),
+            Ok(other) => Err(anyhow!("The '{}' function must return a string or nil, found {}", FORMAT_FUNCTION, other.type_name())),
+            Err(e) => Err(anyhow!("The '{}' function failed: {}", FORMAT_FUNCTION, lua_error_message(&e))),
+        }
+    }
+}
+
+/// Helper function to turn the error raised by a hook into the error shown to the user
+fn veto_error(name: &str, error: rlua::Error) -> anyhow::Error {
+    anyhow!("The '{}' hook stopped the action: {}", name, lua_error_message(&error))
+}
+
+/// Get the message of an error raised in Lua.
+///
+/// Lua appends a stack traceback to errors raised in functions, which is left out.
+pub fn lua_error_message(error: &rlua::Error) -> String {
+    let message = match error {
+        rlua::Error::RuntimeError(message) => message.clone(),
+        rlua::Error::CallbackError { cause, .. } => lua_error_message(cause),
+        error => error.to_string(),
+    };
+    message.lines().next().unwrap_or_default().to_string()
+}
+
+/// Create the Lua view of a todo, as passed to hooks and commands.
+///
+/// Dates are strings in the `YYYY-MM-DD` format, where `created_on` and `completed_on` are local dates.
+pub fn todo_to_table<'lua>(lua: &'lua Lua, todo: &Todo, board: &str) -> rlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("id", todo.id)?;
+    table.set("description", todo.desc.as_str())?;
+    table.set("is_complete", todo.is_complete)?;
+    table.set("is_starred", todo.is_starred)?;
+    table.set("tags", lua.create_sequence_from(todo.tags.iter().map(String::as_str))?)?;
+    table.set("notes", todo.notes.as_str())?;
+    table.set("due", todo.due.map(|due| due.format("%Y-%m-%d").to_string()))?;
+    table.set("priority", priority_name(todo.priority))?;
+    table.set("parent", todo.parent)?;
+    table.set("created_on", local_date(todo.timestamp))?;
+    table.set("completed_on", todo.completed_at.map(local_date))?;
+    table.set("board", board)?;
+    Ok(table)
+}
+
+/// Apply the changeable fields of the Lua view back to the todo.
+///
+/// The fields `description`, `tags`, `notes`, `due`, `priority` and `is_starred` can be changed. All fields are
+/// validated before the todo is changed.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the todo was changed, or an error naming the first invalid field.
+pub fn apply_table(table: &Table, todo: &mut Todo) -> Result<()> {
+    let desc: String = get_field(table, "description", "a string")?;
+    if desc.trim().is_empty() {
+        return Err(anyhow!("The description must not be empty"));
+    }
+
+    let tags: Vec<String> = get_field(table, "tags", "a list of strings")?;
+    let mut normalized_tags = Vec::new();
+    for tag in tags {
+        let normalized = normalize_tag(&tag).ok_or_else(|| anyhow!("Invalid tag '{}'", tag))?;
+        if !normalized_tags.contains(&normalized) {
+            normalized_tags.push(normalized);
+        }
+    }
+
+    let due: Option<String> = get_field(table, "due", "a date string or nil")?;
+    let due = due.map(|due| parse_due_date(&due, Local::now().date_naive())).transpose()?;
+
+    let priority: Option<String> = get_field(table, "priority", "a priority name or nil")?;
+    let priority = match priority {
+        Some(name) => Priority::from_str(&name, true).map_err(|_| anyhow!("Unknown priority '{}'", name))?,
+        None => Priority::default(),
+    };
+
+    let notes: String = get_field(table, "notes", "a string")?;
+    let is_starred: bool = get_field(table, "is_starred", "a boolean")?;
+
+    todo.desc = desc;
+    todo.tags = normalized_tags;
+    todo.notes = notes;
+    todo.due = due;
+    todo.priority = priority;
+    todo.is_starred = is_starred;
+    Ok(())
+}
+
+/// Helper function to format a timestamp as a local date
+fn local_date(timestamp: DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+/// Helper function to read a field of the Lua view with an error naming the field
+fn get_field<'lua, T: rlua::FromLua<'lua>>(table: &Table<'lua>, field: &str, expected: &str) -> Result<T> {
+    table.get(field).map_err(|_| anyhow!("The field '{}' must be {}", field, expected))
+}
+
+/// Helper function to get the name of a priority as accepted on the command line
+fn priority_name(priority: Priority) -> String {
+    priority.to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rlua::Lua;
+    use std::rc::Rc;
+    use crate::hooks::{HookEvent, Hooks};
+    use crate::todo::{Priority, Todo};
+
+    fn hooks(source: &str) -> Hooks {
+        let lua = Lua::new();
+        lua.load(source).exec().expect("Failed to run the hooks");
+        Hooks::new(Rc::new(lua)).expect("Failed to keep the hooks")
+    }
+
+    fn todo(desc: &str) -> Todo {
+        Todo::new(1, desc)
+    }
+
+    #[test]
+    fn test_hook_modifies_todo() {
+        let hooks = hooks(r##"
+            function on_add(todo)
+                if todo.board == "work" then
+                    table.insert(todo.tags, "#work")
+                end
+                todo.description = todo.description .. "!"
+                todo.priority = "high"
+                todo.due = "2030-01-02"
+            end
+        "##);
+        assert_eq!(hooks.defined(), vec![HookEvent::Add]);
+
+        let mut todo = todo("Write report");
+        hooks.on_todo(HookEvent::Add, &mut todo, "work").unwrap();
+        assert_eq!(todo.desc, "Write report!");
+        assert_eq!(todo.tags, vec!["work".to_string()]);
+        assert_eq!(todo.priority, Priority::High);
+        assert_eq!(todo.due, NaiveDate::from_ymd_opt(2030, 1, 2));
+
+        hooks.on_todo(HookEvent::Done, &mut todo, "work").unwrap();
+        hooks.on_reset().unwrap();
+    }
+
+    #[test]
+    fn test_hook_vetoes_action() {
+        let hooks = hooks(r#"
+            function on_remove(todo)
+                if todo.is_starred then
+                    error("Starred todos cannot be removed", 0)
+                end
+            end
+            function on_done(todo) todo.tags = "urgent" end
+            function on_reset() error("Resetting is disabled") end
+        "#);
+
+        let mut todo = todo("Keep me");
+        hooks.on_todo(HookEvent::Remove, &mut todo, "default").unwrap();
+        todo.is_starred = true;
+        let error = hooks.on_todo(HookEvent::Remove, &mut todo, "default").expect_err("The hook should veto");
+        assert_eq!(error.to_string(), "The 'on_remove' hook stopped the action: Starred todos cannot be removed");
+
+        let error = hooks.on_todo(HookEvent::Done, &mut todo, "default").expect_err("The change should be rejected");
+        assert_eq!(format!("{:#}", error), "The 'on_done' hook changed todo 1 in an invalid way: The field 'tags' must be a list of strings");
+
+        let error = hooks.on_reset().expect_err("The hook should veto");
+        assert!(error.to_string().ends_with("Resetting is disabled"));
+        let lua = Lua::new();
+        lua.load("on_add = 5").exec().unwrap();
+        assert!(Hooks::new(Rc::new(lua)).is_err());
+    }
+
+    #[test]
+    fn test_format_todo() {
+        let mut todo = todo("Write report");
+        assert_eq!(hooks("").format_todo(&todo, "default", 0).unwrap(), None);
+
+        let hooks = hooks(r#"
+            function format_todo(todo)
+                if todo.is_starred then return nil end
+                if todo.is_complete then return {} end
+                return string.rep("  ", todo.depth) .. todo.id .. " " .. todo.description .. " @" .. todo.board
+            end
+        "#);
+        assert_eq!(hooks.format_todo(&todo, "work", 1).unwrap(), Some("  1 Write report @work".to_string()));
+
+        todo.is_starred = true;
+        assert_eq!(hooks.format_todo(&todo, "work", 0).unwrap(), None);
+        todo.is_starred = false;
+        todo.is_complete = true;
+        let error = hooks.format_todo(&todo, "work", 0).expect_err("A table is not a line");
+        assert_eq!(error.to_string(), "The 'format_todo' function must return a string or nil, found table");
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::patch::Patch;
+use crate::utils::write_file_atomically;
+
+/// Maximum number of operations that can be undone
+pub const MAX_JOURNAL_ENTRIES: usize = 50;
+
+/// The command line of the running command as it is recorded in the journal, e.g. `done 1 2`
+pub fn command_line() -> String {
+    std::env::args().skip(1).collect::<Vec<_>>().join(" ")
+}
+
+/// Struct representing a single recorded operation
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    /// The time the operation was recorded
+    pub timestamp: DateTime<Utc>,
+    /// The command line of the operation, e.g. `done 1 2`
+    pub command: String,
+    /// The changed parts of the todo list in the state the operation is reverted or reapplied to
+    pub patch: Patch,
+}
+
+/// Struct representing the journal of operations that can be undone and redone
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    undo: Vec<JournalEntry>,
+    redo: Vec<JournalEntry>,
+    #[serde(skip)]
+    is_modified: bool,
+}
+
+impl Journal {
+    /// Record an operation together with the state of the parts of the todo list it changed before it.
+    ///
+    /// Recording a new operation discards all operations that could be redone.
+    pub fn record(&mut self, command: String, patch: Patch) {
+        self.undo.push(JournalEntry { timestamp: Utc::now(), command, patch });
+        if self.undo.len() > MAX_JOURNAL_ENTRIES {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.is_modified = true;
+    }
+
+    /// Take the last operation to undo, remembering the state `current` reads for its patch to redo it.
+    ///
+    /// Returns the entry holding the state before the operation, or `None` if there is nothing to undo.
+    pub fn undo(&mut self, current: impl FnOnce(&Patch) -> Patch) -> Option<JournalEntry> {
+        let entry = self.undo.pop()?;
+        self.redo.push(JournalEntry { patch: current(&entry.patch), ..entry.clone() });
+        self.is_modified = true;
+        Some(entry)
+    }
+
+    /// Take the last undone operation to redo, remembering the state `current` reads for its patch to undo it again.
+    ///
+    /// Returns the entry holding the state after the operation, or `None` if there is nothing to redo.
+    pub fn redo(&mut self, current: impl FnOnce(&Patch) -> Patch) -> Option<JournalEntry> {
+        let entry = self.redo.pop()?;
+        self.undo.push(JournalEntry { patch: current(&entry.patch), ..entry.clone() });
+        self.is_modified = true;
+        Some(entry)
+    }
+
+    /// The operations that can be undone, oldest first
+    pub fn undo_entries(&self) -> &[JournalEntry] {
+        &self.undo
+    }
+
+    /// The operations that can be redone, oldest first
+    pub fn redo_entries(&self) -> &[JournalEntry] {
+        &self.redo
+    }
+
+    /// Load a journal from a file, returning an empty journal if the file does not exist.
+    ///
+    /// A journal that cannot be parsed, e.g. one written by an older version, is replaced by an empty journal, so
+    /// only the history is lost instead of every command failing.
+    pub fn load_from_file(file_path: &Path) -> Result<Self> {
+        if !file_path.exists() {
+            return Ok(Journal::default());
+        }
+
+        let content = fs::read_to_string(file_path).context("Failed to read journal file")?;
+        match serde_json::from_str(&content) {
+            Ok(journal) => Ok(journal),
+            Err(e) => {
+                eprintln!("The operation journal {:?} cannot be read and is started anew: {}", file_path, e);
+                Ok(Journal::default())
+            }
+        }
+    }
+
+    /// Save the journal to a file if it has been modified since it was loaded
+    pub fn save_to_file(&self, file_path: &Path) -> Result<()> {
+        if !self.is_modified {
+            return Ok(());
+        }
+
+        let content = serde_json::to_string(&self).context("Failed to serialize journal")?;
+        write_file_atomically(file_path, content.as_bytes(), None).context("Failed to write journal file")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::{Journal, MAX_JOURNAL_ENTRIES};
+    use crate::patch::Patch;
+
+    // Helper function to create a patch that is told apart by its active board
+    fn patch(name: &str) -> Patch {
+        Patch { active_board: Some(name.to_string()), ..Patch::default() }
+    }
+
+    #[test]
+    fn test_undo_and_redo_swap_patches() {
+        let mut journal = Journal::default();
+        journal.record("add First".to_string(), patch("0"));
+        journal.record("add Second".to_string(), patch("1"));
+
+        let entry = journal.undo(|_| patch("2")).expect("Nothing to undo");
+        assert_eq!(entry.command, "add Second");
+        assert!(entry.patch == patch("1"));
+
+        let entry = journal.redo(|redo| {
+            assert!(*redo == patch("2"));
+            patch("1")
+        }).expect("Nothing to redo");
+        assert!(entry.patch == patch("2"));
+        assert!(journal.redo(|_| patch("2")).is_none());
+    }
+
+    #[test]
+    fn test_record_clears_redo_and_limits_entries() {
+        let mut journal = Journal::default();
+        for i in 0..=MAX_JOURNAL_ENTRIES {
+            journal.record(format!("add {}", i), patch(&i.to_string()));
+        }
+        assert_eq!(journal.undo_entries().len(), MAX_JOURNAL_ENTRIES);
+        assert_eq!(journal.undo_entries()[0].command, "add 1");
+
+        journal.undo(|_| patch("current"));
+        journal.record("rm 1".to_string(), patch("current"));
+        assert!(journal.redo_entries().is_empty());
+    }
+}
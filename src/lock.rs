@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::utils::get_lock_file_path;
+
+/// Interval in which a held lock is checked again while waiting for it
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Struct representing an exclusive advisory lock on a todo file.
+///
+/// The lock is held on a separate `<file>.lock` file next to the todo file, so the todo file itself
+/// can still be replaced atomically. It is released when the `FileLock` is dropped.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Acquire the lock for a todo file, waiting up to `timeout` for other invocations to release it.
+    ///
+    /// # Arguments
+    ///
+    /// `todo_file_path` - The path to the todo file to lock.
+    /// `timeout` - How long to wait for the lock before giving up.
+    ///
+    /// # Returns
+    ///
+    /// `Result<FileLock>` - The acquired lock, or an error if the lock file cannot be opened or the lock
+    /// is still held by another invocation after `timeout`.
+    pub fn acquire(todo_file_path: &Path, timeout: Duration) -> Result<Self> {
+        let lock_path = get_lock_file_path(todo_file_path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open lock file {:?}", lock_path))?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(FileLock { file }),
+                Err(TryLockError::WouldBlock) if start.elapsed() < timeout => thread::sleep(LOCK_RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    return Err(anyhow!(
+                        "The todo list is in use by another todo command. Gave up waiting for the lock {:?} after {}s",
+                        lock_path,
+                        timeout.as_secs_f32()
+                    ));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {:?}", lock_path));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    /// Releases the lock
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock::FileLock;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let file_path = Path::new("test_lock_todos.json");
+
+        let lock = FileLock::acquire(file_path, Duration::ZERO).expect("Failed to acquire lock");
+        assert!(FileLock::acquire(file_path, Duration::from_millis(100)).is_err(), "Lock should be held");
+
+        drop(lock);
+        let lock = FileLock::acquire(file_path, Duration::ZERO).expect("Failed to acquire released lock");
+
+        drop(lock);
+        std::fs::remove_file("test_lock_todos.json.lock").expect("Failed to delete test file");
+    }
+}
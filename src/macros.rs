@@ -3,7 +3,8 @@
 /// # Parameters
 ///
 /// - `$key`: The key for the configuration option, which should be a string.
-/// - `$value`: The value for the configuration option, which will be formatted into the Lua string. 
+/// - `$value`: The value for the configuration option, which is converted into a `ConfigValue` and formatted as Lua,
+///   so booleans, numbers, strings, lists (`Vec`) and tables (`BTreeMap`) can be used.
 ///
 /// # Example
 ///
@@ -12,8 +13,9 @@
 /// let lua_config = generate_lua_config!(
 ///     "first_option" => true,
 ///     "second_option" => 42,
+///     "third_option" => vec!["a", "b"],
 /// );
-/// # assert_eq!(lua_config, "config = {\n    first_option = true,\n    second_option = 42,\n}\n");
+/// # assert_eq!(lua_config, "Config = {\n    first_option = true,\n    second_option = 42,\n    third_option = { \"a\", \"b\" },\n}\n");
 /// ```
 #[macro_export]
 macro_rules! generate_lua_config {
@@ -21,7 +23,7 @@ macro_rules! generate_lua_config {
         {
             let mut lua_config = String::from("Config = {\n");
             $(
-                lua_config.push_str(&format!("    {} = {},\n", $key, $value));
+                lua_config.push_str(&format!("    {} = {},\n", $key, $crate::config::ConfigValue::from($value).to_lua()));
             )*
             lua_config.push_str("}\n");
             lua_config
@@ -29,32 +31,62 @@ macro_rules! generate_lua_config {
     };
 }
 
-/// Retrieves a value from a Lua configuration table with a fallback to a default value.
+/// Generates a TOML configuration string from a list of key-value pairs.
 ///
-/// This macro attempts to get a value associated with a key from a Lua table. 
-/// If the key does not exist or if the retrieval fails, it returns a provided default value.
+/// # Parameters
+///
+/// - `$key`: The key for the configuration option, which should be a string.
+/// - `$value`: The value for the configuration option, which is converted into a `ConfigValue` and formatted as TOML,
+///   so booleans, numbers, strings, lists (`Vec`) and tables (`BTreeMap`) can be used.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate todo;
+/// let toml_config = generate_toml_config!(
+///     "first_option" => true,
+///     "second_option" => 42,
+///     "third_option" => vec!["a", "b"],
+/// );
+/// # assert_eq!(toml_config, "first_option = true\nsecond_option = 42\nthird_option = [\"a\", \"b\"]\n");
+/// ```
+#[macro_export]
+macro_rules! generate_toml_config {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        {
+            let mut toml_config = String::new();
+            $(
+                toml_config.push_str(&format!("{} = {}\n", $key, $crate::config::ConfigValue::from($value).to_toml()));
+            )*
+            toml_config
+        }
+    };
+}
+
+/// Takes a value out of the options read from a configuration file with a fallback to a default value.
+///
+/// This macro removes the value associated with a key from the options, so that the remaining options are the
+/// unknown ones. If the key does not exist, it returns a provided default value. The type of the value is inferred
+/// from the default value and converted with `FromConfigValue`, so a value of the wrong type is an error naming the key
+/// and its line instead of falling back to the default.
 ///
 /// # Parameters
 ///
-/// - `$config`: The Lua table from which to retrieve the value.
+/// - `$values`: The `ConfigValues` read from the configuration file.
 /// - `$key`: The key for the configuration option.
-/// - `$default`: The default value to return if the key does not exist or retrieval fails.
+/// - `$default`: The default value to return if the key does not exist.
 ///
 /// # Example
 ///
 /// ```
 /// # #[macro_use] extern crate todo;
-/// let first_option: bool = get_config_value!(config, "first_option", Config::default().first_option);
-/// let second_option: bool = get_config_value!(config, "second_option", Config::default().second_option);
+/// let first_option: bool = get_config_value!(values, "first_option", Config::default().first_option);
+/// let second_option: Vec<String> = get_config_value!(values, "second_option", Config::default().second_option);
 /// ```
 #[macro_export]
 macro_rules! get_config_value {
-    ($config:expr, $key:expr, $default:expr) => {
-        if let Ok(value) = $config.get::<_, Option<bool>>($key) {
-            value.unwrap_or($default)
-        } else {
-            $default
-        }
+    ($values:expr, $key:expr, $default:expr) => {
+        $values.take($key)?.unwrap_or($default)
     };
 }
 
@@ -5,49 +5,133 @@ mod todo;
 mod utils;
 mod config;
 mod render;
+mod parse;
+mod due;
+mod recurrence;
+mod journal;
+mod patch;
+mod lock;
+mod migration;
+mod storage;
+mod hooks;
+mod commands;
+mod theme;
+mod output;
+mod query;
+mod view;
+mod search;
+mod partial;
 
 use clap::Parser;
-use anyhow::Result;
-use cli::Cli;
+use anyhow::{Context, Result};
+use std::env;
+use std::time::Duration;
+use cli::{expand_aliases, Cli, Pattern};
+use config::{handle_config, load_config};
+use lock::FileLock;
+use output::NoResults;
+use partial::{is_partial, run_partial};
+use render::set_color_choice;
+use storage::{new_project_storage, open_storage};
 use todo::TodoList;
-use utils::get_todo_file_path;
 
 /// Run the main application logic.
 ///
 /// This function handles the following tasks:
-/// - Parses the command-line arguments using the `Cli` struct.
-/// - Loads the todo list from the specified file.
-/// - Executes the appropriate command based on the CLI input or lists todos by default.
-/// - Saves the updated todo list back to the file.
+/// - Parses the command-line arguments using the `Cli` struct and applies the `--color` choice to all output.
+/// - Handles the configuration commands, which need neither the configuration nor the todo list.
+/// - Loads the configuration from `config.toml` or `config.lua` and expands aliases defined in it, along with their options.
+/// - Rejects commands that are neither built in nor defined in the configuration.
+/// - Opens the storage backend selected in the configuration, in the file given by `--file` or the project todo file if any.
+/// - Locks the todo list against concurrent invocations until it is saved.
+/// - Runs commands touching todos by ID on these todos only.
+/// - Loads the todo list from the storage.
+/// - Executes the appropriate command based on the CLI input or lists todos by default, in the output format given by `--format`.
+/// - Saves the updated todo list back to the storage if the command changed it.
 ///
 /// # Returns
 ///
 /// `Result<()>` - Returns `Ok(())` if the operations complete successfully, or an error if any step fails.
 fn run() -> Result<()> {
     let args = Cli::parse();
+    set_color_choice(args.color);
 
-    let file_path = get_todo_file_path()?;
+    let args = match args.pattern {
+        Some(Pattern::Config { action }) => return handle_config(action),
+        pattern => Cli { pattern, ..args },
+    };
 
-    let mut todo_list = TodoList::load_from_file(&file_path)?;
+    let config = load_config()?;
+
+    // Aliases are defined in the configuration, so they may stand for configuration commands as well
+    let args = expand_aliases(args, &config.aliases)?;
+    set_color_choice(args.color);
+    let pattern = match args.pattern {
+        Some(Pattern::Config { action }) => return handle_config(action),
+        pattern => pattern,
+    };
+
+    // Unknown commands fail before the todo list is locked and loaded
+    if let Some(Pattern::Custom(command)) = &pattern {
+        config.check_command(&command[0])?;
+    }
+
+    // `init` creates the project todo list in the current directory by saving an empty list to it
+    let mut storage = match pattern {
+        Some(Pattern::Init) => new_project_storage(&config, &env::current_dir().context("Failed to determine the current directory")?)?,
+        _ => open_storage(&config, args.file, args.global)?,
+    };
+
+    let _lock = FileLock::acquire(storage.companion_path(), Duration::from_secs(config.lock_timeout))?;
+
+    // Commands touching todos by ID only read and write these todos instead of the whole todo list
+    let pattern = match pattern {
+        Some(pattern) if is_partial(&pattern) => return run_partial(storage.as_mut(), config, pattern, args.format),
+        pattern => pattern,
+    };
+
+    let mut todo_list = TodoList::load(storage.as_mut(), config)?;
+
+    todo_list.output_format = args.format;
 
     // Handle CLI commands or default to listing todos
-    match args.pattern {
-        Some(pattern) => todo_list.handle_cli(pattern)?,
-        None => todo_list.list(),
+    let outcome = match pattern {
+        Some(pattern) => todo_list.handle_cli(pattern),
+        None => {
+            todo_list.list();
+            Ok(())
+        }
+    };
+
+    // A filtering or search command that found nothing still saves the todo list, which may have been migrated when it was loaded
+    if outcome.as_ref().is_err_and(|e| !e.is::<NoResults>()) {
+        return outcome;
+    }
+    if todo_list.needs_save() {
+        todo_list.save(storage.as_mut())?;
     }
-   
-    todo_list.save_to_file(&file_path)?;
 
-    Ok(())
+    outcome
 }
 
+/// Exit code of a command that failed
+const EXIT_ERROR: i32 = 1;
+
+/// Exit code of a filtering or search command that found nothing, which differs from the exit code clap uses for
+/// invalid arguments
+const EXIT_NO_RESULTS: i32 = 3;
+
 /// The main entry point of the application.
 ///
 /// This function calls the `run` function and handles any errors that occur by printing them
-/// to stderr and exiting the process with a non-zero status code.
+/// to stderr and exiting the process with `EXIT_ERROR`. Filtering and search commands that found
+/// nothing exit with `EXIT_NO_RESULTS` instead.
 fn main() {
     if let Err(e) = run() {
+        if e.is::<NoResults>() {
+            std::process::exit(EXIT_NO_RESULTS);
+        }
         eprintln!("Error: {:#}", e);
-        std::process::exit(1);
+        std::process::exit(EXIT_ERROR);
     }
 }
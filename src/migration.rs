@@ -0,0 +1,131 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Version of the todo list format written by this build
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Migrations upgrading the todo list format, where the migration at index `i` upgrades version `i` to `i + 1`.
+///
+/// Files written before the format was versioned have version 0. As some of them were written after later
+/// changes to the format, every migration leaves todo lists that already have its shape untouched.
+const MIGRATIONS: [fn(&mut Value); SCHEMA_VERSION as usize] = [
+    migrate_to_boards,
+    migrate_starred_priority,
+];
+
+/// Read the schema version of a todo list in its JSON representation.
+///
+/// # Returns
+///
+/// `Result<u32>` - The schema version, 0 if the todo list is not versioned, or an error if the version is not a number.
+pub fn schema_version(value: &Value) -> Result<u32> {
+    match value.get("version") {
+        None => Ok(0),
+        Some(version) => version.as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow!("Invalid todo list version: {}", version)),
+    }
+}
+
+/// Upgrade a todo list in its JSON representation to the current schema version.
+///
+/// # Arguments
+///
+/// `value` - The JSON representation of the todo list, which is upgraded in place.
+///
+/// # Returns
+///
+/// `Result<u32>` - The schema version the todo list had before, or an error if it was written by a newer version
+/// of the application.
+pub fn migrate_todo_list(value: &mut Value) -> Result<u32> {
+    let version = schema_version(value)?;
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "The todo list has version {}, but this version of todo only understands up to version {}. Please update todo.",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(value);
+    }
+
+    if let Value::Object(todo_list) = value {
+        todo_list.insert("version".to_string(), json!(SCHEMA_VERSION));
+    }
+    Ok(version)
+}
+
+/// Version 1: Move the single list of files written before boards existed into the default board.
+fn migrate_to_boards(value: &mut Value) {
+    if value.get("boards").is_none() {
+        let board = value.take();
+        *value = json!({
+            "boards": { "default": board },
+            "active_board": "default",
+        });
+    }
+}
+
+/// Version 2: Give starred todos from files written before priorities existed a high priority.
+///
+/// Only todos without a `priority` field are touched, so priorities set afterwards are kept.
+fn migrate_starred_priority(value: &mut Value) {
+    let Some(Value::Object(boards)) = value.get_mut("boards") else {
+        return;
+    };
+
+    for board in boards.values_mut() {
+        let Some(Value::Array(todos)) = board.get_mut("todos") else {
+            continue;
+        };
+        for todo in todos.iter_mut().filter_map(|todo| todo.as_object_mut()) {
+            let is_starred = todo.get("is_starred").and_then(|v| v.as_bool()).unwrap_or(false);
+            if is_starred && !todo.contains_key("priority") {
+                todo.insert("priority".to_string(), json!("high"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migration::{migrate_todo_list, schema_version, SCHEMA_VERSION};
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_unversioned_todo_list() {
+        let mut value = json!({
+            "todos": [{"id": 1, "desc": "Starred", "is_starred": true}],
+            "available_ids": [],
+        });
+
+        assert_eq!(migrate_todo_list(&mut value).unwrap(), 0);
+        assert_eq!(schema_version(&value).unwrap(), SCHEMA_VERSION);
+        assert_eq!(value["active_board"], "default");
+        assert_eq!(value["boards"]["default"]["todos"][0]["priority"], "high");
+    }
+
+    #[test]
+    fn test_migrate_keeps_current_todo_list() {
+        let current = json!({
+            "version": SCHEMA_VERSION,
+            "boards": {"home": {"todos": [{"id": 1, "is_starred": true, "priority": "low"}], "available_ids": []}},
+            "active_board": "home",
+        });
+        let mut value = current.clone();
+
+        assert_eq!(migrate_todo_list(&mut value).unwrap(), SCHEMA_VERSION);
+        assert_eq!(value, current);
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_todo_list() {
+        let mut value = json!({"version": SCHEMA_VERSION + 1, "boards": {}, "active_board": "default"});
+
+        let error = migrate_todo_list(&mut value).expect_err("Newer todo lists should be rejected");
+        assert!(error.to_string().contains("Please update todo"));
+        assert!(schema_version(&json!({"version": "two"})).is_err());
+    }
+}
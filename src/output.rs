@@ -0,0 +1,329 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use crate::cli::OutputFormat;
+use crate::todo::{Priority, Todo};
+use crate::view::{value_enum_name, View};
+
+/// Error of a filtering or search command that found nothing.
+///
+/// It is not an error for the user, so it is not printed, but it exits with its own exit code so scripts can tell an
+/// empty result from a failure.
+#[derive(Debug)]
+pub struct NoResults;
+
+impl fmt::Display for NoResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No results")
+    }
+}
+
+impl std::error::Error for NoResults {}
+
+/// Struct representing a todo in machine-readable output.
+///
+/// The fields are part of the stable output schema: fields may be added, but are never renamed or removed.
+#[derive(Serialize)]
+struct TodoRecord<'a> {
+    id: usize,
+    board: &'a str,
+    description: &'a str,
+    is_complete: bool,
+    is_starred: bool,
+    priority: Option<&'static str>,
+    tags: &'a [String],
+    due: Option<String>,
+    recurrence: Option<String>,
+    parent: Option<usize>,
+    notes: &'a str,
+    created_at: String,
+    completed_at: Option<String>,
+}
+
+impl<'a> TodoRecord<'a> {
+    /// The columns of CSV and TSV output, in the order of the fields
+    const COLUMNS: [&'static str; 13] = [
+        "id", "board", "description", "is_complete", "is_starred", "priority", "tags", "due", "recurrence", "parent",
+        "notes", "created_at", "completed_at",
+    ];
+
+    fn new(todo: &'a Todo, board: &'a str) -> Self {
+        TodoRecord {
+            id: todo.id,
+            board,
+            description: &todo.desc,
+            is_complete: todo.is_complete,
+            is_starred: todo.is_starred,
+            priority: priority_name(todo.priority),
+            tags: &todo.tags,
+            due: todo.due.map(|due| due.format("%Y-%m-%d").to_string()),
+            recurrence: todo.recurrence.as_ref().map(ToString::to_string),
+            parent: todo.parent,
+            notes: &todo.notes,
+            created_at: format_timestamp(todo.timestamp),
+            completed_at: todo.completed_at.map(format_timestamp),
+        }
+    }
+
+    /// The fields as CSV and TSV cells, where lists are separated by spaces and missing values are empty
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.board.to_string(),
+            self.description.to_string(),
+            self.is_complete.to_string(),
+            self.is_starred.to_string(),
+            self.priority.unwrap_or_default().to_string(),
+            self.tags.join(" "),
+            self.due.clone().unwrap_or_default(),
+            self.recurrence.clone().unwrap_or_default(),
+            self.parent.map(|parent| parent.to_string()).unwrap_or_default(),
+            self.notes.to_string(),
+            self.created_at.clone(),
+            self.completed_at.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Struct representing a backup in machine-readable output
+#[derive(Serialize)]
+pub struct BackupRecord {
+    /// The timestamp identifying the backup, as passed to `backup open`
+    pub timestamp: String,
+    /// The time the backup was created
+    pub created_at: String,
+    /// The path to the backup file
+    pub path: PathBuf,
+}
+
+impl BackupRecord {
+    /// The columns of CSV and TSV output, in the order of the fields
+    const COLUMNS: [&'static str; 3] = ["timestamp", "created_at", "path"];
+
+    /// Create the record of a backup from its timestamp in seconds since the Unix epoch
+    pub fn new(timestamp: u64, path: PathBuf) -> Self {
+        let created_at = i64::try_from(timestamp).ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(format_timestamp)
+            .unwrap_or_default();
+        BackupRecord { timestamp: timestamp.to_string(), created_at, path }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.timestamp.clone(), self.created_at.clone(), self.path.display().to_string()]
+    }
+}
+
+/// Struct representing a view in machine-readable output
+#[derive(Serialize)]
+pub struct ViewRecord {
+    /// The name the view is run by
+    pub name: String,
+    /// The filter query of the view
+    pub query: String,
+    /// The order the view lists its todos in, if it has one
+    pub sort: Option<String>,
+    /// The format the view lists its todos in, if it has one
+    pub format: Option<String>,
+    /// The number of todos of the active board the view shows
+    pub count: usize,
+}
+
+impl ViewRecord {
+    /// The columns of CSV and TSV output, in the order of the fields
+    const COLUMNS: [&'static str; 5] = ["name", "query", "sort", "format", "count"];
+
+    /// Create the record of a view showing `count` todos
+    pub fn new(name: &str, view: &View, count: usize) -> Self {
+        ViewRecord {
+            name: name.to_string(),
+            query: view.query.clone(),
+            sort: view.sort.as_ref().map(value_enum_name),
+            format: view.format.as_ref().map(value_enum_name),
+            count,
+        }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.query.clone(),
+            self.sort.clone().unwrap_or_default(),
+            self.format.clone().unwrap_or_default(),
+            self.count.to_string(),
+        ]
+    }
+}
+
+/// Format todos in a machine-readable format.
+///
+/// # Arguments
+///
+/// `todos` - The todos to format.
+/// `board` - The name of the board the todos are on.
+/// `format` - The output format, which must not be `OutputFormat::Text`.
+///
+/// # Returns
+///
+/// `String` - A JSON array of todo objects, or CSV or TSV with a header row and one row per todo.
+pub fn format_todos(todos: &[&Todo], board: &str, format: OutputFormat) -> String {
+    let records: Vec<TodoRecord> = todos.iter().map(|todo| TodoRecord::new(todo, board)).collect();
+    let rows: Vec<Vec<String>> = records.iter().map(TodoRecord::cells).collect();
+    format_records(&records, &TodoRecord::COLUMNS, &rows, format)
+}
+
+/// Format backups in a machine-readable format.
+///
+/// # Returns
+///
+/// `String` - A JSON array of backup objects, or CSV or TSV with a header row and one row per backup.
+pub fn format_backups(backups: &[BackupRecord], format: OutputFormat) -> String {
+    let rows: Vec<Vec<String>> = backups.iter().map(BackupRecord::cells).collect();
+    format_records(backups, &BackupRecord::COLUMNS, &rows, format)
+}
+
+/// Format views in a machine-readable format.
+///
+/// # Returns
+///
+/// `String` - A JSON array of view objects, or CSV or TSV with a header row and one row per view.
+pub fn format_views(views: &[ViewRecord], format: OutputFormat) -> String {
+    let rows: Vec<Vec<String>> = views.iter().map(ViewRecord::cells).collect();
+    format_records(views, &ViewRecord::COLUMNS, &rows, format)
+}
+
+/// Helper function to format records as JSON, or their rows as CSV or TSV
+fn format_records<T: Serialize>(records: &[T], columns: &[&str], rows: &[Vec<String>], format: OutputFormat) -> String {
+    let (separator, escape): (&str, fn(&str) -> String) = match format {
+        OutputFormat::Csv => (",", escape_csv),
+        OutputFormat::Tsv => ("\t", escape_tsv),
+        OutputFormat::Json | OutputFormat::Text => {
+            // Records only hold strings, numbers and booleans, which always serialize
+            return serde_json::to_string_pretty(records).expect("Failed to serialize records") + "\n";
+        }
+    };
+
+    let header = columns.iter().map(|column| escape(column)).collect::<Vec<_>>().join(separator);
+    let mut output = header + "\n";
+    for row in rows {
+        output.push_str(&row.iter().map(|cell| escape(cell)).collect::<Vec<_>>().join(separator));
+        output.push('\n');
+    }
+    output
+}
+
+/// Helper function to quote a CSV cell containing separators, quotes or line breaks, as described in RFC 4180
+fn escape_csv(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Helper function to escape the backslashes, tabs and line breaks of a TSV cell, which cannot be quoted
+fn escape_tsv(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Helper function to get the name of a priority as accepted on the command line, or `None` without a priority
+fn priority_name(priority: Priority) -> Option<&'static str> {
+    match priority {
+        Priority::High => Some("high"),
+        Priority::Medium => Some("medium"),
+        Priority::Low => Some("low"),
+        Priority::None => None,
+    }
+}
+
+/// Helper function to format a timestamp as RFC 3339 in UTC
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use std::path::PathBuf;
+    use crate::cli::{OutputFormat, SortBy};
+    use crate::output::{format_backups, format_todos, format_views, BackupRecord, ViewRecord};
+    use crate::todo::{Priority, Todo};
+    use crate::view::View;
+
+    fn todo() -> Todo {
+        Todo {
+            is_starred: true,
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 8, 30, 0).unwrap(),
+            tags: vec!["home".to_string(), "shop".to_string()],
+            notes: "Ask \"Bob\"\n\tfirst".to_string(),
+            due: NaiveDate::from_ymd_opt(2024, 5, 3),
+            priority: Priority::High,
+            ..Todo::new(3, "Buy milk, eggs")
+        }
+    }
+
+    #[test]
+    fn test_format_todos_as_json() {
+        let todo = todo();
+        let json: serde_json::Value = serde_json::from_str(&format_todos(&[&todo], "default", OutputFormat::Json)).unwrap();
+        assert_eq!(json, serde_json::json!([{
+            "id": 3,
+            "board": "default",
+            "description": "Buy milk, eggs",
+            "is_complete": false,
+            "is_starred": true,
+            "priority": "high",
+            "tags": ["home", "shop"],
+            "due": "2024-05-03",
+            "recurrence": null,
+            "parent": null,
+            "notes": "Ask \"Bob\"\n\tfirst",
+            "created_at": "2024-05-01T08:30:00Z",
+            "completed_at": null,
+        }]));
+        assert_eq!(format_todos(&[], "default", OutputFormat::Json), "[]\n");
+    }
+
+    #[test]
+    fn test_format_todos_as_csv_and_tsv() {
+        let todo = todo();
+        let header = "id,board,description,is_complete,is_starred,priority,tags,due,recurrence,parent,notes,created_at,completed_at";
+
+        let csv = format_todos(&[&todo], "default", OutputFormat::Csv);
+        let row = "3,default,\"Buy milk, eggs\",false,true,high,home shop,2024-05-03,,,\"Ask \"\"Bob\"\"\n\tfirst\",2024-05-01T08:30:00Z,";
+        assert_eq!(csv, format!("{}\n{}\n", header, row));
+
+        let tsv = format_todos(&[&todo], "default", OutputFormat::Tsv);
+        let row = "3\tdefault\tBuy milk, eggs\tfalse\ttrue\thigh\thome shop\t2024-05-03\t\t\tAsk \"Bob\"\\n\\tfirst\t2024-05-01T08:30:00Z\t";
+        assert_eq!(tsv, format!("{}\n{}\n", header.replace(',', "\t"), row));
+    }
+
+    #[test]
+    fn test_format_views() {
+        let view = View { query: "not done and age>14d".to_string(), sort: Some(SortBy::Date), format: None };
+        let views = vec![ViewRecord::new("stale", &view, 4)];
+        assert_eq!(format_views(&views, OutputFormat::Csv), "name,query,sort,format,count\nstale,not done and age>14d,date,,4\n");
+
+        let json: serde_json::Value = serde_json::from_str(&format_views(&views, OutputFormat::Json)).unwrap();
+        assert_eq!(json, serde_json::json!([{
+            "name": "stale",
+            "query": "not done and age>14d",
+            "sort": "date",
+            "format": null,
+            "count": 4,
+        }]));
+    }
+
+    #[test]
+    fn test_format_backups() {
+        let backups = vec![BackupRecord::new(1714552200, PathBuf::from("backup/todos_backup_1714552200.json"))];
+        assert_eq!(
+            format_backups(&backups, OutputFormat::Csv),
+            "timestamp,created_at,path\n1714552200,2024-05-01T08:30:00Z,backup/todos_backup_1714552200.json\n"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::todo::Priority;
+
+/// Struct representing a todo description split into its plain text and inline tokens
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedDescription {
+    /// The description with all recognized tokens removed
+    pub desc: String,
+    /// Tags given as `#tag` tokens, lowercased and without duplicates
+    pub tags: Vec<String>,
+    /// The raw value of the last `due:` token
+    pub due: Option<String>,
+    /// The priority given by the last `!priority` token
+    pub priority: Option<Priority>,
+    /// The raw value of the last `every:` token
+    pub every: Option<String>,
+}
+
+/// Parses a raw todo description and extracts inline tokens such as `#tag`, `due:fri`, `every:mon` or `!high`.
+///
+/// Tokens are only recognized as whole words. Everything that is not a token is kept
+/// in the description, joined by single spaces.
+///
+/// # Arguments
+///
+/// `input` - The raw description as typed by the user.
+///
+/// # Returns
+///
+/// `ParsedDescription` - The cleaned description together with the extracted tokens.
+pub fn parse_description(input: &str) -> ParsedDescription {
+    let mut parsed = ParsedDescription::default();
+    let mut words = Vec::new();
+
+    for word in input.split_whitespace() {
+        if let Some(tag) = parse_tag(word) {
+            if !parsed.tags.contains(&tag) {
+                parsed.tags.push(tag);
+            }
+        } else if let Some(due) = parse_prefixed(word, "due:") {
+            parsed.due = Some(due.to_string());
+        } else if let Some(every) = parse_prefixed(word, "every:") {
+            parsed.every = Some(every.to_string());
+        } else if let Some(priority) = Priority::from_token(word) {
+            parsed.priority = Some(priority);
+        } else {
+            words.push(word);
+        }
+    }
+
+    parsed.desc = words.join(" ");
+    parsed
+}
+
+/// Parses a single `#tag` word into a normalized tag name.
+///
+/// A leading `#` is required. Trailing punctuation such as in `#home,` is ignored.
+pub fn parse_tag(word: &str) -> Option<String> {
+    let name = word.strip_prefix('#')?;
+    normalize_tag(name.trim_end_matches(['.', ',', ';', ':', '!', '?']))
+}
+
+/// Strips a case-insensitive prefix such as `due:` from a word, returning the non-empty value.
+fn parse_prefixed<'a>(word: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = word.get(..prefix.len())?;
+    let value = &word[prefix.len()..];
+    (head.eq_ignore_ascii_case(prefix) && !value.is_empty()).then_some(value)
+}
+
+/// Normalizes a tag name given with or without a leading `#`.
+///
+/// Tags may only contain alphanumeric characters, `-`, `_`, `.` and `/`. Returns `None`
+/// if the name is empty or contains any other character.
+pub fn normalize_tag(name: &str) -> Option<String> {
+    let name = name.strip_prefix('#').unwrap_or(name);
+    let is_valid = !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+
+    is_valid.then(|| name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{normalize_tag, parse_description};
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_parse_description_extracts_tags() {
+        let parsed = parse_description("Deploy #Work staging #release-1.4 #work,");
+
+        assert_eq!(parsed.desc, "Deploy staging");
+        assert_eq!(parsed.tags, vec!["work".to_string(), "release-1.4".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_description_keeps_non_tag_hashes() {
+        let parsed = parse_description("Fix issue # 12 and C#");
+
+        assert_eq!(parsed.desc, "Fix issue # 12 and C#");
+        assert!(parsed.tags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_description_extracts_due() {
+        let parsed = parse_description("Send invoice Due:fri #work");
+
+        assert_eq!(parsed.desc, "Send invoice");
+        assert_eq!(parsed.due, Some("fri".to_string()));
+        assert_eq!(parse_description("due: tomorrow").due, None);
+        assert_eq!(parse_description("Report every:mon,thu").every, Some("mon,thu".to_string()));
+    }
+
+    #[test]
+    fn test_parse_description_extracts_priority() {
+        let parsed = parse_description("Fix prod !HIGH now!");
+
+        assert_eq!(parsed.desc, "Fix prod now!");
+        assert_eq!(parsed.priority, Some(Priority::High));
+        assert_eq!(parse_description("Wow !!").priority, None);
+        assert_eq!(parse_description("!none").priority, None);
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("#Home"), Some("home".to_string()));
+        assert_eq!(normalize_tag("home"), Some("home".to_string()));
+        assert_eq!(normalize_tag("#"), None);
+        assert_eq!(normalize_tag("a,b"), None);
+    }
+}
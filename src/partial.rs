@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use crate::cli::{OutputFormat, Pattern};
+use crate::config::Config;
+use crate::journal::{command_line, Journal};
+use crate::patch::Patch;
+use crate::storage::Storage;
+use crate::todo::{Board, TodoList};
+use crate::utils::get_journal_file_path;
+
+/// Whether a command only touches todos by ID, so it can run on these todos without loading the whole todo list
+pub fn is_partial(pattern: &Pattern) -> bool {
+    matches!(
+        pattern,
+        Pattern::Done { .. } | Pattern::Undone { .. } | Pattern::Star { .. } | Pattern::Edit { .. } | Pattern::Rm { .. }
+    )
+}
+
+/// Run a command that only touches todos by ID on these todos.
+///
+/// Only the todos the command names, together with their subtasks where the command needs them, are read from the
+/// storage. The command runs on a board holding just these todos, and only the todos it changed are written back and
+/// recorded in the journal. Afterwards the board is listed, as after running the command on the whole todo list.
+///
+/// # Arguments
+///
+/// `storage` - The storage holding the todo list.
+/// `config` - The configuration, which defines the hooks and the look of the output.
+/// `pattern` - The command, which must be one for which `is_partial` holds.
+/// `output_format` - The format given by `--format`, if any.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the command succeeded, or an error if it failed, in which case nothing is written.
+pub fn run_partial(storage: &mut dyn Storage, config: Config, pattern: Pattern, output_format: Option<OutputFormat>) -> Result<()> {
+    let board_name = storage.active_board()?;
+    let id_space = storage.id_space(&board_name)?;
+    let todos = match &pattern {
+        Pattern::Edit { id, .. } => storage.get(&board_name, *id)?.into_iter().collect(),
+        Pattern::Done { cascade, args } => storage.query(&board_name, args, *cascade)?,
+        Pattern::Undone { args } | Pattern::Star { args } => storage.query(&board_name, args, false)?,
+        // Removing a todo checks for subtasks even without `--cascade`
+        Pattern::Rm { args, .. } => storage.query(&board_name, args, true)?,
+        _ => unreachable!("Only commands touching todos by ID run on a part of the todo list"),
+    };
+
+    let indices: HashMap<usize, usize> = todos.iter().map(|(index, todo)| (todo.id, *index)).collect();
+    let omitted = id_space.len - todos.len();
+    let board = Board {
+        todos: todos.into_iter().map(|(_, todo)| todo).collect(),
+        available_ids: id_space.available_ids,
+        omitted,
+    };
+    let mut todo_list = TodoList::from_boards(BTreeMap::from([(board_name.clone(), board)]), board_name.clone());
+    todo_list.config = config;
+    todo_list.output_format = output_format;
+    let before = todo_list.boards.clone();
+
+    match pattern {
+        Pattern::Done { cascade, args } => todo_list.complete_todos(args, cascade)?,
+        Pattern::Undone { args } => todo_list.reopen_todos(args)?,
+        Pattern::Star { args } => todo_list.toggle_stars(args)?,
+        Pattern::Edit { id, description } => todo_list.edit_todo(id, description)?,
+        Pattern::Rm { cascade, args } => todo_list.remove_todos(args, cascade)?,
+        _ => unreachable!("Only commands touching todos by ID run on a part of the todo list"),
+    }
+
+    if let Some(mut revert) = Patch::revert(&before, &board_name, &todo_list) {
+        // These commands never reorder todos, so the patches only hold todos by their index
+        let mut update = revert.capture(&todo_list.boards, &todo_list.active_board);
+        to_board_indices(&mut revert, &indices, omitted);
+        to_board_indices(&mut update, &indices, omitted);
+
+        let journal_path = get_journal_file_path(storage.companion_path());
+        let mut journal = Journal::load_from_file(&journal_path).context("Failed to load operation journal")?;
+        journal.record(command_line(), revert);
+        storage.update(&update)?;
+        journal.save_to_file(&journal_path).context("Failed to save operation journal")?;
+    }
+
+    let mut listed = storage.load()?;
+    listed.config = todo_list.config;
+    listed.output_format = todo_list.output_format;
+    listed.list();
+    Ok(())
+}
+
+/// Helper function to replace the indices of todos on the board holding only the touched todos with their indices on
+/// the whole board.
+///
+/// Todos the command added are appended to the board, after all todos that were not read.
+fn to_board_indices(patch: &mut Patch, indices: &HashMap<usize, usize>, omitted: usize) {
+    for board_patch in patch.boards.values_mut().flatten() {
+        for (id, (index, _)) in board_patch.todos.iter_mut().filter_map(|(id, state)| Some((id, state.as_mut()?))) {
+            *index = indices.get(id).copied().unwrap_or(*index + omitted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use crate::cli::Pattern;
+    use crate::config::Config;
+    use crate::journal::Journal;
+    use crate::partial::run_partial;
+    use crate::storage::{JsonStorage, SqliteStorage, Storage};
+    use crate::todo::TodoList;
+    use std::fs;
+    use std::path::Path;
+
+    // Helper function to save a todo list with subtasks and a recurring todo to a storage
+    fn save_todo_list(storage: &mut dyn Storage) -> TodoList {
+        let mut todo_list = TodoList::default();
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["First :: Second every:weekly :: Third".to_string()] }).unwrap();
+        todo_list.handle_cli(Pattern::Add { parent: Some(1), args: vec!["Child :: Other child".to_string()] }).unwrap();
+        todo_list.handle_cli(Pattern::Add { parent: Some(4), args: vec!["Grandchild".to_string()] }).unwrap();
+        storage.save(&todo_list).expect("Failed to save todo list");
+        todo_list
+    }
+
+    // Helper function to serialize a todo list without the times todos were created and completed, which differ between runs
+    fn without_times(mut todo_list: TodoList) -> serde_json::Value {
+        for todo in todo_list.boards.values_mut().flat_map(|board| board.todos.iter_mut()) {
+            todo.timestamp = DateTime::UNIX_EPOCH;
+            todo.completed_at = todo.completed_at.map(|_| DateTime::UNIX_EPOCH);
+        }
+        serde_json::to_value(&todo_list).unwrap()
+    }
+
+    fn patterns() -> Vec<Pattern> {
+        vec![
+            Pattern::Done { cascade: false, args: vec![2] },
+            Pattern::Star { args: vec![3, 5] },
+            Pattern::Edit { id: 3, description: vec!["Third edited #work".to_string()] },
+            Pattern::Rm { cascade: true, args: vec![4] },
+            Pattern::Done { cascade: true, args: vec![1] },
+            Pattern::Undone { args: vec![1] },
+        ]
+    }
+
+    // Helper function to check that commands run on a storage have the same effect as on the loaded todo list
+    fn assert_partial_matches_full(storage: &mut dyn Storage) {
+        let mut todo_list = save_todo_list(storage);
+        for pattern in patterns() {
+            run_partial(storage, Config::default(), pattern, None).expect("Failed to run command on the storage");
+        }
+        for pattern in patterns() {
+            todo_list.handle_cli(pattern).expect("Failed to run command on the todo list");
+        }
+
+        assert_eq!(without_times(storage.load().expect("Failed to load todo list")), without_times(todo_list));
+    }
+
+    #[test]
+    fn test_partial_commands_match_full_commands() {
+        assert_partial_matches_full(&mut SqliteStorage::open("test_partial_sqlite.db".into(), None).unwrap());
+        assert_partial_matches_full(&mut JsonStorage::new("test_partial.json".into()));
+
+        let journal = Journal::load_from_file(Path::new("test_partial_sqlite.journal.json")).unwrap();
+        assert_eq!(journal.undo_entries().len(), patterns().len());
+
+        for file in ["test_partial_sqlite.db", "test_partial_sqlite.journal.json", "test_partial.json", "test_partial.json.bak", "test_partial.journal.json"] {
+            fs::remove_file(file).expect("Failed to delete test file");
+        }
+    }
+
+    #[test]
+    fn test_failing_partial_commands_write_nothing() {
+        let db_path = "test_partial_failure.db";
+        let mut storage = SqliteStorage::open(db_path.into(), None).unwrap();
+        let todo_list = save_todo_list(&mut storage);
+
+        let error = run_partial(&mut storage, Config::default(), Pattern::Rm { cascade: false, args: vec![1] }, None)
+            .expect_err("Removing a todo with subtasks should fail");
+        assert!(format!("{:#}", error).contains("has subtasks"));
+        let error = run_partial(&mut storage, Config::default(), Pattern::Done { cascade: false, args: vec![2, 99] }, None)
+            .expect_err("Completing a missing todo should fail");
+        assert!(format!("{:#}", error).contains("Todo item with ID 99 not found"));
+
+        assert_eq!(without_times(storage.load().expect("Failed to load todo list")), without_times(todo_list));
+        fs::remove_file(db_path).expect("Failed to delete test file");
+    }
+}
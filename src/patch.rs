@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::todo::{Board, Todo, TodoList};
+
+/// Struct representing the state of the parts of a todo list an operation touched.
+///
+/// Applying a patch brings these parts into its state and leaves the rest of the todo list as it is, so the journal
+/// only keeps the todos an operation changed to undo it.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Patch {
+    /// The active board, if the operation switched it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_board: Option<String>,
+    /// The state of the touched boards by name, or `None` for boards that do not exist
+    pub boards: BTreeMap<String, Option<BoardPatch>>,
+}
+
+/// Struct representing the state of the touched todos of a board
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BoardPatch {
+    /// The IDs available for reuse
+    pub available_ids: BTreeSet<usize>,
+    /// The touched todos by ID with their index on the board, or `None` for todos not on the board
+    pub todos: BTreeMap<usize, Option<(usize, Todo)>>,
+    /// The IDs of all todos of the board in their order, if the operation reordered the todos it did not change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<usize>>,
+}
+
+impl Patch {
+    /// Find the parts of a todo list an operation changed.
+    ///
+    /// # Arguments
+    ///
+    /// `boards` - The boards before the operation.
+    /// `active_board` - The active board before the operation.
+    /// `todo_list` - The todo list after the operation.
+    ///
+    /// # Returns
+    ///
+    /// `Option<Patch>` - The state of the changed parts before the operation, which reverts the operation when it is
+    /// applied, or `None` if the operation changed nothing.
+    pub fn revert(boards: &BTreeMap<String, Board>, active_board: &str, todo_list: &TodoList) -> Option<Patch> {
+        let mut patch = Patch {
+            active_board: (active_board != todo_list.active_board).then(|| active_board.to_string()),
+            boards: BTreeMap::new(),
+        };
+
+        for (name, board) in boards {
+            let board_patch = match todo_list.boards.get(name) {
+                Some(current) => match BoardPatch::revert(board, current) {
+                    Some(board_patch) => Some(board_patch),
+                    None => continue,
+                },
+                None => Some(BoardPatch::capture(board, board.todos.iter().map(|todo| todo.id), false)),
+            };
+            patch.boards.insert(name.clone(), board_patch);
+        }
+        for name in todo_list.boards.keys().filter(|name| !boards.contains_key(*name)) {
+            patch.boards.insert(name.clone(), None);
+        }
+
+        (patch != Patch::default()).then_some(patch)
+    }
+
+    /// Read the current state of the parts of a todo list the patch touches, which undoes applying the patch.
+    ///
+    /// # Arguments
+    ///
+    /// `boards` - The current boards of the todo list.
+    /// `active_board` - The current active board of the todo list.
+    pub fn capture(&self, boards: &BTreeMap<String, Board>, active_board: &str) -> Patch {
+        let captured = self.boards.iter()
+            .map(|(name, board_patch)| {
+                let current = boards.get(name).map(|board| match board_patch {
+                    Some(board_patch) => BoardPatch::capture(board, board_patch.todos.keys().copied(), board_patch.order.is_some()),
+                    // Applying the patch removes the board, so all of its todos are touched
+                    None => BoardPatch::capture(board, board.todos.iter().map(|todo| todo.id), false),
+                });
+                (name.clone(), current)
+            })
+            .collect();
+
+        Patch {
+            active_board: self.active_board.as_ref().map(|_| active_board.to_string()),
+            boards: captured,
+        }
+    }
+
+    /// Bring the parts of a todo list the patch touches into the state of the patch
+    pub fn apply(&self, todo_list: &mut TodoList) {
+        for (name, board_patch) in &self.boards {
+            match board_patch {
+                Some(board_patch) => board_patch.apply(todo_list.boards.entry(name.clone()).or_default()),
+                None => {
+                    todo_list.boards.remove(name);
+                }
+            }
+        }
+        if let Some(active_board) = &self.active_board {
+            todo_list.active_board = active_board.clone();
+        }
+    }
+}
+
+impl BoardPatch {
+    /// Helper function to read the state of todos of a board by ID, together with the order of all todos if `with_order`
+    /// is set
+    fn capture(board: &Board, ids: impl Iterator<Item = usize>, with_order: bool) -> BoardPatch {
+        let indices: HashMap<usize, usize> = board.todos.iter().enumerate().map(|(index, todo)| (todo.id, index)).collect();
+        BoardPatch {
+            available_ids: board.available_ids.clone(),
+            todos: ids.map(|id| (id, indices.get(&id).map(|&index| (index, board.todos[index].clone())))).collect(),
+            order: with_order.then(|| board.todos.iter().map(|todo| todo.id).collect()),
+        }
+    }
+
+    /// Helper function to find the todos of a board an operation changed.
+    ///
+    /// # Returns
+    ///
+    /// `Option<BoardPatch>` - The state of the changed todos of the board before the operation, or `None` if the
+    /// operation left the board as it was.
+    fn revert(before: &Board, after: &Board) -> Option<BoardPatch> {
+        let before_todos: HashMap<usize, &Todo> = before.todos.iter().map(|todo| (todo.id, todo)).collect();
+        let after_todos: HashMap<usize, &Todo> = after.todos.iter().map(|todo| (todo.id, todo)).collect();
+
+        let mut changed: BTreeSet<usize> = before.todos.iter()
+            .filter(|todo| after_todos.get(&todo.id) != Some(todo))
+            .map(|todo| todo.id)
+            .collect();
+        changed.extend(after.todos.iter().map(|todo| todo.id).filter(|id| !before_todos.contains_key(id)));
+
+        let unchanged = |board: &Board| -> Vec<usize> {
+            board.todos.iter().map(|todo| todo.id).filter(|id| !changed.contains(id)).collect()
+        };
+        let is_reordered = unchanged(before) != unchanged(after);
+
+        if changed.is_empty() && !is_reordered && before.available_ids == after.available_ids {
+            return None;
+        }
+        Some(BoardPatch::capture(before, changed.into_iter(), is_reordered))
+    }
+
+    /// Helper function to bring the touched todos of a board into the state of the patch.
+    ///
+    /// The touched todos are removed and inserted again at their index in the order of their index, which restores
+    /// their positions among the todos the patch does not touch.
+    fn apply(&self, board: &mut Board) {
+        board.available_ids = self.available_ids.clone();
+        board.todos.retain(|todo| !self.todos.contains_key(&todo.id));
+
+        let mut todos: Vec<&(usize, Todo)> = self.todos.values().flatten().collect();
+        todos.sort_by_key(|(index, _)| *index);
+        for (index, todo) in todos {
+            board.todos.insert((*index).min(board.todos.len()), todo.clone());
+        }
+
+        if let Some(order) = &self.order {
+            let positions: HashMap<usize, usize> = order.iter().enumerate().map(|(position, id)| (*id, position)).collect();
+            board.todos.sort_by_key(|todo| positions.get(&todo.id).copied().unwrap_or(usize::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{BoardAction, Pattern};
+    use crate::patch::Patch;
+    use crate::todo::TodoList;
+
+    // Helper function to run commands on a todo list
+    fn run(todo_list: &mut TodoList, patterns: Vec<Pattern>) {
+        for pattern in patterns {
+            todo_list.handle_cli(pattern).expect("Failed to run command");
+        }
+    }
+
+    #[test]
+    fn test_patch_reverts_and_reapplies_changes() {
+        let mut todo_list = TodoList::default();
+        run(&mut todo_list, vec![Pattern::Add { parent: None, args: vec!["First :: Second :: Third :: Fourth".to_string()] }]);
+        let before = serde_json::to_value(&todo_list).unwrap();
+        let (boards, active_board) = (todo_list.boards.clone(), todo_list.active_board.clone());
+
+        run(&mut todo_list, vec![
+            Pattern::Rm { cascade: false, args: vec![2] },
+            Pattern::Done { cascade: false, args: vec![3] },
+            Pattern::Board { action: Some(BoardAction::Create { name: "work".to_string() }) },
+            Pattern::Board { action: Some(BoardAction::Switch { name: "work".to_string() }) },
+        ]);
+        // Reorder the todos that did not change otherwise
+        todo_list.boards.get_mut("default").unwrap().todos.swap(0, 2);
+        let after = serde_json::to_value(&todo_list).unwrap();
+
+        let patch = Patch::revert(&boards, &active_board, &todo_list).expect("The changes were not found");
+        let board_patch = patch.boards["default"].as_ref().unwrap();
+        assert_eq!(board_patch.todos.keys().copied().collect::<Vec<_>>(), vec![2, 3], "Only changed todos should be kept");
+        assert_eq!(board_patch.order, Some(vec![1, 2, 3, 4]));
+        assert!(patch.boards["work"].is_none());
+        assert_eq!(patch.active_board.as_deref(), Some("default"));
+
+        let redo = patch.capture(&todo_list.boards, &todo_list.active_board);
+        patch.apply(&mut todo_list);
+        assert_eq!(serde_json::to_value(&todo_list).unwrap(), before);
+
+        redo.apply(&mut todo_list);
+        assert_eq!(serde_json::to_value(&todo_list).unwrap(), after);
+    }
+
+    #[test]
+    fn test_patch_of_unchanged_todo_list_is_empty() {
+        let mut todo_list = TodoList::default();
+        run(&mut todo_list, vec![Pattern::Add { parent: None, args: vec!["First".to_string()] }]);
+        let (boards, active_board) = (todo_list.boards.clone(), todo_list.active_board.clone());
+
+        run(&mut todo_list, vec![Pattern::List]);
+        assert!(Patch::revert(&boards, &active_board, &todo_list).is_none());
+    }
+}
@@ -0,0 +1,630 @@
+use chrono::{Local, Months, NaiveDate};
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+use crate::due::{parse_due_date, DueFilter};
+use crate::parse::normalize_tag;
+use crate::todo::{Priority, Todo};
+
+/// Struct representing an error in a query, pointing at the column it was found at
+#[derive(Debug, PartialEq)]
+pub struct QueryError {
+    /// The query as written by the user
+    pub query: String,
+    /// The 1-based column of the offending character
+    pub column: usize,
+    /// What is wrong at the column
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    /// Formats the error with the query and a marker under the offending column
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid query at column {}: {}\n  {}\n  {}^", self.column, self.message, self.query, " ".repeat(self.column - 1))
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Struct representing a parsed query matching todos.
+///
+/// A query combines terms with `and`, `or` and `not` and parentheses, where terms next to each other must all match.
+/// `::` is accepted as `or`. The terms are:
+///
+/// * `word` - The description contains the word.
+/// * `"a phrase"` - The description contains the phrase.
+/// * `/pattern/` - The description matches the regular expression, where the `i` flag of `/pattern/i` ignores case.
+/// * `#tag` or `tag:tag` - The todo has the tag.
+/// * `!high` or `priority:high` - The todo has the priority.
+/// * `due:value` - The due date matches, as described for `DueFilter`.
+/// * `done`, `done:yes` or `done:no` - The todo is done or open.
+/// * `starred`, `starred:yes` or `starred:no` - The todo is starred or not.
+/// * `id:3`, `id:3..9`, `id:3..` or `id:..9` - The ID is in the range.
+/// * `age>7d` - The todo was created more than 7 days ago, with `<`, `<=`, `>=`, `=` and the units `d` and `w`.
+/// * `created:2026-10` or `completed:2026-10-17` - The todo was created or completed in the year, month or day, with
+///   `<`, `<=`, `>` and `>=` comparing to the start or the end of the period.
+/// * `notes:word` - The notes contain the word.
+#[derive(Debug)]
+pub struct Query {
+    expr: Option<Expr>,
+    today: NaiveDate,
+}
+
+impl Query {
+    /// Parse a query.
+    ///
+    /// # Arguments
+    ///
+    /// `input` - The query as written by the user.
+    /// `case_sensitive` - Whether words, phrases and regular expressions match case-sensitively.
+    /// `today` - The date relative dates and ages are resolved against.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Query, QueryError>` - The query, which matches all todos if it is empty, or an error pointing at the
+    /// column of the first problem.
+    pub fn parse(input: &str, case_sensitive: bool, today: NaiveDate) -> Result<Self, QueryError> {
+        let error = |column: usize, message: String| QueryError { query: input.to_string(), column, message };
+        let tokens = tokenize(input).map_err(|(column, message)| error(column, message))?;
+
+        let mut parser = Parser { tokens, position: 0, case_sensitive, today, end: input.chars().count() + 1 };
+        let expr = if parser.tokens.is_empty() {
+            None
+        } else {
+            Some(parser.parse_or().map_err(|(column, message)| error(column, message))?)
+        };
+        if let Some(token) = parser.tokens.get(parser.position) {
+            let message = match token.kind {
+                TokenKind::RightParen => "unexpected ')' without a matching '('".to_string(),
+                _ => "unexpected term".to_string(),
+            };
+            return Err(error(token.column, message));
+        }
+        Ok(Query { expr, today })
+    }
+
+    /// Check whether a todo matches the query
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.expr.as_ref().is_none_or(|expr| expr.matches(todo, self.today))
+    }
+}
+
+/// Enum representing the tokens of a query
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Regex(String, String),
+}
+
+/// Struct representing a token with the 1-based column it starts at
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+/// Helper function to split a query into tokens, or fail with the column and description of the problem
+fn tokenize(input: &str) -> Result<Vec<Token>, (usize, String)> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let column = index + 1;
+        let kind = match chars[index] {
+            c if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '(' => {
+                index += 1;
+                TokenKind::LeftParen
+            }
+            ')' => {
+                index += 1;
+                TokenKind::RightParen
+            }
+            ':' if chars.get(index + 1) == Some(&':') => {
+                index += 2;
+                TokenKind::Or
+            }
+            '"' => {
+                let (text, end) = read_delimited(&chars, index, '"')
+                    .ok_or_else(|| (column, "unterminated phrase, expected a closing '\"'".to_string()))?;
+                index = end;
+                TokenKind::Phrase(text)
+            }
+            '/' => {
+                let (pattern, end) = read_delimited(&chars, index, '/')
+                    .ok_or_else(|| (column, "unterminated regular expression, expected a closing '/'".to_string()))?;
+                let flags: String = chars[end..].iter().take_while(|c| c.is_alphanumeric()).collect();
+                index = end + flags.chars().count();
+                TokenKind::Regex(pattern, flags)
+            }
+            _ => {
+                // Words end at whitespace, parentheses and `::`
+                let ends_word = |index: usize| {
+                    chars[index].is_whitespace()
+                        || matches!(chars[index], '(' | ')')
+                        || (chars[index] == ':' && chars.get(index + 1) == Some(&':'))
+                };
+                let start = index;
+                while index < chars.len() && !ends_word(index) {
+                    index += 1;
+                }
+                let word: String = chars[start..index].iter().collect();
+                match word.as_str() {
+                    "and" | "AND" | "&&" => TokenKind::And,
+                    "or" | "OR" | "||" => TokenKind::Or,
+                    "not" | "NOT" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+        tokens.push(Token { kind, column });
+    }
+    Ok(tokens)
+}
+
+/// Helper function to read the text between a delimiter at `start` and the next unescaped delimiter.
+///
+/// A backslash escapes the delimiter, other backslashes are kept for regular expressions.
+///
+/// # Returns
+///
+/// `Option<(String, usize)>` - The text and the index after the closing delimiter, or `None` if it is missing.
+fn read_delimited(chars: &[char], start: usize, delimiter: char) -> Option<(String, usize)> {
+    let mut text = String::new();
+    let mut index = start + 1;
+    while index < chars.len() {
+        match chars[index] {
+            '\\' if chars.get(index + 1) == Some(&delimiter) => {
+                text.push(delimiter);
+                index += 2;
+            }
+            c if c == delimiter => return Some((text, index + 1)),
+            c => {
+                text.push(c);
+                index += 1;
+            }
+        }
+    }
+    None
+}
+
+/// Enum representing a parsed query expression
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+impl Expr {
+    fn matches(&self, todo: &Todo, today: NaiveDate) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(todo, today) && right.matches(todo, today),
+            Expr::Or(left, right) => left.matches(todo, today) || right.matches(todo, today),
+            Expr::Not(expr) => !expr.matches(todo, today),
+            Expr::Term(term) => term.matches(todo, today),
+        }
+    }
+}
+
+/// Enum representing the comparison of a field predicate
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Compare a value with the period from `start` to `end`, both included
+    fn in_period<T: PartialOrd>(&self, value: T, start: T, end: T) -> bool {
+        match self {
+            Comparison::Equal => value >= start && value <= end,
+            Comparison::Less => value < start,
+            Comparison::LessOrEqual => value <= end,
+            Comparison::Greater => value > end,
+            Comparison::GreaterOrEqual => value >= start,
+        }
+    }
+}
+
+/// Enum representing a single term of a query
+#[derive(Debug)]
+enum Term {
+    Text { text: String, case_sensitive: bool },
+    Regex(Regex),
+    Tag(String),
+    Priority(Priority),
+    Due(DueFilter),
+    Done(bool),
+    Starred(bool),
+    Id(Option<usize>, Option<usize>),
+    Age(Comparison, i64),
+    Created(Comparison, NaiveDate, NaiveDate),
+    Completed(Comparison, NaiveDate, NaiveDate),
+    Notes { text: String, case_sensitive: bool },
+}
+
+impl Term {
+    fn matches(&self, todo: &Todo, today: NaiveDate) -> bool {
+        match self {
+            Term::Text { text, case_sensitive } => contains(&todo.desc, text, *case_sensitive),
+            Term::Regex(regex) => regex.is_match(&todo.desc),
+            Term::Tag(tag) => todo.tags.contains(tag),
+            Term::Priority(priority) => todo.priority == *priority,
+            Term::Due(filter) => filter.matches(todo.due, todo.is_complete, today),
+            Term::Done(done) => todo.is_complete == *done,
+            Term::Starred(starred) => todo.is_starred == *starred,
+            Term::Id(min, max) => min.is_none_or(|min| todo.id >= min) && max.is_none_or(|max| todo.id <= max),
+            Term::Age(comparison, days) => {
+                let age = (today - todo.timestamp.with_timezone(&Local).date_naive()).num_days();
+                comparison.in_period(age, *days, *days)
+            }
+            Term::Created(comparison, start, end) => {
+                comparison.in_period(todo.timestamp.with_timezone(&Local).date_naive(), *start, *end)
+            }
+            Term::Completed(comparison, start, end) => todo.completed_at
+                .is_some_and(|completed| comparison.in_period(completed.with_timezone(&Local).date_naive(), *start, *end)),
+            Term::Notes { text, case_sensitive } => contains(&todo.notes, text, *case_sensitive),
+        }
+    }
+}
+
+/// Helper function to check if a text contains another, where `needle` is already lowercase if case is ignored
+fn contains(haystack: &str, needle: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        haystack.contains(needle)
+    } else {
+        haystack.to_lowercase().contains(needle)
+    }
+}
+
+/// Struct holding the state of parsing the tokens of a query
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    case_sensitive: bool,
+    today: NaiveDate,
+    /// The column after the end of the query, which errors at the end of the query point at
+    end: usize,
+}
+
+/// The result of parsing, failing with the column and description of the problem
+type ParseResult<T> = Result<T, (usize, String)>;
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Parse terms combined with `or`
+    fn parse_or(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&TokenKind::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    /// Parse terms combined with `and` or written next to each other
+    fn parse_and(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(TokenKind::And) => {
+                    self.next();
+                }
+                Some(TokenKind::Or | TokenKind::RightParen) | None => return Ok(expr),
+                Some(_) => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+    }
+
+    /// Parse a term, which may be negated by `not`
+    fn parse_not(&mut self) -> ParseResult<Expr> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    /// Parse a term or a query in parentheses
+    fn parse_atom(&mut self) -> ParseResult<Expr> {
+        let Some(token) = self.next() else {
+            return Err((self.end, "expected a term at the end of the query".to_string()));
+        };
+        match token.kind {
+            TokenKind::LeftParen => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RightParen, .. }) => Ok(expr),
+                    Some(other) => Err((other.column, format!("expected ')' to close the '(' at column {}", token.column))),
+                    None => Err((self.end, format!("expected ')' to close the '(' at column {}", token.column))),
+                }
+            }
+            TokenKind::RightParen => Err((token.column, "expected a term before ')'".to_string())),
+            TokenKind::And | TokenKind::Or => Err((token.column, "expected a term before the operator".to_string())),
+            TokenKind::Not => unreachable!("`not` is parsed by parse_not"),
+            TokenKind::Phrase(phrase) => Ok(Expr::Term(self.text(phrase))),
+            TokenKind::Regex(pattern, flags) => self.regex(&pattern, &flags, token.column).map(Expr::Term),
+            TokenKind::Word(word) => self.word(&word, token.column).map(Expr::Term),
+        }
+    }
+
+    /// Create a term matching a text in the description
+    fn text(&self, text: String) -> Term {
+        let text = if self.case_sensitive { text } else { text.to_lowercase() };
+        Term::Text { text, case_sensitive: self.case_sensitive }
+    }
+
+    /// Create a term matching a regular expression
+    fn regex(&self, pattern: &str, flags: &str, column: usize) -> ParseResult<Term> {
+        let mut case_sensitive = self.case_sensitive;
+        for flag in flags.chars() {
+            match flag {
+                'i' => case_sensitive = false,
+                'c' => case_sensitive = true,
+                _ => return Err((column + pattern.chars().count() + 2, format!("unknown regular expression flag '{}', use i or c", flag))),
+            }
+        }
+        RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Term::Regex)
+            .map_err(|e| (column + 1, format!("invalid regular expression: {}", e.to_string().lines().last().unwrap_or_default())))
+    }
+
+    /// Create the term of a word, which is a field predicate, a tag, a priority or a text
+    fn word(&self, word: &str, column: usize) -> ParseResult<Term> {
+        if let Some(tag) = word.strip_prefix('#') {
+            return normalize_tag(tag).map(Term::Tag).ok_or_else(|| (column, format!("invalid tag '{}'", word)));
+        }
+        if let Some(priority) = Priority::from_token(word) {
+            return Ok(Term::Priority(priority));
+        }
+        match word {
+            "done" => return Ok(Term::Done(true)),
+            "starred" => return Ok(Term::Starred(true)),
+            _ => {}
+        }
+
+        let field_length = word.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(word.len());
+        let (field, rest) = word.split_at(field_length);
+        let Some((comparison, value)) = parse_comparison(rest) else {
+            return Ok(self.text(word.to_string()));
+        };
+        let value_column = column + word[..word.len() - value.len()].chars().count();
+        let only_equal = |term: Term| match comparison {
+            Comparison::Equal => Ok(term),
+            _ => Err((value_column - 1, format!("'{}' only supports ':'", field))),
+        };
+        let invalid = |expected: &str| (value_column, format!("invalid {} '{}', expected {}", field, value, expected));
+
+        match field.to_lowercase().as_str() {
+            "tag" => normalize_tag(value).map(Term::Tag).ok_or_else(|| invalid("a tag name")).and_then(only_equal),
+            "priority" => match value.to_lowercase().as_str() {
+                "high" | "h" => only_equal(Term::Priority(Priority::High)),
+                "medium" | "med" | "m" => only_equal(Term::Priority(Priority::Medium)),
+                "low" | "l" => only_equal(Term::Priority(Priority::Low)),
+                "none" => only_equal(Term::Priority(Priority::None)),
+                _ => Err(invalid("high, medium, low or none")),
+            },
+            "due" => DueFilter::parse(value, self.today)
+                .map_err(|_| invalid("today, overdue, week or a date"))
+                .and_then(|filter| only_equal(Term::Due(filter))),
+            "done" => parse_yes_no(value).map(Term::Done).ok_or_else(|| invalid("yes or no")).and_then(only_equal),
+            "starred" => parse_yes_no(value).map(Term::Starred).ok_or_else(|| invalid("yes or no")).and_then(only_equal),
+            "id" => parse_id_range(value).ok_or_else(|| invalid("an ID or a range like 3..9")).and_then(only_equal),
+            "age" => parse_age(value).map(|days| Term::Age(comparison, days)).ok_or_else(|| invalid("a number of days or weeks like 7d or 2w")),
+            "created" => self.period(value)
+                .map(|(start, end)| Term::Created(comparison, start, end))
+                .ok_or_else(|| invalid("a date like 2026-10-17, a month like 2026-10 or a year")),
+            "completed" => self.period(value)
+                .map(|(start, end)| Term::Completed(comparison, start, end))
+                .ok_or_else(|| invalid("a date like 2026-10-17, a month like 2026-10 or a year")),
+            "notes" => {
+                let text = if self.case_sensitive { value.to_string() } else { value.to_lowercase() };
+                only_equal(Term::Notes { text, case_sensitive: self.case_sensitive })
+            }
+            _ => Ok(self.text(word.to_string())),
+        }
+    }
+
+    /// Parse a year, month or day into its first and last day, where days may also be relative like `today`
+    fn period(&self, value: &str) -> Option<(NaiveDate, NaiveDate)> {
+        let parts: Vec<&str> = value.split('-').collect();
+        match parts.as_slice() {
+            [year] if year.len() == 4 => {
+                let year = year.parse().ok()?;
+                Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year, 12, 31)?))
+            }
+            [year, month] if year.len() == 4 => {
+                let start = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+                let end = start.checked_add_months(Months::new(1))?.pred_opt()?;
+                Some((start, end))
+            }
+            _ => {
+                let date = parse_due_date(value, self.today).ok()?;
+                Some((date, date))
+            }
+        }
+    }
+}
+
+/// Helper function to split the comparison operator off the value of a field predicate
+fn parse_comparison(rest: &str) -> Option<(Comparison, &str)> {
+    let operators = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (":", Comparison::Equal),
+        ("=", Comparison::Equal),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+    ];
+    operators.into_iter()
+        .find_map(|(operator, comparison)| rest.strip_prefix(operator).map(|value| (comparison, value)))
+}
+
+/// Helper function to parse `yes` or `no`
+fn parse_yes_no(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" | "y" | "true" => Some(true),
+        "no" | "n" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Helper function to parse an ID or a range of IDs like `3..9`, `3..` or `..9`
+fn parse_id_range(value: &str) -> Option<Term> {
+    let parse_bound = |bound: &str| match bound {
+        "" => Some(None),
+        bound => bound.parse().ok().map(Some),
+    };
+    match value.split_once("..") {
+        Some((min, max)) if !(min.is_empty() && max.is_empty()) => Some(Term::Id(parse_bound(min)?, parse_bound(max)?)),
+        Some(_) => None,
+        None => value.parse().ok().map(|id| Term::Id(Some(id), Some(id))),
+    }
+}
+
+/// Helper function to parse an age like `7d` or `2w` into days
+fn parse_age(value: &str) -> Option<i64> {
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let number: i64 = number.parse().ok()?;
+    match unit {
+        "d" => Some(number),
+        "w" => number.checked_mul(7),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+    use crate::query::Query;
+    use crate::todo::{Priority, Todo};
+
+    fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    fn todo(id: usize, desc: &str) -> Todo {
+        Todo::new(id, desc)
+    }
+
+    fn todos() -> Vec<Todo> {
+        let mut deploy = todo(1, "Deploy staging server");
+        deploy.tags = vec!["work".to_string()];
+        deploy.priority = Priority::High;
+        deploy.timestamp = Utc::now() - Duration::days(20);
+
+        let mut milk = todo(2, "Buy milk");
+        milk.tags = vec!["home".to_string()];
+        milk.is_complete = true;
+        milk.completed_at = Some(Utc.with_ymd_and_hms(2026, 10, 5, 12, 0, 0).unwrap());
+
+        let mut report = todo(3, "Write the Q3 report");
+        report.tags = vec!["work".to_string()];
+        report.is_starred = true;
+        report.notes = "Ask Alice for the numbers".to_string();
+        report.due = Some(today());
+
+        vec![deploy, milk, report]
+    }
+
+    fn ids(query: &str) -> Vec<usize> {
+        let query = Query::parse(query, false, today()).unwrap_or_else(|e| panic!("{}", e));
+        todos().iter().filter(|todo| query.matches(todo)).map(|todo| todo.id).collect()
+    }
+
+    fn error(query: &str) -> (usize, String) {
+        let error = Query::parse(query, false, today()).expect_err("The query should be rejected");
+        (error.column, error.message)
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        assert_eq!(ids(""), vec![1, 2, 3]);
+        assert_eq!(ids("#work"), vec![1, 3]);
+        assert_eq!(ids("#work and not starred"), vec![1]);
+        assert_eq!(ids("#work !high"), vec![1]);
+        assert_eq!(ids("milk or report"), vec![2, 3]);
+        assert_eq!(ids("milk::report"), vec![2, 3]);
+        assert_eq!(ids("not (done or #work)"), Vec::<usize>::new());
+        assert_eq!(ids("(milk OR deploy) AND #work"), vec![1]);
+        assert_eq!(ids("staging deploy"), vec![1]);
+    }
+
+    #[test]
+    fn test_field_predicates() {
+        assert_eq!(ids("done:yes"), vec![2]);
+        assert_eq!(ids("done:no"), vec![1, 3]);
+        assert_eq!(ids("tag:home"), vec![2]);
+        assert_eq!(ids("priority:high"), vec![1]);
+        assert_eq!(ids("due:today"), vec![3]);
+        assert_eq!(ids("id:2..3"), vec![2, 3]);
+        assert_eq!(ids("id:..1"), vec![1]);
+        assert_eq!(ids("id:2"), vec![2]);
+        assert_eq!(ids("age>7d"), vec![1]);
+        assert_eq!(ids("age<=2w"), vec![2, 3]);
+        assert_eq!(ids("completed:2026-10"), vec![2]);
+        assert_eq!(ids("completed<2026-10-05"), Vec::<usize>::new());
+        assert_eq!(ids("completed>=2026"), vec![2]);
+        assert_eq!(ids("created:today"), vec![2, 3]);
+        assert_eq!(ids("created<today"), vec![1]);
+        assert_eq!(ids(&format!("created>{}", today().year() + 1)), Vec::<usize>::new());
+        assert_eq!(ids("notes:alice"), vec![3]);
+    }
+
+    #[test]
+    fn test_phrases_regex_and_case() {
+        assert_eq!(ids("\"staging server\""), vec![1]);
+        assert_eq!(ids("\"server staging\""), Vec::<usize>::new());
+        assert_eq!(ids("/^(buy|write)\\b/"), vec![2, 3]);
+        assert_eq!(ids("/q\\d/"), vec![3]);
+
+        let query = Query::parse("q3", true, today()).unwrap();
+        assert!(!todos().iter().any(|todo| query.matches(todo)));
+        let query = Query::parse("/q\\d/i", true, today()).unwrap();
+        assert!(query.matches(&todos()[2]));
+        let query = Query::parse("/Q\\d/c", false, today()).unwrap();
+        assert!(query.matches(&todos()[2]));
+    }
+
+    #[test]
+    fn test_errors_point_at_the_column() {
+        assert_eq!(error("not done and (age>7d"), (21, "expected ')' to close the '(' at column 14".to_string()));
+        assert_eq!(error("done )"), (6, "unexpected ')' without a matching '('".to_string()));
+        assert_eq!(error("#work and"), (10, "expected a term at the end of the query".to_string()));
+        assert_eq!(error("or milk"), (1, "expected a term before the operator".to_string()));
+        assert_eq!(error("age>soon"), (5, "invalid age 'soon', expected a number of days or weeks like 7d or 2w".to_string()));
+        assert_eq!(error("milk \"oat"), (6, "unterminated phrase, expected a closing '\"'".to_string()));
+        assert_eq!(error("/(milk/"), (2, "invalid regular expression: error: unclosed group".to_string()));
+        assert_eq!(error("/milk/x"), (7, "unknown regular expression flag 'x', use i or c".to_string()));
+        assert_eq!(error("done>yes"), (5, "'done' only supports ':'".to_string()));
+
+        let error = Query::parse("id:x", false, today()).expect_err("The query should be rejected");
+        assert_eq!(error.to_string(), "Invalid query at column 4: invalid id 'x', expected an ID or a range like 3..9\n  id:x\n     ^");
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The longest interval a todo can repeat in, in days, which is about ten years
+pub const MAX_INTERVAL_DAYS: u32 = 3660;
+
+/// Enum representing the rule by which a recurring todo repeats
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    /// Every day
+    Daily,
+    /// Every week on the given weekdays
+    Weekly(Vec<Weekday>),
+    /// Every month on the given day, clamped to the last day of shorter months
+    Monthly(u32),
+    /// Every given number of days
+    Every(u32),
+}
+
+impl Recurrence {
+    /// Parses a recurrence rule as written after `every:`.
+    ///
+    /// Supported rules are:
+    ///
+    /// * `day` or `daily`
+    /// * `week` or `weekly`, repeating on the weekday of `anchor`
+    /// * A comma separated list of weekdays such as `mon,thu`
+    /// * `month` or `monthly`, repeating on the day of the month of `anchor`
+    /// * A day of the month such as `15th`
+    /// * An interval such as `3d` or `2w`, of at most `MAX_INTERVAL_DAYS` days
+    ///
+    /// # Arguments
+    ///
+    /// `value` - The rule as written by the user.
+    /// `anchor` - The date weekly and monthly rules without an explicit day are anchored to.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Recurrence>` - The parsed rule, or an error if the rule is not understood.
+    pub fn parse(value: &str, anchor: NaiveDate) -> Result<Self> {
+        let value = value.trim().to_lowercase();
+        let invalid = || anyhow!("Invalid recurrence '{}'. Use e.g. daily, weekly, mon,thu, monthly, 15th or 3d", value);
+
+        let recurrence = match value.as_str() {
+            "day" | "daily" => Recurrence::Daily,
+            "week" | "weekly" => Recurrence::Weekly(vec![anchor.weekday()]),
+            "month" | "monthly" => Recurrence::Monthly(anchor.day()),
+            _ => {
+                if let Some(day) = parse_day_of_month(&value) {
+                    Recurrence::Monthly(day)
+                } else if let Some(days) = parse_interval(&value) {
+                    if days > MAX_INTERVAL_DAYS {
+                        return Err(anyhow!("Invalid recurrence '{}'. Intervals can be at most {} days", value, MAX_INTERVAL_DAYS));
+                    }
+                    Recurrence::Every(days)
+                } else {
+                    let mut weekdays = value.split(',')
+                        .map(|day| day.parse::<Weekday>().ok())
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?;
+                    weekdays.sort_by_key(|day| day.num_days_from_monday());
+                    weekdays.dedup();
+                    Recurrence::Weekly(weekdays)
+                }
+            }
+        };
+
+        Ok(recurrence)
+    }
+
+    /// Returns the first date on or after `date` matching the rule, or `None` if it is beyond the last date.
+    pub fn first_on_or_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily | Recurrence::Every(_) => Some(date),
+            _ => date.pred_opt().map_or(Some(date), |previous| self.next_after(previous)),
+        }
+    }
+
+    /// Returns the next date strictly after `date` matching the rule, or `None` if it is beyond the last date.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::Every(days) => date.checked_add_days(Days::new((*days).into())),
+            Recurrence::Weekly(weekdays) => (1..=7)
+                .map_while(|offset| date.checked_add_days(Days::new(offset)))
+                .find(|next| weekdays.contains(&next.weekday())),
+            Recurrence::Monthly(day) => {
+                let this_month = day_in_month(date, *day);
+                if this_month > date {
+                    Some(this_month)
+                } else {
+                    date.checked_add_months(Months::new(1)).map(|next_month| day_in_month(next_month, *day))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    /// Formats the rule for display
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(weekdays) => {
+                let days: Vec<String> = weekdays.iter()
+                    .map(|day| day.to_string().to_lowercase())
+                    .collect();
+                write!(f, "{}", days.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "monthly on the {}{}", day, ordinal_suffix(*day)),
+            Recurrence::Every(days) => write!(f, "every {}d", days),
+        }
+    }
+}
+
+/// Returns the given day in the month of `date`, clamped to the last day of the month.
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day.min(31)).rev()
+        .find_map(|day| date.with_day(day))
+        .unwrap_or(date)
+}
+
+/// Parses a day of the month such as `15th`.
+fn parse_day_of_month(value: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"].iter().find_map(|suffix| value.strip_suffix(suffix))?;
+    let day: u32 = digits.parse().ok()?;
+    (1..=31).contains(&day).then_some(day)
+}
+
+/// Parses an interval such as `3d` or `2w` into a number of days.
+fn parse_interval(value: &str) -> Option<u32> {
+    let unit = value.chars().last()?;
+    let amount: u32 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit {
+        'd' => amount,
+        'w' => amount.checked_mul(7)?,
+        _ => return None,
+    };
+    (days > 0).then_some(days)
+}
+
+/// Returns the English ordinal suffix of a day of the month.
+fn ordinal_suffix(day: u32) -> &'static str {
+    match (day % 10, day % 100) {
+        (1, 11) | (2, 12) | (3, 13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recurrence::Recurrence;
+    use chrono::{NaiveDate, Weekday};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        // 2026-10-16 is a Friday
+        let anchor = date(2026, 10, 16);
+
+        assert_eq!(Recurrence::parse("daily", anchor).unwrap(), Recurrence::Daily);
+        assert_eq!(Recurrence::parse("weekly", anchor).unwrap(), Recurrence::Weekly(vec![Weekday::Fri]));
+        assert_eq!(Recurrence::parse("thu,mon", anchor).unwrap(), Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+        assert_eq!(Recurrence::parse("monthly", anchor).unwrap(), Recurrence::Monthly(16));
+        assert_eq!(Recurrence::parse("31st", anchor).unwrap(), Recurrence::Monthly(31));
+        assert_eq!(Recurrence::parse("2w", anchor).unwrap(), Recurrence::Every(14));
+        assert!(Recurrence::parse("sometimes", anchor).is_err());
+        assert!(Recurrence::parse("0d", anchor).is_err());
+        assert!(Recurrence::parse("32nd", anchor).is_err());
+        assert_eq!(Recurrence::parse("3660d", anchor).unwrap(), Recurrence::Every(3660));
+        assert!(Recurrence::parse("3661d", anchor).is_err());
+        assert!(Recurrence::parse("999999999w", anchor).is_err());
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let friday = date(2026, 10, 16);
+
+        assert_eq!(Recurrence::Daily.next_after(friday), Some(date(2026, 10, 17)));
+        assert_eq!(Recurrence::Every(3).next_after(friday), Some(date(2026, 10, 19)));
+        assert_eq!(Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]).next_after(friday), Some(date(2026, 10, 19)));
+        assert_eq!(Recurrence::Weekly(vec![Weekday::Fri]).next_after(friday), Some(date(2026, 10, 23)));
+        assert_eq!(Recurrence::Monthly(16).next_after(friday), Some(date(2026, 11, 16)));
+        assert_eq!(Recurrence::Monthly(31).next_after(date(2027, 1, 31)), Some(date(2027, 2, 28)));
+
+        assert_eq!(Recurrence::Daily.next_after(NaiveDate::MAX), None);
+        assert_eq!(Recurrence::Every(999_999_999).next_after(friday), None);
+        assert_eq!(Recurrence::Weekly(vec![Weekday::Mon]).next_after(NaiveDate::MAX), None);
+        assert_eq!(Recurrence::Monthly(1).next_after(NaiveDate::MAX), None);
+    }
+
+    #[test]
+    fn test_first_occurrence() {
+        let friday = date(2026, 10, 16);
+
+        assert_eq!(Recurrence::Weekly(vec![Weekday::Fri]).first_on_or_after(friday), Some(friday));
+        assert_eq!(Recurrence::Monthly(1).first_on_or_after(friday), Some(date(2026, 11, 1)));
+        assert_eq!(Recurrence::Every(5).first_on_or_after(friday), Some(friday));
+    }
+}
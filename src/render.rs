@@ -1,36 +1,146 @@
 use colored::*;
-use chrono::{DateTime, Utc}; 
-use crate::config::Config;
-use crate::todo::Todo;
+use chrono::{DateTime, Local, NaiveDate, Utc}; 
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env;
+use std::path::Path;
+use crate::cli::ColorChoice;
+use crate::config::{Config, ConfigOption, ConfigSource};
+use crate::journal::JournalEntry;
+use crate::search::SearchResult;
+use crate::theme::{Element, Glyph, Theme};
+use crate::todo::{Board, Priority, Todo};
+use crate::view::{value_enum_name, View};
 
-/// Renders a single todo item based on configuration settings.
-pub fn render_todo(todo: &Todo, _config: &Config, max_indent_count: usize) {
+/// Decide whether all output is colored.
+///
+/// With `ColorChoice::Auto`, output is colored if it goes to a terminal, `NO_COLOR` is not set and `TERM` is not
+/// `dumb`. The other choices override the environment.
+pub fn set_color_choice(choice: ColorChoice) {
+    match choice {
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+        ColorChoice::Auto if env::var("TERM").is_ok_and(|term| term == "dumb") => colored::control::set_override(false),
+        ColorChoice::Auto => {}
+    }
+}
+
+/// Formats the line of a single todo item in the style of the theme.
+///
+/// Subtasks are indented by their `depth` in the tree, and `progress` holds the number of
+/// completed and total subtasks of a parent todo.
+fn format_todo(todo: &Todo, theme: &Theme, max_indent_count: usize, depth: usize, progress: Option<(usize, usize)>) -> String {
     let indent = " ".repeat(max_indent_count - todo.id.to_string().len());
-    let id_display = format!("{}.", todo.id).dimmed();
+    let id_display = theme.paint(Element::Id, &format!("{}.", todo.id));
+    let tree_indent = "  ".repeat(depth);
 
     let status = if todo.is_complete {
-        "[✔]".dimmed()
+        theme.paint(Element::Done, theme.glyph(Glyph::Done))
     } else {
-        "[ ]".normal()
+        theme.paint(Element::Pending, theme.glyph(Glyph::Pending))
     };
 
     let description = if todo.is_complete {
-        todo.desc.dimmed()
+        theme.paint(Element::Done, &todo.desc)
+    } else if theme.show_age {
+        format!("{} {}", theme.paint(Element::Pending, &todo.desc), theme.paint(Element::Age, &days_since(todo.timestamp))).normal()
+    } else {
+        theme.paint(Element::Pending, &todo.desc)
+    };
+    let description = match todo.priority.label() {
+        Some(label) if todo.is_complete => format!("{} {}", theme.paint(Element::Done, label), description).normal(),
+        Some(label) => format!("{} {}", color_priority(label, todo.priority, theme), description).normal(),
+        None => description,
+    };
+
+    let description = match progress {
+        Some((done, total)) => format!("{} {}", description, theme.paint(Element::Progress, &format!("[{}/{}]", done, total))).normal(),
+        None => description,
+    };
+
+    let due = match todo.due {
+        Some(due) if !todo.is_complete => format_due(due, theme),
+        _ => "".normal(),
+    };
+    let recurrence = match &todo.recurrence {
+        Some(recurrence) => theme.paint(Element::Recur, &format!("{} {}", theme.glyph(Glyph::Recur), recurrence)),
+        None => "".normal(),
+    };
+
+    let tags = todo.tags.iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let tags = if todo.is_complete {
+        theme.paint(Element::Done, &tags)
     } else {
-        format!("{} {}", todo.desc, days_since(todo.timestamp).dimmed()).normal()
+        theme.paint(Element::Tags, &tags)
     };
 
     let star = if todo.is_starred {
-        "􀆿".yellow()
+        theme.paint(Element::Star, theme.glyph(Glyph::Star))
     } else {
         "".normal()
     };
 
-    println!(" {} {} {} {} {}", indent, id_display, status, description, star);
+    let notes = if todo.notes.is_empty() {
+        "".normal()
+    } else {
+        theme.paint(Element::Notes, theme.glyph(Glyph::Notes))
+    };
+
+    // Details the todo does not have are left out, so the line has no gaps or trailing spaces
+    let mut line = format!(" {} {} {}{} {}", indent, id_display, tree_indent, status, description);
+    for detail in [due, recurrence, tags, notes, star].iter().filter(|detail| !detail.is_empty()) {
+        line = format!("{} {}", line, detail);
+    }
+    line
 }
 
-/// Renders the list of todos.
-pub fn render_todo_list(todos: &[&Todo], config: &Config) {
+/// Renders a single todo item with its notes and metadata.
+pub fn render_todo_detail(todo: &Todo, board_name: &str, config: &Config) {
+    let theme = config.theme();
+    let status = if todo.is_complete { theme.glyph(Glyph::Done) } else { theme.glyph(Glyph::Pending) };
+    println!("{} {} {}", theme.paint(Element::Id, &format!("{}.", todo.id)), status, todo.desc.bold());
+
+    let tags = if todo.tags.is_empty() {
+        "-".to_string()
+    } else {
+        todo.tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ")
+    };
+    let completed = todo.completed_at
+        .map(format_timestamp)
+        .unwrap_or_else(|| "-".to_string());
+
+    println!("  {}     {}", "Board:".dimmed(), board_name);
+    if let Some(parent) = todo.parent {
+        println!("  {}    {}", "Parent:".dimmed(), parent);
+    }
+    println!("  {}      {}", "Tags:".dimmed(), theme.paint(Element::Tags, &tags));
+    println!("  {}   {}", "Starred:".dimmed(), if todo.is_starred { "yes" } else { "no" });
+    println!("  {}  {}", "Priority:".dimmed(), todo.priority.label().map_or("-".normal(), |label| color_priority(label, todo.priority, &theme)));
+    println!("  {}   {} ({} ago)", "Created:".dimmed(), format_timestamp(todo.timestamp), days_since(todo.timestamp));
+    if let Some(due) = todo.due {
+        println!("  {}       {} ({})", "Due:".dimmed(), due.format("%Y-%m-%d"), format_due(due, &theme));
+    }
+    println!("  {} {}", "Completed:".dimmed(), completed);
+    if let Some(recurrence) = &todo.recurrence {
+        println!("  {}   {}", "Repeats:".dimmed(), recurrence);
+    }
+
+    if !todo.notes.is_empty() {
+        println!("\n  {}", "Notes:".underline());
+        for line in todo.notes.lines() {
+            println!("    {}", line);
+        }
+    }
+}
+
+/// Renders the list of todos as a tree of todos and their subtasks.
+///
+/// Todos whose parent is not part of `todos` are rendered at the top level. If the configuration defines a Lua
+/// `format_todo` function, it formats the lines of the todos.
+pub fn render_todo_list(todos: &[&Todo], board_name: &str, config: &Config) {
+    let theme = config.theme();
     let max_id_width = todos.iter()
         .map(|todo| todo.id)
         .max()
@@ -38,19 +148,230 @@ pub fn render_todo_list(todos: &[&Todo], config: &Config) {
         .to_string()
         .len();
 
-    let status_summary = format_status_summary(todos);
-    let title = "Your todos:".underline();
-    println!("{} {}", title, status_summary);
+    let status_summary = theme.paint(Element::Summary, &format_status_summary(todos));
+    println!("{} {}", theme.paint(Element::Title, &theme.title), status_summary);
 
+    let ids: HashSet<usize> = todos.iter().map(|todo| todo.id).collect();
+    let mut children: HashMap<usize, Vec<&Todo>> = HashMap::new();
     for todo in todos {
-        render_todo(todo, config, max_id_width);
+        if let Some(parent) = todo.parent.filter(|parent| ids.contains(parent)) {
+            children.entry(parent).or_default().push(todo);
+        }
+    }
+
+    let mut renderer = TreeRenderer { config, theme, board_name, max_id_width, children, use_format_function: true };
+    for todo in todos.iter().filter(|todo| todo.parent.is_none_or(|parent| !ids.contains(&parent))) {
+        renderer.render(todo, 0);
     }
 
     let completion_rate = calculate_completion_rate(todos);
-    let completed_string = format!("\n{}% of all todos complete!", completion_rate).dimmed();
+    let completed_string = renderer.theme.paint(Element::Summary, &format!("\n{}% of all todos complete!", completion_rate));
     println!("{}", completed_string);
 }
 
+/// Struct holding what is needed to render the todos of a todo list as a tree
+struct TreeRenderer<'a> {
+    config: &'a Config,
+    theme: Theme,
+    board_name: &'a str,
+    max_id_width: usize,
+    children: HashMap<usize, Vec<&'a Todo>>,
+    /// Whether to call the Lua `format_todo` function, which is no longer called once it fails
+    use_format_function: bool,
+}
+
+impl TreeRenderer<'_> {
+    /// Renders a todo item followed by all of its subtasks.
+    fn render(&mut self, todo: &Todo, depth: usize) {
+        let progress = self.children.contains_key(&todo.id)
+            .then(|| subtask_progress(todo.id, &self.children));
+        println!("{}", self.line(todo, depth, progress));
+
+        for child in self.children.get(&todo.id).cloned().into_iter().flatten() {
+            self.render(child, depth + 1);
+        }
+    }
+
+    /// Formats the line of a todo item with the Lua `format_todo` function, falling back to the theme if the function
+    /// is not defined, returns `nil` or fails.
+    fn line(&mut self, todo: &Todo, depth: usize, progress: Option<(usize, usize)>) -> String {
+        if self.use_format_function {
+            match self.config.hooks.format_todo(todo, self.board_name, depth) {
+                Ok(Some(line)) => return line,
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Error: {:#}", e);
+                    self.use_format_function = false;
+                }
+            }
+        }
+        format_todo(todo, &self.theme, self.max_id_width, depth, progress)
+    }
+}
+/// Counts the completed and total subtasks of a todo, only counting subtasks without subtasks of their own.
+fn subtask_progress(id: usize, children: &HashMap<usize, Vec<&Todo>>) -> (usize, usize) {
+    children.get(&id).into_iter().flatten().fold((0, 0), |(done, total), child| {
+        if children.contains_key(&child.id) {
+            let (child_done, child_total) = subtask_progress(child.id, children);
+            (done + child_done, total + child_total)
+        } else {
+            (done + usize::from(child.is_complete), total + 1)
+        }
+    })
+}
+
+/// Renders the list of boards, highlighting the active one.
+pub fn render_board_list(boards: &BTreeMap<String, Board>, active_board: &str) {
+    println!("{}", "Your boards:".underline());
+
+    for (name, board) in boards {
+        let todos: Vec<&Todo> = board.todos.iter().collect();
+        let status_summary = format_status_summary(&todos);
+
+        if name == active_board {
+            println!("  {} {} {}", "*".yellow(), name.bold(), status_summary.dimmed());
+        } else {
+            println!("    {} {}", name, status_summary.dimmed());
+        }
+    }
+}
+
+/// Renders all tags with the number of todos carrying them.
+pub fn render_tag_list(tag_counts: &BTreeMap<&str, usize>) {
+    println!("{}", "Your tags:".underline());
+
+    for (tag, count) in tag_counts {
+        println!("  {} {}", format!("#{}", tag).cyan(), format!("({})", count).dimmed());
+    }
+}
+
+/// Renders the results of a search, best first, with the characters matched by the search highlighted.
+///
+/// Results are rendered as a flat list in the style of the theme, without calling the Lua `format_todo` function.
+/// The first line of the notes with a match is shown below a result.
+pub fn render_search_results(results: &[SearchResult], found: usize, config: &Config) {
+    let theme = config.theme();
+    let max_id_width = results.iter().map(|result| result.todo.id).max().unwrap_or(0).to_string().len();
+
+    let summary = format!("[{} of {}]", results.len(), found);
+    println!("{} {}", theme.paint(Element::Title, "Search results:"), theme.paint(Element::Summary, &summary));
+
+    for result in results {
+        let todo = result.todo;
+        let text = if todo.is_complete { Element::Done } else { Element::Pending };
+        let indent = " ".repeat(max_id_width - todo.id.to_string().len());
+        let status = if todo.is_complete { theme.glyph(Glyph::Done) } else { theme.glyph(Glyph::Pending) };
+
+        let mut line = format!(" {} {} {}", indent, theme.paint(Element::Id, &format!("{}.", todo.id)), theme.paint(text, status));
+        if let Some(label) = todo.priority.label() {
+            line = format!("{} {}", line, color_priority(label, todo.priority, &theme));
+        }
+        line = format!("{} {}", line, highlight(&todo.desc, &result.description, text, &theme));
+        if let Some(due) = todo.due.filter(|_| !todo.is_complete) {
+            line = format!("{} {}", line, format_due(due, &theme));
+        }
+        let tag_element = if todo.is_complete { Element::Done } else { Element::Tags };
+        for (tag, indices) in todo.tags.iter().zip(&result.tags) {
+            line = format!("{} {}{}", line, theme.paint(tag_element, "#"), highlight(tag, indices, tag_element, &theme));
+        }
+        if todo.is_starred {
+            line = format!("{} {}", line, theme.paint(Element::Star, theme.glyph(Glyph::Star)));
+        }
+        println!("{}", line);
+
+        let mut offset = 0;
+        for notes_line in todo.notes.split('\n') {
+            let length = notes_line.chars().count();
+            let indices: BTreeSet<usize> = result.notes.range(offset..offset + length).map(|index| index - offset).collect();
+            if !indices.is_empty() {
+                let notes_indent = " ".repeat(max_id_width + 4);
+                println!(" {}{} {}", notes_indent, theme.paint(Element::Notes, theme.glyph(Glyph::Notes)), highlight(notes_line, &indices, Element::Notes, &theme));
+                break;
+            }
+            offset += length + 1;
+        }
+    }
+}
+
+/// Styles the characters of a text at the given indices as search highlights, and the other characters as `element`.
+fn highlight(text: &str, indices: &BTreeSet<usize>, element: Element, theme: &Theme) -> String {
+    let mut styled = String::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (index, c) in text.chars().enumerate() {
+        let matched = indices.contains(&index);
+        if matched != run_matched && !run.is_empty() {
+            styled.push_str(&theme.paint(if run_matched { Element::Highlight } else { element }, &run).to_string());
+            run.clear();
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        styled.push_str(&theme.paint(if run_matched { Element::Highlight } else { element }, &run).to_string());
+    }
+    styled
+}
+
+/// Renders all views with the number of todos they show, their query and how they list their todos.
+pub fn render_view_list(views: &[(&str, &View, usize)]) {
+    println!("{}", "Your views:".underline());
+
+    if views.is_empty() {
+        println!("  {}", "No saved views".dimmed());
+    }
+    for (name, view, count) in views {
+        let mut options = Vec::new();
+        if let Some(sort) = &view.sort {
+            options.push(format!("sorted by {}", value_enum_name(sort)));
+        }
+        if let Some(format) = &view.format {
+            options.push(format!("as {}", value_enum_name(format)));
+        }
+        let options = if options.is_empty() { String::new() } else { format!(" ({})", options.join(", ")) };
+        println!("  {} {} {}{}", name.bold(), format!("({})", count).dimmed(), view.query, options.dimmed());
+    }
+}
+
+/// Renders the operations that can be undone, most recent first, and those that can be redone.
+pub fn render_history(undo_entries: &[JournalEntry], redo_entries: &[JournalEntry]) {
+    println!("{}", "Your history:".underline());
+
+    if undo_entries.is_empty() {
+        println!("  {}", "Nothing to undo".dimmed());
+    }
+    for (step, entry) in undo_entries.iter().rev().enumerate() {
+        println!("  {} {} {}", format!("{}.", step + 1).dimmed(), format_timestamp(entry.timestamp).dimmed(), entry.command);
+    }
+
+    if !redo_entries.is_empty() {
+        println!("\n{}", "Undone:".underline());
+        for entry in redo_entries.iter().rev() {
+            println!("  {} {}", format_timestamp(entry.timestamp).dimmed(), entry.command.dimmed());
+        }
+    }
+}
+
+/// Renders the effective settings with the source of their values, dimming the defaults.
+pub fn render_config(config_path: &Path, options: &[ConfigOption]) {
+    println!("{} {}", "Your configuration:".underline(), format!("({})", config_path.display()).dimmed());
+
+    let key_width = options.iter().map(|option| option.key.len()).max().unwrap_or_default();
+    for option in options {
+        let value = match &option.value {
+            Some(value) => value.to_toml().normal(),
+            None => "unset".dimmed(),
+        };
+        let source = format!("({})", option.source);
+        let source = match option.source {
+            ConfigSource::Default => source.dimmed(),
+            ConfigSource::File => source.green(),
+            ConfigSource::Env => source.yellow(),
+        };
+        println!("  {:<width$} = {} {}", option.key, value, source, width = key_width);
+    }
+}
+
 /// Calculates the number of days since the given date.
 fn days_since(date: DateTime<Utc>) -> String {
     let now = Utc::now();
@@ -58,17 +379,51 @@ fn days_since(date: DateTime<Utc>) -> String {
     format!("{}d", diff.num_days())
 }
 
+/// Colors a priority label by its urgency.
+fn color_priority(label: &str, priority: Priority, theme: &Theme) -> ColoredString {
+    match priority {
+        Priority::High => theme.paint(Element::High, label),
+        Priority::Medium => theme.paint(Element::Medium, label),
+        Priority::Low => theme.paint(Element::Low, label),
+        Priority::None => label.normal(),
+    }
+}
+
+/// Formats a due date relative to today, colored by its urgency.
+fn format_due(due: NaiveDate, theme: &Theme) -> ColoredString {
+    let days = (due - Local::now().date_naive()).num_days();
+    match days {
+        0 => theme.paint(Element::Due, "due today"),
+        d if d > 0 => theme.paint(Element::Due, &format!("due in {}d", d)),
+        d => theme.paint(Element::Overdue, &format!("overdue {}d", -d)),
+    }
+}
+
+/// Formats a timestamp in the local timezone.
+fn format_timestamp(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Counts the completed and total todos of the list.
+///
+/// Todos with subtasks are left out, as their progress is given by their subtasks.
+fn count_completed(todos: &[&Todo]) -> (usize, usize) {
+    let parents: HashSet<usize> = todos.iter().filter_map(|t| t.parent).collect();
+    let leaves = todos.iter().filter(|t| !parents.contains(&t.id));
+    leaves.fold((0, 0), |(done, total), t| (done + usize::from(t.is_complete), total + 1))
+}
+
 /// Formats a summary of the todo list's completion status.
-fn format_status_summary(todos: &[&Todo]) -> ColoredString {
-    let done_count = todos.iter().filter(|t| t.is_complete).count();
-    format!("[{}/{}]", done_count, todos.len()).dimmed()
+fn format_status_summary(todos: &[&Todo]) -> String {
+    let (done_count, total_count) = count_completed(todos);
+    format!("[{}/{}]", done_count, total_count)
 }
 
 /// Calculates the completion rate of the todo list.
 fn calculate_completion_rate(todos: &[&Todo]) -> usize {
-    if todos.is_empty() {
+    let (done_count, total_count) = count_completed(todos);
+    if total_count == 0 {
         return 0;
     }
-    let done_count = todos.iter().filter(|t| t.is_complete).count();
-    100 * done_count / todos.len()
+    100 * done_count / total_count
 }
@@ -0,0 +1,282 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::BTreeSet;
+use crate::todo::Todo;
+
+/// The lowest score of a fuzzy match per character of the word, below which the characters are too scattered to count
+/// as a match
+const MIN_SCORE_PER_CHAR: i64 = 8;
+
+/// The score per character of a word matching a word of a field with a typo, which ranks it below fuzzy matches
+const TYPO_SCORE_PER_CHAR: i64 = 6;
+
+/// Matches in the notes count for less than matches in the description and tags, by this divisor
+const NOTES_DIVISOR: i64 = 2;
+
+/// Struct representing a todo found by a search, with the characters the search words matched
+pub struct SearchResult<'a> {
+    /// The todo that was found
+    pub todo: &'a Todo,
+    /// The indices of the matched characters of the description
+    pub description: BTreeSet<usize>,
+    /// The indices of the matched characters of every tag, in the order of the tags
+    pub tags: Vec<BTreeSet<usize>>,
+    /// The indices of the matched characters of the notes
+    pub notes: BTreeSet<usize>,
+}
+
+/// Enum representing the part of a todo a word matched
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Description,
+    Tag(usize),
+    Notes,
+}
+
+/// Struct representing the best match of a word in a todo
+struct WordMatch {
+    field: Field,
+    score: i64,
+    indices: Vec<usize>,
+}
+
+/// Struct searching todos by fuzzy matching words against their description, tags and notes
+pub struct Searcher {
+    matcher: SkimMatcherV2,
+    words: Vec<String>,
+}
+
+impl Searcher {
+    /// Create a searcher for the words of a query.
+    ///
+    /// Words match case-insensitively unless they contain uppercase characters. A leading `#` of a word is ignored,
+    /// so `#work` finds the tag `work`.
+    pub fn new(query: &str) -> Self {
+        let words = query.split_whitespace()
+            .map(|word| word.trim_start_matches('#'))
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect();
+        Searcher { matcher: SkimMatcherV2::default().smart_case(), words }
+    }
+
+    /// Search todos for the words of the query.
+    ///
+    /// A todo is found if every word matches its description, one of its tags or its notes, where the words may be
+    /// in any order, may skip characters and may have a typo. The score of a todo is the sum of the scores of the best
+    /// match of every word. The matched characters are only determined for the results that are returned, so
+    /// searching stays fast for long lists.
+    ///
+    /// # Arguments
+    ///
+    /// `todos` - The todos to search.
+    /// `limit` - The maximum number of results.
+    ///
+    /// # Returns
+    ///
+    /// `(Vec<SearchResult>, usize)` - The best results, best first and open todos before done todos of the same score,
+    /// and the number of todos found.
+    pub fn search<'a>(&self, todos: &'a [Todo], limit: usize) -> (Vec<SearchResult<'a>>, usize) {
+        if self.words.is_empty() {
+            return (Vec::new(), 0);
+        }
+
+        let mut scored: Vec<(i64, &Todo)> = todos.iter()
+            .filter_map(|todo| self.score(todo).map(|score| (score, todo)))
+            .collect();
+        let found = scored.len();
+
+        let order = |(score, todo): &(i64, &Todo)| (std::cmp::Reverse(*score), todo.is_complete, todo.id);
+        if scored.len() > limit && limit > 0 {
+            scored.select_nth_unstable_by_key(limit - 1, order);
+        }
+        scored.truncate(limit);
+        scored.sort_by_key(order);
+
+        let results = scored.into_iter()
+            .map(|(_, todo)| self.highlight(todo))
+            .collect();
+        (results, found)
+    }
+
+    /// Helper function to get the score of a todo, or `None` if a word does not match it
+    fn score(&self, todo: &Todo) -> Option<i64> {
+        self.words.iter()
+            .map(|word| self.match_word(todo, word, false).map(|word_match| word_match.score))
+            .sum()
+    }
+
+    /// Helper function to find the matched characters of a found todo
+    fn highlight<'a>(&self, todo: &'a Todo) -> SearchResult<'a> {
+        let mut result = SearchResult {
+            todo,
+            description: BTreeSet::new(),
+            tags: vec![BTreeSet::new(); todo.tags.len()],
+            notes: BTreeSet::new(),
+        };
+        for word_match in self.words.iter().filter_map(|word| self.match_word(todo, word, true)) {
+            let indices = match word_match.field {
+                Field::Description => &mut result.description,
+                Field::Tag(index) => &mut result.tags[index],
+                Field::Notes => &mut result.notes,
+            };
+            indices.extend(word_match.indices);
+        }
+        result
+    }
+
+    /// Helper function to find the best match of a word in the description, the tags and the notes of a todo
+    fn match_word(&self, todo: &Todo, word: &str, with_indices: bool) -> Option<WordMatch> {
+        let fields = std::iter::once((Field::Description, todo.desc.as_str()))
+            .chain(todo.tags.iter().enumerate().map(|(index, tag)| (Field::Tag(index), tag.as_str())))
+            .chain(std::iter::once((Field::Notes, todo.notes.as_str())));
+
+        fields
+            .filter_map(|(field, text)| {
+                let (score, indices) = self.match_text(text, word, with_indices)?;
+                let score = if field == Field::Notes { score / NOTES_DIVISOR } else { score };
+                Some(WordMatch { field, score, indices })
+            })
+            // The first field wins a tie, which prefers the description
+            .fold(None, |best: Option<WordMatch>, candidate| match best {
+                Some(best) if best.score >= candidate.score => Some(best),
+                _ => Some(candidate),
+            })
+    }
+
+    /// Helper function to match a word against a text, fuzzily or with a typo
+    fn match_text(&self, text: &str, word: &str, with_indices: bool) -> Option<(i64, Vec<usize>)> {
+        let length = word.chars().count() as i64;
+        let fuzzy = if with_indices {
+            self.matcher.fuzzy_indices(text, word)
+        } else {
+            self.matcher.fuzzy_match(text, word).map(|score| (score, Vec::new()))
+        };
+        match fuzzy {
+            Some((score, indices)) if score >= length * MIN_SCORE_PER_CHAR => Some((score, indices)),
+            _ => match_typo(text, word).map(|indices| (length * TYPO_SCORE_PER_CHAR, indices)),
+        }
+    }
+}
+
+/// Helper function to find a word of a text that differs from `word` by at most one typo, or two for long words.
+///
+/// # Returns
+///
+/// `Option<Vec<usize>>` - The indices of the characters of the word of the text, or `None` if there is none.
+fn match_typo(text: &str, word: &str) -> Option<Vec<usize>> {
+    let word: Vec<char> = word.to_lowercase().chars().collect();
+    let max_typos = match word.len() {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut start = 0;
+    while start < chars.len() {
+        if !chars[start].is_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let end = (start..chars.len()).find(|&index| !chars[index].is_alphanumeric()).unwrap_or(chars.len());
+        if (end - start).abs_diff(word.len()) <= max_typos {
+            let candidate: Vec<char> = chars[start..end].iter().flat_map(|c| c.to_lowercase()).collect();
+            if edit_distance(&candidate, &word) <= max_typos {
+                return Some((start..end).collect());
+            }
+        }
+        start = end;
+    }
+    None
+}
+
+/// Helper function to count the insertions, deletions, substitutions and swaps of adjacent characters that turn one
+/// word into another
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use crate::search::{edit_distance, Searcher};
+    use crate::todo::Todo;
+
+    fn todo(id: usize, desc: &str, tags: &[&str], notes: &str) -> Todo {
+        Todo {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            notes: notes.to_string(),
+            ..Todo::new(id, desc)
+        }
+    }
+
+    fn todos() -> Vec<Todo> {
+        vec![
+            todo(1, "Deploy staging server", &["work"], ""),
+            todo(2, "Buy milk", &["home"], ""),
+            todo(3, "Write the release notes", &["work"], "Mention the staging deploy"),
+            todo(4, "Water the plants", &["home"], ""),
+        ]
+    }
+
+    fn ids(query: &str) -> Vec<usize> {
+        let todos = todos();
+        let (results, _) = Searcher::new(query).search(&todos, 10);
+        results.iter().map(|result| result.todo.id).collect()
+    }
+
+    #[test]
+    fn test_search_ranks_matches() {
+        assert_eq!(ids("deploy staging"), vec![1, 3]);
+        assert_eq!(ids("staging deploy"), vec![1, 3]);
+        assert_eq!(ids("dply stg"), vec![1, 3]);
+        assert_eq!(ids("deplyo"), vec![1, 3]);
+        assert_eq!(ids("#home"), vec![2, 4]);
+        assert_eq!(ids("plants home"), vec![4]);
+        assert_eq!(ids("xylophone"), Vec::<usize>::new());
+        assert_eq!(ids(""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_search_highlights_matches() {
+        let todos = todos();
+        let (results, found) = Searcher::new("staging wrk").search(&todos, 1);
+        assert_eq!(found, 2);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].description, BTreeSet::from([7, 8, 9, 10, 11, 12, 13]));
+        assert_eq!(results[0].tags, vec![BTreeSet::from([0, 2, 3])]);
+        assert!(results[0].notes.is_empty());
+
+        let (results, _) = Searcher::new("mnetion").search(&todos, 10);
+        assert_eq!(results[0].notes, BTreeSet::from([0, 1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |word: &str| word.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("deploy"), &chars("deploy")), 0);
+        assert_eq!(edit_distance(&chars("deplyo"), &chars("deploy")), 1);
+        assert_eq!(edit_distance(&chars("stagin"), &chars("staging")), 1);
+        assert_eq!(edit_distance(&chars("server"), &chars("sever")), 1);
+        assert_eq!(edit_distance(&chars("milk"), &chars("silk")), 1);
+        assert_eq!(edit_distance(&chars("plants"), &chars("planet")), 2);
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use crate::migration::SCHEMA_VERSION;
+use crate::patch::Patch;
+use crate::storage::{IdSpace, Storage};
+use crate::todo::{read_todo_list_from_file, Todo, TodoList};
+use crate::utils::{backup_todo_file, confirm, get_latest_backup_file_path, get_previous_version_file_path, write_file_atomically};
+
+/// Struct representing a todo list stored as a single JSON file.
+///
+/// The file is replaced atomically on every save and its previous version is kept as `<file>.bak`.
+pub struct JsonStorage {
+    file_path: PathBuf,
+    /// The path the previous version, the backups and the other files belonging to the todo file are named after
+    companion_path: PathBuf,
+    /// Whether the todo list was recovered from a previous version because the file is corrupted
+    is_recovered: bool,
+    /// The todo list read for accessing single todos, which has to be read completely from a JSON file
+    loaded: Option<TodoList>,
+}
+
+/// Function deciding whether to load the todo list from a previous version of a corrupted file
+type AcceptRecovery<'a> = &'a mut dyn FnMut(&Path) -> Result<bool>;
+
+impl JsonStorage {
+    /// Create a storage for the JSON file at the given path
+    pub fn new(file_path: PathBuf) -> Self {
+        JsonStorage::with_companion_path(file_path.clone(), file_path)
+    }
+
+    /// Create a storage for the JSON file at the given path, keeping the files belonging to it named after another path
+    pub fn with_companion_path(file_path: PathBuf, companion_path: PathBuf) -> Self {
+        JsonStorage { file_path, companion_path, is_recovered: false, loaded: None }
+    }
+
+    /// Helper function to get the todo list read for accessing single todos, reading it on first use
+    fn loaded(&mut self) -> Result<&mut TodoList> {
+        if self.loaded.is_none() {
+            let todo_list = self.load()?;
+            // An upgraded or recovered todo list is written right away, even if no todo is changed
+            if todo_list.needs_save() {
+                self.save(&todo_list)?;
+            }
+            self.loaded = Some(todo_list);
+        }
+        Ok(self.loaded.as_mut().expect("todo list was loaded"))
+    }
+
+    /// Helper function to load the todo list, asking `accept` whether to recover a corrupted file from a previous
+    /// version, or pointing to the previous versions in the error if `accept` is not given
+    fn load_with(&mut self, accept: Option<AcceptRecovery>) -> Result<TodoList> {
+        let todo_list = match read_todo_list_from_file(&self.file_path) {
+            Ok(todo_list) => todo_list,
+            Err(e) => {
+                let todo_list = recover_todo_list(&self.file_path, &self.companion_path, e, accept)?;
+                self.is_recovered = true;
+                return Ok(todo_list);
+            }
+        };
+
+        if let Some(version) = todo_list.migrated_from {
+            let content = fs::read_to_string(&self.file_path).context("Failed to read todo file")?;
+            let backup_path = backup_todo_file(&self.companion_path, &content).context("Failed to back up the todo file before upgrading it")?;
+            eprintln!(
+                "Upgraded the todo list from version {} to {}. The previous file was backed up to {:?}",
+                version, SCHEMA_VERSION, backup_path
+            );
+        }
+        Ok(todo_list)
+    }
+}
+
+impl Storage for JsonStorage {
+    fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    fn companion_path(&self) -> &Path {
+        &self.companion_path
+    }
+
+    /// Load the todo list from the JSON file.
+    ///
+    /// If the file was written by an older version, it is backed up before the upgraded todo list is saved over it.
+    /// If the file is corrupted, the user is offered to load a previous version instead.
+    fn load(&mut self) -> Result<TodoList> {
+        if !std::io::stdin().is_terminal() {
+            return self.load_with(None);
+        }
+        // The todo list read for accessing single todos is up to date, as all changes to it were written
+        if let Some(todo_list) = self.loaded.take() {
+            return Ok(todo_list);
+        }
+        self.load_with(Some(&mut |candidate: &Path| confirm(&format!("Load the todo list from {:?} instead?", candidate))))
+    }
+
+    /// Replace the JSON file with the todo list, keeping its previous version as `<companion>.bak`.
+    ///
+    /// A corrupted file the todo list was recovered from is not kept, so it does not replace the good previous version.
+    fn save(&mut self, todo_list: &TodoList) -> Result<()> {
+        let content = serde_json::to_string_pretty(todo_list)
+            .context("Failed to serialize todo list")?;
+
+        let previous_path = get_previous_version_file_path(&self.companion_path);
+        write_file_atomically(&self.file_path, content.as_bytes(), (!self.is_recovered).then_some(previous_path.as_path()))
+            .context("Failed to write todo file")
+    }
+
+    fn active_board(&mut self) -> Result<String> {
+        Ok(self.loaded()?.active_board.clone())
+    }
+
+    fn id_space(&mut self, board: &str) -> Result<IdSpace> {
+        let todo_list = self.loaded()?;
+        Ok(match todo_list.boards.get(board) {
+            Some(board) => IdSpace { available_ids: board.available_ids.clone(), len: board.todos.len() },
+            None => IdSpace { available_ids: Default::default(), len: 0 },
+        })
+    }
+
+    fn query(&mut self, board: &str, ids: &[usize], with_descendants: bool) -> Result<Vec<(usize, Todo)>> {
+        let todo_list = self.loaded()?;
+        let Some(board) = todo_list.boards.get(board) else {
+            return Ok(Vec::new());
+        };
+        let ids = if with_descendants { board.with_descendants(ids.to_vec()) } else { ids.to_vec() };
+        Ok(board.todos.iter()
+            .enumerate()
+            .filter(|(_, todo)| ids.contains(&todo.id))
+            .map(|(index, todo)| (index, todo.clone()))
+            .collect())
+    }
+
+    /// Apply the patch to the todo list and replace the JSON file with it, as the file can only be written completely
+    fn update(&mut self, patch: &Patch) -> Result<()> {
+        self.loaded()?;
+        let mut todo_list = self.loaded.take().expect("todo list was loaded");
+        patch.apply(&mut todo_list);
+        let result = self.save(&todo_list);
+        self.loaded = Some(todo_list);
+        result
+    }
+}
+
+/// Helper function to recover from a todo file that cannot be parsed.
+///
+/// The previous version of the file (`<companion>.bak`) and the most recent backup are offered to the user in
+/// this order. If one is accepted, the corrupted file is kept as `<companion>.corrupt` and the accepted version
+/// is loaded instead.
+///
+/// # Arguments
+///
+/// `file_path` - The path to the corrupted todo file.
+/// `companion_path` - The path the previous versions of the todo file are named after.
+/// `error` - The error that occurred while reading the file.
+/// `accept` - Asks whether to load a previous version, or `None` if nobody can be asked.
+///
+/// # Returns
+///
+/// `Result<TodoList>` - The todo list loaded from an accepted previous version, or the original error if the file
+/// is not corrupted or no previous version was accepted.
+fn recover_todo_list(file_path: &Path, companion_path: &Path, error: anyhow::Error, mut accept: Option<AcceptRecovery>) -> Result<TodoList> {
+    if error.downcast_ref::<serde_json::Error>().is_none() {
+        return Err(error);
+    }
+
+    let mut candidates = vec![get_previous_version_file_path(companion_path)];
+    if let Ok(Some(latest_backup)) = get_latest_backup_file_path(companion_path) {
+        candidates.push(latest_backup);
+    }
+
+    if accept.is_some() {
+        eprintln!("Error: {:#}", error);
+    }
+
+    let mut available = None;
+    for candidate in candidates.into_iter().filter(|candidate| candidate.exists()) {
+        let Ok(mut todo_list) = read_todo_list_from_file(&candidate) else {
+            continue;
+        };
+
+        let is_accepted = match accept.as_mut() {
+            Some(accept) => accept(&candidate)?,
+            None => false,
+        };
+        if is_accepted {
+            let corrupt_path = companion_path.with_extension("json.corrupt");
+            fs::copy(file_path, &corrupt_path).context("Failed to keep the corrupted todo file")?;
+            eprintln!("The corrupted todo file was kept at {:?}", corrupt_path);
+            // The recovered todo list replaces the corrupted file when it is saved
+            todo_list.is_modified = true;
+            return Ok(todo_list);
+        }
+        available.get_or_insert(candidate);
+    }
+
+    match available {
+        Some(candidate) => Err(error.context(format!("A previous version of the todo list is available at {:?}", candidate))),
+        None => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{JsonStorage, Storage};
+    use crate::todo::TodoList;
+    use std::fs;
+
+    #[test]
+    fn test_save_after_recovery_keeps_previous_version() {
+        let test_dir = std::env::temp_dir().join(format!("todo_recovery_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&test_dir).expect("Failed to create test directory");
+        let file_path = test_dir.join("todos.json");
+        let previous_path = test_dir.join("todos.json.bak");
+
+        let mut todo_list = TodoList::default();
+        todo_list.handle_cli(crate::cli::Pattern::Add { parent: None, args: vec!["First".to_string()] }).expect("Failed to add todo");
+        JsonStorage::new(file_path.clone()).save(&todo_list).expect("Failed to save todo list");
+        fs::copy(&file_path, &previous_path).expect("Failed to copy test file");
+        fs::write(&file_path, "{\"boards\": {").expect("Failed to corrupt test file");
+
+        let mut storage = JsonStorage::new(file_path.clone());
+        let recovered = storage.load_with(Some(&mut |candidate: &std::path::Path| Ok(candidate == previous_path))).expect("Failed to recover todo list");
+        storage.save(&recovered).expect("Failed to save recovered todo list");
+
+        let previous = JsonStorage::new(previous_path.clone()).load().expect("The previous version was overwritten");
+        assert_eq!(previous.boards["default"].todos.len(), 1);
+        assert!(test_dir.join("todos.json.corrupt").exists());
+        JsonStorage::new(file_path).load().expect("The recovered todo list was not saved");
+
+        fs::remove_dir_all(test_dir).expect("Failed to delete test directory");
+    }
+}
@@ -0,0 +1,302 @@
+mod json;
+mod sqlite;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::config::Config;
+use crate::patch::Patch;
+use crate::todo::{Todo, TodoList};
+use crate::utils::{get_data_dir, get_database_file_path, get_project_companion_path, get_todo_file_path};
+
+/// Name of the todo file of a project, which is discovered from the current directory upwards
+pub const PROJECT_FILE_NAME: &str = ".todo.json";
+
+pub use json::JsonStorage;
+pub use sqlite::SqliteStorage;
+
+/// Trait abstracting where and how the todo list is persisted
+pub trait Storage {
+    /// The path to the file holding the todo list
+    fn path(&self) -> &Path;
+
+    /// The path the lock, the journal, the previous version and the backups of the todo list are named after.
+    ///
+    /// This is the todo file itself, unless the todo file is a project file these files should not be placed next to.
+    fn companion_path(&self) -> &Path {
+        self.path()
+    }
+
+    /// Load the todo list, returning an empty todo list if nothing has been stored yet
+    fn load(&mut self) -> Result<TodoList>;
+
+    /// Save the todo list.
+    ///
+    /// Backends storing single todos only write the todos that changed since the todo list was loaded.
+    fn save(&mut self, todo_list: &TodoList) -> Result<()>;
+
+    /// The name of the active board, without loading the todo list
+    fn active_board(&mut self) -> Result<String>;
+
+    /// The IDs of a board new todos take their ID from, without loading the todos
+    fn id_space(&mut self, board: &str) -> Result<IdSpace>;
+
+    /// Read todos of a board by ID, without loading the other todos.
+    ///
+    /// # Arguments
+    ///
+    /// `board` - The name of the board.
+    /// `ids` - The IDs of the todos to read. IDs that do not exist are left out.
+    /// `with_descendants` - Whether to read all subtasks of the todos as well, including nested ones.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Vec<(usize, Todo)>>` - The todos with their index on the board, in the order of the board.
+    fn query(&mut self, board: &str, ids: &[usize], with_descendants: bool) -> Result<Vec<(usize, Todo)>>;
+
+    /// Read a single todo of a board by ID together with its index on the board, or `None` if it does not exist
+    fn get(&mut self, board: &str, id: usize) -> Result<Option<(usize, Todo)>> {
+        Ok(self.query(board, &[id], false)?.pop())
+    }
+
+    /// Write the parts of the todo list a patch touches, leaving all other todos as they are stored.
+    ///
+    /// A todo list loaded from the storage before does not see the changes, so it must not be saved afterwards.
+    fn update(&mut self, patch: &Patch) -> Result<()>;
+}
+
+/// Struct representing the IDs of a board that new todos take their ID from
+pub struct IdSpace {
+    /// The IDs of removed todos available for reuse
+    pub available_ids: BTreeSet<usize>,
+    /// The number of todos on the board
+    pub len: usize,
+}
+
+/// Enum representing the available storage backends
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A single JSON file, rewritten on every change
+    #[default]
+    Json,
+    /// An embedded SQLite database holding one row per todo
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// The backend a todo file is stored in by its extension: `.db` and `.sqlite` files are SQLite databases, and all
+    /// other files are JSON files
+    pub fn of_file(file: &Path) -> Self {
+        match file.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("db") || extension.eq_ignore_ascii_case("sqlite") => StorageBackend::Sqlite,
+            _ => StorageBackend::Json,
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = anyhow::Error;
+
+    /// Parses the name of a storage backend as written in the configuration
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "json" => Ok(StorageBackend::Json),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(anyhow!("Unknown storage backend '{}'. Use json or sqlite", value)),
+        }
+    }
+}
+
+impl fmt::Display for StorageBackend {
+    /// Formats the backend as written in the configuration
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageBackend::Json => write!(f, "json"),
+            StorageBackend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Open the storage of the backend selected in the configuration.
+///
+/// The todo list is stored in the first of the following files:
+///
+/// * The given file
+/// * The file named by the `TODO_FILE` environment variable
+/// * The project todo file `.todo.json` in the current directory or its closest parent having one, unless `global` is set
+/// * The todo file in the data directory
+///
+/// The backend selected in the configuration is used for the todo file in the data directory. A given file is stored in
+/// the backend its extension stands for, as is the file in `TODO_FILE`. Project todo files are always stored as JSON,
+/// so they can be checked into a repository, and their lock, journal and backups are kept in the data directory. A new
+/// SQLite database in the data directory imports the existing JSON todo list on its first load, so switching the
+/// backend keeps all todos.
+///
+/// # Arguments
+///
+/// `config` - The configuration selecting the backend and the data directory.
+/// `file` - The file given on the command line, if any.
+/// `global` - Whether to skip the search for a project todo file.
+///
+/// # Returns
+///
+/// `Result<Box<dyn Storage>>` - The opened storage, or an error if its file cannot be located or opened.
+pub fn open_storage(config: &Config, file: Option<PathBuf>, global: bool) -> Result<Box<dyn Storage>> {
+    let file = file.or_else(|| env::var_os("TODO_FILE").filter(|file| !file.is_empty()).map(PathBuf::from));
+    if file.is_none() && !global {
+        let current_dir = env::current_dir().context("Failed to determine the current directory")?;
+        if let Some(project_file) = find_project_file(&current_dir) {
+            return open_project_storage(config, project_file);
+        }
+    }
+
+    let storage: Box<dyn Storage> = match (config.storage, file) {
+        (_, Some(file)) => match StorageBackend::of_file(&file) {
+            StorageBackend::Json => Box::new(JsonStorage::new(file)),
+            StorageBackend::Sqlite => Box::new(SqliteStorage::open(file, None)?),
+        },
+        (StorageBackend::Json, None) => {
+            let data_dir = get_data_dir(config.data_dir.as_deref())?;
+            Box::new(JsonStorage::new(get_todo_file_path(&data_dir)))
+        }
+        (StorageBackend::Sqlite, None) => {
+            let data_dir = get_data_dir(config.data_dir.as_deref())?;
+            Box::new(SqliteStorage::open(get_database_file_path(&data_dir), Some(get_todo_file_path(&data_dir)))?)
+        }
+    };
+    Ok(storage)
+}
+
+/// Find the project todo file in a directory or its closest parent having one.
+///
+/// # Returns
+///
+/// `Option<PathBuf>` - The path to the project todo file, or `None` if there is none.
+fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE_NAME))
+        .find(|file| file.is_file())
+}
+
+/// Open the storage of a new project todo list in a directory.
+///
+/// Project todo lists are always stored as JSON, whichever backend is configured, so `init` never creates a database.
+///
+/// # Arguments
+///
+/// `config` - The configuration selecting the data directory.
+/// `dir` - The directory to create the project todo file in.
+///
+/// # Returns
+///
+/// `Result<Box<dyn Storage>>` - The storage of the project todo file to create, or an error if the directory already
+/// has one.
+pub fn new_project_storage(config: &Config, dir: &Path) -> Result<Box<dyn Storage>> {
+    let file = dir.join(PROJECT_FILE_NAME);
+    if file.exists() {
+        return Err(anyhow!("A project todo list already exists at {:?}", file));
+    }
+    open_project_storage(config, file)
+}
+
+/// Helper function to open the storage of a project todo file.
+///
+/// The files belonging to the todo file are kept in the data directory, so they do not clutter the repository.
+fn open_project_storage(config: &Config, project_file: PathBuf) -> Result<Box<dyn Storage>> {
+    let data_dir = get_data_dir(config.data_dir.as_deref())?;
+    let companion_path = get_project_companion_path(&data_dir, &project_file)?;
+    Ok(Box::new(JsonStorage::with_companion_path(project_file, companion_path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::Pattern;
+    use crate::config::Config;
+    use crate::storage::{find_project_file, new_project_storage, open_storage, JsonStorage, Storage, StorageBackend, PROJECT_FILE_NAME};
+    use crate::todo::TodoList;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_find_project_file_in_parent_directory() {
+        let project_dir = std::env::temp_dir().join(format!("todo_project_{}", std::process::id()));
+        let nested_dir = project_dir.join("src").join("nested");
+        fs::create_dir_all(&nested_dir).expect("Failed to create test directory");
+
+        assert_eq!(find_project_file(&nested_dir), None);
+
+        fs::write(project_dir.join(PROJECT_FILE_NAME), "{}").expect("Failed to write project file");
+        assert_eq!(find_project_file(&nested_dir), Some(project_dir.join(PROJECT_FILE_NAME)));
+
+        fs::remove_dir_all(project_dir).expect("Failed to delete test directory");
+    }
+
+    #[test]
+    fn test_given_file_is_stored_in_the_backend_of_its_extension() {
+        let sqlite_config = Config { storage: StorageBackend::Sqlite, ..Config::default() };
+        let json_path = PathBuf::from("test_given_file.json");
+        open_storage(&sqlite_config, Some(json_path.clone()), false).unwrap().save(&TodoList::default()).expect("Failed to save todo list");
+        serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&json_path).unwrap()).expect("The given file was not stored as JSON");
+        fs::remove_file(json_path).expect("Failed to delete test file");
+
+        let db_path = PathBuf::from("test_given_file.sqlite");
+        open_storage(&Config::default(), Some(db_path.clone()), false).unwrap().save(&TodoList::default()).expect("Failed to save todo list");
+        assert!(fs::read(&db_path).unwrap().starts_with(b"SQLite format 3"), "The given file was not stored as SQLite");
+        fs::remove_file(db_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_init_creates_json_project_file_with_sqlite_backend() {
+        let project_dir = std::env::temp_dir().join(format!("todo_init_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(&project_dir).expect("Failed to create test directory");
+        let data_dir = std::env::temp_dir().join(format!("todo_init_data_{}", std::process::id()));
+        let sqlite_config = || Config { storage: StorageBackend::Sqlite, data_dir: Some(data_dir.clone()), ..Config::default() };
+
+        let mut storage = new_project_storage(&sqlite_config(), &project_dir).expect("Failed to open project storage");
+        let mut todo_list = TodoList::load(storage.as_mut(), sqlite_config()).expect("Failed to load todo list");
+        todo_list.handle_cli(Pattern::Init).expect("Failed to run init");
+        todo_list.save(storage.as_mut()).expect("Failed to save todo list");
+
+        let project_file = find_project_file(&project_dir).expect("The project file was not created");
+        JsonStorage::new(project_file).load().expect("The project file is not a JSON todo list");
+        assert!(new_project_storage(&sqlite_config(), &project_dir).is_err(), "A second init should fail");
+
+        fs::remove_dir_all(project_dir).expect("Failed to delete test directory");
+        fs::remove_dir_all(data_dir).expect("Failed to delete test directory");
+    }
+
+    #[test]
+    fn test_project_files_are_kept_in_the_data_directory() {
+        let project_dir = std::env::temp_dir().join(format!("todo_project_files_{}", std::process::id()));
+        let data_dir = std::env::temp_dir().join(format!("todo_project_files_data_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&project_dir);
+        fs::create_dir_all(&project_dir).expect("Failed to create test directory");
+        let config = Config { data_dir: Some(data_dir.clone()), ..Config::default() };
+
+        let mut storage = new_project_storage(&config, &project_dir).expect("Failed to open project storage");
+        let mut todo_list = TodoList::load(storage.as_mut(), config).expect("Failed to load todo list");
+        todo_list.handle_cli(Pattern::Init).expect("Failed to run init");
+        todo_list.save(storage.as_mut()).expect("Failed to save todo list");
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["Task".to_string()] }).expect("Failed to add todo");
+        todo_list.handle_cli(Pattern::Reset).expect("Failed to reset todo list");
+        todo_list.save(storage.as_mut()).expect("Failed to save todo list");
+
+        let project_files: Vec<_> = fs::read_dir(&project_dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(project_files, vec![PROJECT_FILE_NAME]);
+
+        let companion_dir = storage.companion_path().parent().unwrap().to_path_buf();
+        assert!(companion_dir.starts_with(data_dir.join("projects")));
+        for file in ["todos.journal.json", "todos.json.bak", "backup"] {
+            assert!(companion_dir.join(file).exists(), "{} is not kept in the data directory", file);
+        }
+
+        fs::remove_dir_all(project_dir).expect("Failed to delete test directory");
+        fs::remove_dir_all(data_dir).expect("Failed to delete test directory");
+    }
+}
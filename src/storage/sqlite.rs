@@ -0,0 +1,593 @@
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use crate::patch::Patch;
+use crate::storage::{IdSpace, Storage};
+use crate::todo::{read_todo_list_from_file, Board, Priority, Todo, TodoList, DEFAULT_BOARD};
+
+/// Version of the database schema written by this build, stored as the `user_version` of the database
+const DATABASE_VERSION: u32 = 1;
+
+/// Schema of the database, created when the database is opened
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS boards (
+        name TEXT PRIMARY KEY,
+        available_ids TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS todos (
+        board TEXT NOT NULL,
+        id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        description TEXT NOT NULL,
+        is_complete INTEGER NOT NULL,
+        is_starred INTEGER NOT NULL,
+        timestamp TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        completed_at TEXT,
+        due TEXT,
+        priority TEXT NOT NULL,
+        parent INTEGER,
+        recurrence TEXT,
+        PRIMARY KEY (board, id)
+    );
+    CREATE INDEX IF NOT EXISTS todos_by_position ON todos (board, position);
+    CREATE INDEX IF NOT EXISTS todos_by_parent ON todos (board, parent);
+";
+
+/// Columns of the `todos` table in the order `todo_from_row` reads them, starting at the `id` column
+const TODO_COLUMNS: &str = "id, description, is_complete, is_starred, timestamp, tags, notes, completed_at, due, priority, parent, recurrence";
+
+/// Struct representing the stored state of a todo, used to skip writing unchanged todos
+struct StoredTodo {
+    /// The position of the todo within its board
+    position: i64,
+    /// The todo as it is stored
+    todo: Todo,
+}
+
+/// Struct representing a todo list stored in an embedded SQLite database with one row per todo.
+///
+/// Saving only writes the todos, boards and settings that changed since the todo list was loaded, all
+/// within a single transaction.
+pub struct SqliteStorage {
+    db_path: PathBuf,
+    connection: Connection,
+    /// JSON todo file imported when the database is still empty
+    import_path: Option<PathBuf>,
+    active_board: Option<String>,
+    boards: HashMap<String, String>,
+    todos: HashMap<(String, usize), StoredTodo>,
+    /// Whether the database was updated after the todo list was loaded, so saving rewrites it completely
+    is_stale: bool,
+}
+
+impl SqliteStorage {
+    /// Open the database at the given path, creating it if it does not exist.
+    ///
+    /// Databases written by a newer version of the application are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// `db_path` - The path to the database file.
+    /// `import_path` - A JSON todo file to import on the first load if the database holds no boards yet.
+    ///
+    /// # Returns
+    ///
+    /// `Result<SqliteStorage>` - The opened storage, or an error if the database cannot be opened or created.
+    pub fn open(db_path: PathBuf, import_path: Option<PathBuf>) -> Result<Self> {
+        let connection = Connection::open(&db_path)
+            .with_context(|| format!("Failed to open database {:?}", db_path))?;
+
+        let version: u32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > DATABASE_VERSION {
+            return Err(anyhow!(
+                "The database {:?} has version {}, but this version of todo only understands up to version {}. Please update todo.",
+                db_path,
+                version,
+                DATABASE_VERSION
+            ));
+        }
+        connection.execute_batch(SCHEMA).context("Failed to create database schema")?;
+        connection.pragma_update(None, "user_version", DATABASE_VERSION)?;
+
+        Ok(SqliteStorage {
+            db_path,
+            connection,
+            import_path,
+            active_board: None,
+            boards: HashMap::new(),
+            todos: HashMap::new(),
+            is_stale: false,
+        })
+    }
+
+    /// Helper function to write the JSON todo file to the database if it still needs to be imported, so single todos
+    /// can be read and written
+    fn import_pending(&mut self) -> Result<()> {
+        if let Some(todo_list) = self.import()? {
+            self.save(&todo_list)?;
+        }
+        Ok(())
+    }
+
+    /// Import the JSON todo file if the database holds no boards yet
+    fn import(&self) -> Result<Option<TodoList>> {
+        let board_count: usize = self.connection.query_row("SELECT COUNT(*) FROM boards", [], |row| row.get(0))?;
+        match &self.import_path {
+            Some(import_path) if board_count == 0 && import_path.exists() => {
+                let mut todo_list = read_todo_list_from_file(import_path)
+                    .with_context(|| format!("Failed to import {:?}", import_path))?;
+                // The imported todo list is written to the database when it is saved
+                todo_list.is_modified = true;
+                eprintln!("Imported the todo list from {:?}", import_path);
+                Ok(Some(todo_list))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn path(&self) -> &Path {
+        &self.db_path
+    }
+
+    /// Load all boards and todos from the database
+    fn load(&mut self) -> Result<TodoList> {
+        if let Some(todo_list) = self.import()? {
+            return Ok(todo_list);
+        }
+
+        let active_board: Option<String> = self.connection
+            .query_row("SELECT value FROM settings WHERE key = 'active_board'", [], |row| row.get(0))
+            .optional()?;
+
+        let mut boards = BTreeMap::new();
+        let mut statement = self.connection.prepare("SELECT name, available_ids FROM boards")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(0)?;
+            let available_ids: String = row.get(1)?;
+            let board = Board {
+                todos: Vec::new(),
+                available_ids: serde_json::from_str::<BTreeSet<usize>>(&available_ids)
+                    .with_context(|| format!("Failed to parse available IDs of board '{}'", name))?,
+                omitted: 0,
+            };
+            self.boards.insert(name.clone(), available_ids);
+            boards.insert(name, board);
+        }
+
+        let mut statement = self.connection.prepare(
+            &format!("SELECT board, position, {} FROM todos ORDER BY board, position", TODO_COLUMNS)
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let board_name: String = row.get(0)?;
+            let position: i64 = row.get(1)?;
+            let todo = todo_from_row(row).context("Failed to read todo from database")?;
+            let board = boards.get_mut(&board_name)
+                .ok_or_else(|| anyhow!("Todo item with ID {} belongs to unknown board '{}'", todo.id, board_name))?;
+
+            self.todos.insert((board_name, todo.id), StoredTodo { position, todo: todo.clone() });
+            board.todos.push(todo);
+        }
+
+        if boards.is_empty() {
+            return Ok(TodoList::default());
+        }
+
+        self.active_board = active_board.clone();
+        let active_board = active_board
+            .filter(|name| boards.contains_key(name))
+            .or_else(|| boards.keys().next().cloned())
+            .expect("at least one board exists");
+
+        Ok(TodoList::from_boards(boards, active_board))
+    }
+
+    /// Write the changes since the todo list was loaded in a single transaction.
+    ///
+    /// Todos keep their stored position as long as their order is unchanged, so removing or adding a todo
+    /// does not rewrite the todos after it.
+    fn save(&mut self, todo_list: &TodoList) -> Result<()> {
+        let transaction = self.connection.transaction().context("Failed to start database transaction")?;
+
+        if self.is_stale {
+            transaction.execute_batch("DELETE FROM todos; DELETE FROM boards;")?;
+            self.active_board = None;
+            self.boards.clear();
+            self.todos.clear();
+        }
+
+        if self.active_board.as_ref() != Some(&todo_list.active_board) {
+            transaction.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('active_board', ?1)",
+                params![todo_list.active_board],
+            )?;
+        }
+
+        let mut boards = HashMap::new();
+        for (name, board) in &todo_list.boards {
+            let available_ids = serde_json::to_string(&board.available_ids)
+                .context("Failed to serialize available IDs")?;
+            if self.boards.remove(name).as_ref() != Some(&available_ids) {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO boards (name, available_ids) VALUES (?1, ?2)",
+                    params![name, available_ids],
+                )?;
+            }
+            boards.insert(name.clone(), available_ids);
+        }
+        for name in self.boards.keys() {
+            transaction.execute("DELETE FROM todos WHERE board = ?1", params![name])?;
+            transaction.execute("DELETE FROM boards WHERE name = ?1", params![name])?;
+        }
+
+        let mut todos = HashMap::new();
+        {
+            let mut delete_todo = transaction.prepare_cached("DELETE FROM todos WHERE board = ?1 AND id = ?2")?;
+
+            for (name, board) in &todo_list.boards {
+                let mut last_position = -1;
+                for todo in &board.todos {
+                    let key = (name.clone(), todo.id);
+                    let stored = self.todos.remove(&key);
+                    let position = match &stored {
+                        Some(stored) if stored.position > last_position => stored.position,
+                        _ => last_position + 1,
+                    };
+
+                    if stored.is_none_or(|stored| stored.position != position || stored.todo != *todo) {
+                        write_todo(&transaction, name, position, todo)?;
+                    }
+
+                    last_position = position;
+                    todos.insert(key, StoredTodo { position, todo: todo.clone() });
+                }
+            }
+
+            // Todos left over were removed from their board
+            for (board, id) in self.todos.keys() {
+                delete_todo.execute(params![board, id])?;
+            }
+        }
+
+        transaction.commit().context("Failed to write todos to the database")?;
+
+        self.active_board = Some(todo_list.active_board.clone());
+        self.boards = boards;
+        self.todos = todos;
+        self.is_stale = false;
+        Ok(())
+    }
+
+    /// Read the active board from the settings, falling back to the first board like loading the todo list does
+    fn active_board(&mut self) -> Result<String> {
+        self.import_pending()?;
+        let active_board: Option<String> = self.connection.query_row(
+            "SELECT COALESCE(
+                 (SELECT name FROM boards WHERE name = (SELECT value FROM settings WHERE key = 'active_board')),
+                 (SELECT MIN(name) FROM boards)
+             )",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(active_board.unwrap_or_else(|| DEFAULT_BOARD.to_string()))
+    }
+
+    /// Read the available IDs of a board and count its todos
+    fn id_space(&mut self, board: &str) -> Result<IdSpace> {
+        self.import_pending()?;
+        let available_ids: Option<String> = self.connection
+            .query_row("SELECT available_ids FROM boards WHERE name = ?1", params![board], |row| row.get(0))
+            .optional()?;
+        let len: usize = self.connection
+            .query_row("SELECT COUNT(*) FROM todos WHERE board = ?1", params![board], |row| row.get(0))?;
+
+        Ok(IdSpace {
+            available_ids: match available_ids {
+                Some(available_ids) => serde_json::from_str(&available_ids)
+                    .with_context(|| format!("Failed to parse available IDs of board '{}'", board))?,
+                None => BTreeSet::new(),
+            },
+            len,
+        })
+    }
+
+    /// Select the todos and their subtasks with a recursive query, counting the todos before each of them for its index
+    fn query(&mut self, board: &str, ids: &[usize], with_descendants: bool) -> Result<Vec<(usize, Todo)>> {
+        self.import_pending()?;
+        let mut statement = self.connection.prepare_cached(&format!(
+            "WITH RECURSIVE selected (id) AS (
+                 SELECT value FROM json_each(?2)
+                 UNION SELECT todos.id FROM todos JOIN selected ON todos.parent = selected.id WHERE todos.board = ?1 AND ?3
+             )
+             SELECT board, (SELECT COUNT(*) FROM todos AS earlier WHERE earlier.board = todos.board AND earlier.position < todos.position), {}
+             FROM todos WHERE board = ?1 AND id IN selected ORDER BY position",
+            TODO_COLUMNS
+        ))?;
+
+        let ids = serde_json::to_string(ids).context("Failed to serialize todo IDs")?;
+        let mut rows = statement.query(params![board, ids, with_descendants])?;
+        let mut todos = Vec::new();
+        while let Some(row) = rows.next()? {
+            let index: usize = row.get(1)?;
+            todos.push((index, todo_from_row(row).context("Failed to read todo from database")?));
+        }
+        Ok(todos)
+    }
+
+    /// Write the touched todos in a single transaction, moving the todos after an inserted todo only if there is no
+    /// free position before it
+    fn update(&mut self, patch: &Patch) -> Result<()> {
+        self.import_pending()?;
+        let transaction = self.connection.transaction().context("Failed to start database transaction")?;
+
+        for (name, board_patch) in &patch.boards {
+            let Some(board_patch) = board_patch else {
+                transaction.execute("DELETE FROM todos WHERE board = ?1", params![name])?;
+                transaction.execute("DELETE FROM boards WHERE name = ?1", params![name])?;
+                continue;
+            };
+
+            let available_ids = serde_json::to_string(&board_patch.available_ids)
+                .context("Failed to serialize available IDs")?;
+            transaction.execute(
+                "INSERT OR REPLACE INTO boards (name, available_ids) VALUES (?1, ?2)",
+                params![name, available_ids],
+            )?;
+            for id in board_patch.todos.keys() {
+                transaction.execute("DELETE FROM todos WHERE board = ?1 AND id = ?2", params![name, id])?;
+            }
+
+            let mut todos: Vec<&(usize, Todo)> = board_patch.todos.values().flatten().collect();
+            todos.sort_by_key(|(index, _)| *index);
+            for (index, todo) in todos {
+                let position = free_position(&transaction, name, *index)?;
+                write_todo(&transaction, name, position, todo)?;
+            }
+
+            if let Some(order) = &board_patch.order {
+                for (position, id) in order.iter().enumerate() {
+                    transaction.execute(
+                        "UPDATE todos SET position = ?3 WHERE board = ?1 AND id = ?2",
+                        params![name, id, position as i64],
+                    )?;
+                }
+            }
+        }
+
+        if let Some(active_board) = &patch.active_board {
+            transaction.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('active_board', ?1)",
+                params![active_board],
+            )?;
+        }
+
+        transaction.commit().context("Failed to write todos to the database")?;
+        self.is_stale = true;
+        Ok(())
+    }
+}
+
+/// Write a todo to the `todos` table, replacing the stored todo with the same ID
+fn write_todo(transaction: &Transaction, board: &str, position: i64, todo: &Todo) -> Result<()> {
+    let mut statement = transaction.prepare_cached(
+        "INSERT OR REPLACE INTO todos (board, position, id, description, is_complete, is_starred, timestamp,
+                                       tags, notes, completed_at, due, priority, parent, recurrence)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"
+    )?;
+    statement.execute(params![
+        board,
+        position,
+        todo.id,
+        todo.desc,
+        todo.is_complete,
+        todo.is_starred,
+        todo.timestamp,
+        serde_json::to_string(&todo.tags)?,
+        todo.notes,
+        todo.completed_at,
+        todo.due,
+        priority_name(todo.priority),
+        todo.parent,
+        todo.recurrence.as_ref().map(serde_json::to_string).transpose()?,
+    ])?;
+    Ok(())
+}
+
+/// Find the position a todo inserted at an index of a board is stored at.
+///
+/// The todo takes a free position between its neighbours if there is one. Otherwise the todos from the index on are
+/// moved back by one position to make room for it.
+fn free_position(transaction: &Transaction, board: &str, index: usize) -> Result<i64> {
+    let position_at = |index: usize| -> Result<Option<i64>> {
+        Ok(transaction
+            .query_row(
+                "SELECT position FROM todos WHERE board = ?1 ORDER BY position LIMIT 1 OFFSET ?2",
+                params![board, index],
+                |row| row.get(0),
+            )
+            .optional()?)
+    };
+
+    let Some(next) = position_at(index)? else {
+        let last: Option<i64> = transaction
+            .query_row("SELECT MAX(position) FROM todos WHERE board = ?1", params![board], |row| row.get(0))?;
+        return Ok(last.map_or(0, |last| last + 1));
+    };
+    let previous = match index {
+        0 => None,
+        index => position_at(index - 1)?,
+    };
+
+    match previous {
+        None => Ok(next - 1),
+        Some(previous) if next - previous > 1 => Ok(previous + 1),
+        Some(_) => {
+            transaction.execute("UPDATE todos SET position = position + 1 WHERE board = ?1 AND position >= ?2", params![board, next])?;
+            Ok(next)
+        }
+    }
+}
+
+/// Read a todo from a row selected from the `todos` table, starting at the `id` column
+fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
+    Ok(Todo {
+        id: row.get(2)?,
+        desc: row.get(3)?,
+        is_complete: row.get(4)?,
+        is_starred: row.get(5)?,
+        timestamp: row.get(6)?,
+        tags: json_column(row, 7)?,
+        notes: row.get(8)?,
+        completed_at: row.get(9)?,
+        due: row.get(10)?,
+        priority: {
+            let name: String = row.get(11)?;
+            Priority::from_str(&name, true)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, Type::Text, e.into()))?
+        },
+        parent: row.get(12)?,
+        recurrence: row.get::<_, Option<String>>(13)?
+            .map(|recurrence| parse_json(13, &recurrence))
+            .transpose()?,
+    })
+}
+
+/// Read a column holding a JSON value
+fn json_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let value: String = row.get(index)?;
+    parse_json(index, &value)
+}
+
+/// Parse the JSON value of a column
+fn parse_json<T: DeserializeOwned>(index: usize, value: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(value)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+/// The name a priority is stored as
+fn priority_name(priority: Priority) -> String {
+    priority.to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::Pattern;
+    use crate::patch::{BoardPatch, Patch};
+    use crate::storage::{SqliteStorage, Storage};
+    use crate::todo::{Todo, TodoList};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::path::PathBuf;
+
+    fn open(db_path: &str) -> SqliteStorage {
+        SqliteStorage::open(PathBuf::from(db_path), None).expect("Failed to open database")
+    }
+
+    fn count_rows(storage: &SqliteStorage, sql: &str) -> usize {
+        storage.connection.query_row(sql, [], |row| row.get(0)).expect("Failed to count rows")
+    }
+
+    #[test]
+    fn test_save_and_load_todo_list() {
+        let db_path = "test_sqlite_round_trip.db";
+        let mut storage = open(db_path);
+
+        let mut todo_list = storage.load().expect("Failed to load empty database");
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["First task #work due:2026-11-03 !high every:weekly".to_string()] }).expect("Failed to add todo");
+        todo_list.handle_cli(Pattern::Add { parent: Some(1), args: vec!["Second task".to_string()] }).expect("Failed to add subtask");
+        storage.save(&todo_list).expect("Failed to save todo list");
+
+        let loaded = open(db_path).load().expect("Failed to load todo list");
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&todo_list).unwrap());
+
+        std::fs::remove_file(db_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_save_only_writes_changed_todos() {
+        let db_path = "test_sqlite_incremental.db";
+        let mut storage = open(db_path);
+
+        let mut todo_list = storage.load().expect("Failed to load empty database");
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["First :: Second :: Third".to_string()] }).expect("Failed to add todos");
+        storage.save(&todo_list).expect("Failed to save todo list");
+
+        // Mark the other rows, so rewriting them would be noticed
+        storage.connection.execute("UPDATE todos SET notes = 'untouched' WHERE id != 2", []).unwrap();
+        todo_list.handle_cli(Pattern::Done { cascade: false, args: vec![2] }).expect("Failed to mark todo as done");
+        todo_list.handle_cli(Pattern::Rm { cascade: false, args: vec![1] }).expect("Failed to remove todo");
+        storage.save(&todo_list).expect("Failed to save todo list");
+
+        assert_eq!(count_rows(&storage, "SELECT COUNT(*) FROM todos"), 2);
+        assert_eq!(count_rows(&storage, "SELECT COUNT(*) FROM todos WHERE notes = 'untouched'"), 1);
+        assert_eq!(count_rows(&storage, "SELECT COUNT(*) FROM todos WHERE id = 2 AND is_complete"), 1);
+
+        let loaded = open(db_path).load().expect("Failed to load todo list");
+        let ids: Vec<usize> = loaded.boards[&loaded.active_board].todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        std::fs::remove_file(db_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_update_writes_todos_at_their_index() {
+        let db_path = "test_sqlite_update.db";
+        let mut storage = open(db_path);
+
+        let mut todo_list = storage.load().expect("Failed to load empty database");
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["First :: Second :: Third".to_string()] }).expect("Failed to add todos");
+        storage.save(&todo_list).expect("Failed to save todo list");
+
+        // Insert before the first todo and between two todos without a free position, and remove the second todo
+        let todo = |id: usize| Todo { id, ..todo_list.boards["default"].todos[0].clone() };
+        let patch = Patch {
+            active_board: None,
+            boards: BTreeMap::from([("default".to_string(), Some(BoardPatch {
+                available_ids: BTreeSet::from([2]),
+                todos: BTreeMap::from([(2, None), (4, Some((1, todo(4)))), (5, Some((0, todo(5))))]),
+                order: None,
+            }))]),
+        };
+        storage.update(&patch).expect("Failed to update todos");
+
+        let (index, _) = storage.get("default", 1).unwrap().expect("The first todo was removed");
+        assert_eq!(index, 2);
+        let loaded = open(db_path).load().expect("Failed to load todo list");
+        let ids: Vec<usize> = loaded.boards["default"].todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, vec![5, 4, 1, 3]);
+        assert_eq!(loaded.boards["default"].available_ids, BTreeSet::from([2]));
+
+        std::fs::remove_file(db_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_import_json_todo_list() {
+        let db_path = "test_sqlite_import.db";
+        let json_path = PathBuf::from("test_sqlite_import.json");
+        let mut todo_list = TodoList::default();
+        todo_list.handle_cli(Pattern::Add { parent: None, args: vec!["Imported task".to_string()] }).expect("Failed to add todo");
+        std::fs::write(&json_path, serde_json::to_string(&todo_list).unwrap()).unwrap();
+
+        let mut storage = SqliteStorage::open(PathBuf::from(db_path), Some(json_path.clone())).expect("Failed to open database");
+        let imported = storage.load().expect("Failed to import todo list");
+        storage.save(&imported).expect("Failed to save todo list");
+        assert_eq!(count_rows(&storage, "SELECT COUNT(*) FROM todos"), 1);
+
+        std::fs::remove_file(db_path).expect("Failed to delete test file");
+        std::fs::remove_file(json_path).expect("Failed to delete test file");
+    }
+}
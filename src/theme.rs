@@ -0,0 +1,663 @@
+use anyhow::{anyhow, Context, Result};
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use crate::config::{ConfigValue, FromConfigValue};
+
+/// Enum representing the built-in themes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Colored output with dimmed details
+    #[default]
+    Default,
+    /// Output without colors, using only bold, dimmed and underlined text
+    Mono,
+    /// Bright colors that stay readable on dark and light terminals
+    Contrast,
+}
+
+impl FromStr for ThemeName {
+    type Err = anyhow::Error;
+
+    /// Parses the name of a theme as written in the configuration
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "default" => Ok(ThemeName::Default),
+            "mono" => Ok(ThemeName::Mono),
+            "contrast" => Ok(ThemeName::Contrast),
+            _ => Err(anyhow!("Unknown theme '{}'. Use default, mono or contrast", value)),
+        }
+    }
+}
+
+impl fmt::Display for ThemeName {
+    /// Formats the theme as written in the configuration
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeName::Default => write!(f, "default"),
+            ThemeName::Mono => write!(f, "mono"),
+            ThemeName::Contrast => write!(f, "contrast"),
+        }
+    }
+}
+
+/// Enum representing the sets of symbols the todo list can be rendered with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphSet {
+    /// Unicode symbols if the terminal supports them, ASCII otherwise
+    #[default]
+    Auto,
+    /// Symbols of a Nerd Font, which must be installed and used by the terminal
+    Nerd,
+    /// Standard Unicode symbols like `★` and `✔`
+    Unicode,
+    /// Pure ASCII symbols like `*` and `[x]`
+    Ascii,
+}
+
+impl GlyphSet {
+    /// Resolve `GlyphSet::Auto` to the glyph set the terminal can display, keeping the other glyph sets.
+    ///
+    /// Unicode is used if the locale given by `LC_ALL`, `LC_CTYPE` or `LANG` uses UTF-8 and `TERM` is not a terminal
+    /// limited to ASCII. Nerd Fonts cannot be detected, so they are never chosen automatically.
+    pub fn resolve(self) -> GlyphSet {
+        match self {
+            GlyphSet::Auto => {
+                let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter()
+                    .find_map(|variable| env::var(variable).ok().filter(|value| !value.is_empty()));
+                detect_glyph_set(env::var("TERM").ok().as_deref(), locale.as_deref())
+            }
+            set => set,
+        }
+    }
+}
+
+impl FromStr for GlyphSet {
+    type Err = anyhow::Error;
+
+    /// Parses the name of a glyph set as written in the configuration
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(GlyphSet::Auto),
+            "nerd" => Ok(GlyphSet::Nerd),
+            "unicode" => Ok(GlyphSet::Unicode),
+            "ascii" => Ok(GlyphSet::Ascii),
+            _ => Err(anyhow!("Unknown glyph set '{}'. Use auto, nerd, unicode or ascii", value)),
+        }
+    }
+}
+
+impl fmt::Display for GlyphSet {
+    /// Formats the glyph set as written in the configuration
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlyphSet::Auto => write!(f, "auto"),
+            GlyphSet::Nerd => write!(f, "nerd"),
+            GlyphSet::Unicode => write!(f, "unicode"),
+            GlyphSet::Ascii => write!(f, "ascii"),
+        }
+    }
+}
+
+/// Helper function to choose the glyph set from the terminal type and the locale.
+///
+/// Without a locale, Windows terminals are assumed to support Unicode, as Windows does not set one.
+fn detect_glyph_set(term: Option<&str>, locale: Option<&str>) -> GlyphSet {
+    if matches!(term, Some("dumb" | "linux" | "vt100" | "vt220")) {
+        return GlyphSet::Ascii;
+    }
+    let is_utf8 = match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => cfg!(windows),
+    };
+    if is_utf8 {
+        GlyphSet::Unicode
+    } else {
+        GlyphSet::Ascii
+    }
+}
+
+/// Enum representing the parts of the todo list a theme styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Element {
+    /// The title above the todo list
+    Title,
+    /// The number of completed todos and the completion rate
+    Summary,
+    /// The ID of a todo
+    Id,
+    /// An open todo
+    Pending,
+    /// A completed todo
+    Done,
+    /// The number of days since a todo was created
+    Age,
+    /// The tags of a todo
+    Tags,
+    /// The star of a starred todo
+    Star,
+    /// The mark of a todo with notes
+    Notes,
+    /// The recurrence of a todo
+    Recur,
+    /// The number of completed subtasks of a todo
+    Progress,
+    /// The label of a high priority todo
+    High,
+    /// The label of a medium priority todo
+    Medium,
+    /// The label of a low priority todo
+    Low,
+    /// A due date that has not passed
+    Due,
+    /// A due date that has passed
+    Overdue,
+    /// The characters matched by a search
+    Highlight,
+}
+
+impl Element {
+    /// All elements, in the order they are documented
+    pub const ALL: [Element; 17] = [
+        Element::Title, Element::Summary, Element::Id, Element::Pending, Element::Done, Element::Age, Element::Tags,
+        Element::Star, Element::Notes, Element::Recur, Element::Progress, Element::High, Element::Medium, Element::Low,
+        Element::Due, Element::Overdue, Element::Highlight,
+    ];
+
+    /// The name of the element as written in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Element::Title => "title",
+            Element::Summary => "summary",
+            Element::Id => "id",
+            Element::Pending => "pending",
+            Element::Done => "done",
+            Element::Age => "age",
+            Element::Tags => "tags",
+            Element::Star => "star",
+            Element::Notes => "notes",
+            Element::Recur => "recur",
+            Element::Progress => "progress",
+            Element::High => "high",
+            Element::Medium => "medium",
+            Element::Low => "low",
+            Element::Due => "due",
+            Element::Overdue => "overdue",
+            Element::Highlight => "highlight",
+        }
+    }
+}
+
+impl FromStr for Element {
+    type Err = anyhow::Error;
+
+    /// Parses the name of an element as written in the configuration
+    fn from_str(value: &str) -> Result<Self> {
+        Element::ALL.into_iter()
+            .find(|element| element.name() == value)
+            .ok_or_else(|| anyhow!("unknown element '{}'", value))
+    }
+}
+
+/// Enum representing the symbols of the todo list a theme can replace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Glyph {
+    /// The status of a completed todo
+    Done,
+    /// The status of an open todo
+    Pending,
+    /// The mark of a starred todo
+    Star,
+    /// The mark of a todo with notes
+    Notes,
+    /// The mark in front of the recurrence of a todo
+    Recur,
+}
+
+impl Glyph {
+    /// All glyphs, in the order they are documented
+    pub const ALL: [Glyph; 5] = [Glyph::Done, Glyph::Pending, Glyph::Star, Glyph::Notes, Glyph::Recur];
+
+    /// The name of the glyph as written in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            Glyph::Done => "done",
+            Glyph::Pending => "pending",
+            Glyph::Star => "star",
+            Glyph::Notes => "notes",
+            Glyph::Recur => "recur",
+        }
+    }
+
+    /// The symbol of the glyph in a glyph set, where `GlyphSet::Auto` must be resolved first
+    fn symbol(&self, set: GlyphSet) -> &'static str {
+        match (set, self) {
+            (GlyphSet::Nerd, Glyph::Done) => "\u{f046}",
+            (GlyphSet::Nerd, Glyph::Pending) => "\u{f096}",
+            (GlyphSet::Nerd, Glyph::Star) => "\u{f005}",
+            (GlyphSet::Nerd, Glyph::Notes) => "\u{f040}",
+            (GlyphSet::Nerd, Glyph::Recur) => "\u{f021}",
+            (GlyphSet::Auto | GlyphSet::Unicode, Glyph::Done) => "[✔]",
+            (GlyphSet::Auto | GlyphSet::Unicode, Glyph::Pending) => "[ ]",
+            (GlyphSet::Auto | GlyphSet::Unicode, Glyph::Star) => "★",
+            (GlyphSet::Auto | GlyphSet::Unicode, Glyph::Notes) => "✎",
+            (GlyphSet::Auto | GlyphSet::Unicode, Glyph::Recur) => "↻",
+            (GlyphSet::Ascii, Glyph::Done) => "[x]",
+            (GlyphSet::Ascii, Glyph::Pending) => "[ ]",
+            (GlyphSet::Ascii, Glyph::Star) => "*",
+            (GlyphSet::Ascii, Glyph::Notes) => "+",
+            (GlyphSet::Ascii, Glyph::Recur) => "~",
+        }
+    }
+}
+
+impl FromStr for Glyph {
+    type Err = anyhow::Error;
+
+    /// Parses the name of a glyph as written in the configuration
+    fn from_str(value: &str) -> Result<Self> {
+        Glyph::ALL.into_iter()
+            .find(|glyph| glyph.name() == value)
+            .ok_or_else(|| anyhow!("unknown glyph '{}'", value))
+    }
+}
+
+/// The names of the colors a style accepts
+const COLORS: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("white", Color::White),
+    ("bright black", Color::BrightBlack),
+    ("bright red", Color::BrightRed),
+    ("bright green", Color::BrightGreen),
+    ("bright yellow", Color::BrightYellow),
+    ("bright blue", Color::BrightBlue),
+    ("bright magenta", Color::BrightMagenta),
+    ("bright cyan", Color::BrightCyan),
+    ("bright white", Color::BrightWhite),
+];
+
+/// Struct representing how an element is styled, written as a color and attributes like `"bold red"`.
+///
+/// Colors are the names in `COLORS` or hex colors like `"#ff8800"`, and the attributes are `bold`, `dimmed`,
+/// `italic` and `underline`. `"none"` leaves the element unstyled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Style {
+    color: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    /// Apply the style to a text
+    pub fn paint(&self, text: &str) -> ColoredString {
+        let mut painted = text.normal();
+        if let Some(color) = self.color {
+            painted = painted.color(color);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if self.italic {
+            painted = painted.italic();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        painted
+    }
+}
+
+impl FromStr for Style {
+    type Err = anyhow::Error;
+
+    /// Parses a style as written in the configuration, e.g. `"bold bright red"`
+    fn from_str(value: &str) -> Result<Self> {
+        let mut style = Style::default();
+        let mut words = value.split_whitespace().map(str::to_lowercase);
+        while let Some(word) = words.next() {
+            match word.as_str() {
+                "none" | "normal" => {}
+                "bold" => style.bold = true,
+                "dimmed" => style.dimmed = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "bright" => {
+                    let color = words.next().unwrap_or_default();
+                    style.color = Some(parse_color(&format!("bright {}", color))?);
+                }
+                color => style.color = Some(parse_color(color)?),
+            }
+        }
+        Ok(style)
+    }
+}
+
+impl TryFrom<String> for Style {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Style {
+    /// Formats the style as written in the configuration
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attributes = [(self.bold, "bold"), (self.dimmed, "dimmed"), (self.italic, "italic"), (self.underline, "underline")];
+        let mut words: Vec<String> = attributes.iter()
+            .filter(|(is_set, _)| *is_set)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        match self.color {
+            Some(Color::TrueColor { r, g, b }) => words.push(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            Some(color) => words.extend(COLORS.iter().find(|(_, known)| *known == color).map(|(name, _)| name.to_string())),
+            None => {}
+        }
+        if words.is_empty() {
+            return write!(f, "none");
+        }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+/// Helper function to parse a color name or a hex color
+fn parse_color(name: &str) -> Result<Color> {
+    if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16);
+        if let (Ok(r), Ok(g), Ok(b)) = (channel(0), channel(2), channel(4)) {
+            return Ok(Color::TrueColor { r, g, b });
+        }
+    }
+    COLORS.iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, color)| *color)
+        .ok_or_else(|| anyhow!("unknown color or attribute '{}'", name))
+}
+
+/// Struct representing the changes the configuration makes to the selected theme.
+///
+/// In the configuration, this is the `style` table with the optional keys `colors`, `glyphs`, `title` and `show_age`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct StyleOverrides {
+    /// Styles by the element they replace the style of
+    pub colors: BTreeMap<Element, Style>,
+    /// Symbols by the glyph they replace
+    pub glyphs: BTreeMap<Glyph, String>,
+    /// The title above the todo list
+    pub title: Option<String>,
+    /// Whether to show the number of days since a todo was created
+    pub show_age: Option<bool>,
+}
+
+impl StyleOverrides {
+    /// Build the overrides from the `style` table of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `table` - The keys and values of the `style` table.
+    ///
+    /// # Returns
+    ///
+    /// `Result<StyleOverrides>` - The overrides, or an error naming the first unknown key, element, glyph or style.
+    pub fn from_table(table: BTreeMap<String, ConfigValue>) -> Result<Self> {
+        let mut overrides = StyleOverrides::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "colors" => {
+                    let colors: BTreeMap<String, String> = FromConfigValue::from_config_value(value).context("invalid colors")?;
+                    for (element, style) in colors {
+                        let element: Element = element.parse().context("invalid colors")?;
+                        let style: Style = style.parse().with_context(|| format!("invalid color of '{}'", element.name()))?;
+                        overrides.colors.insert(element, style);
+                    }
+                }
+                "glyphs" => {
+                    let glyphs: BTreeMap<String, String> = FromConfigValue::from_config_value(value).context("invalid glyphs")?;
+                    for (glyph, symbol) in glyphs {
+                        overrides.glyphs.insert(glyph.parse().context("invalid glyphs")?, symbol);
+                    }
+                }
+                "title" => overrides.title = Some(FromConfigValue::from_config_value(value).context("invalid title")?),
+                "show_age" => overrides.show_age = Some(FromConfigValue::from_config_value(value).context("invalid show_age")?),
+                _ => return Err(anyhow!("unknown style option '{}'", key)),
+            }
+        }
+        Ok(overrides)
+    }
+}
+
+impl From<StyleOverrides> for ConfigValue {
+    fn from(overrides: StyleOverrides) -> Self {
+        let mut table = BTreeMap::new();
+        if !overrides.colors.is_empty() {
+            let colors: BTreeMap<String, String> = overrides.colors.iter()
+                .map(|(element, style)| (element.name().to_string(), style.to_string()))
+                .collect();
+            table.insert("colors".to_string(), colors.into());
+        }
+        if !overrides.glyphs.is_empty() {
+            let glyphs: BTreeMap<String, String> = overrides.glyphs.into_iter()
+                .map(|(glyph, symbol)| (glyph.name().to_string(), symbol))
+                .collect();
+            table.insert("glyphs".to_string(), glyphs.into());
+        }
+        if let Some(title) = overrides.title {
+            table.insert("title".to_string(), title.into());
+        }
+        if let Some(show_age) = overrides.show_age {
+            table.insert("show_age".to_string(), show_age.into());
+        }
+        ConfigValue::Table(table)
+    }
+}
+
+/// Struct representing the resolved look of the todo list: a built-in theme with the overrides of the configuration
+#[derive(Debug, Clone)]
+pub struct Theme {
+    styles: BTreeMap<Element, Style>,
+    glyphs: BTreeMap<Glyph, String>,
+    /// The title above the todo list
+    pub title: String,
+    /// Whether to show the number of days since a todo was created
+    pub show_age: bool,
+}
+
+impl Theme {
+    /// Resolve a built-in theme with the overrides of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `name` - The built-in theme.
+    /// `glyph_set` - The set of symbols, where `GlyphSet::Auto` is resolved for the terminal.
+    /// `overrides` - The changes the configuration makes to the theme, where glyphs replace those of the glyph set.
+    ///
+    /// # Returns
+    ///
+    /// `Theme` - The resolved theme.
+    pub fn new(name: ThemeName, glyph_set: GlyphSet, overrides: &StyleOverrides) -> Self {
+        let mut styles: BTreeMap<Element, Style> = builtin_styles(name).into_iter()
+            .map(|(element, style)| (element, style.parse().expect("Built-in styles are valid")))
+            .collect();
+        styles.extend(overrides.colors.iter().map(|(element, style)| (*element, *style)));
+
+        let glyph_set = glyph_set.resolve();
+        let mut glyphs: BTreeMap<Glyph, String> = Glyph::ALL.into_iter()
+            .map(|glyph| (glyph, glyph.symbol(glyph_set).to_string()))
+            .collect();
+        glyphs.extend(overrides.glyphs.clone());
+
+        Theme {
+            styles,
+            glyphs,
+            title: overrides.title.clone().unwrap_or_else(|| "Your todos:".to_string()),
+            show_age: overrides.show_age.unwrap_or(true),
+        }
+    }
+
+    /// Style a text as an element
+    pub fn paint(&self, element: Element, text: &str) -> ColoredString {
+        self.styles.get(&element).copied().unwrap_or_default().paint(text)
+    }
+
+    /// Get the symbol of a glyph
+    pub fn glyph(&self, glyph: Glyph) -> &str {
+        self.glyphs.get(&glyph).map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// Helper function to get the styles of a built-in theme, where elements without a style are left unstyled
+fn builtin_styles(name: ThemeName) -> Vec<(Element, &'static str)> {
+    match name {
+        ThemeName::Default => vec![
+            (Element::Title, "underline"),
+            (Element::Summary, "dimmed"),
+            (Element::Id, "dimmed"),
+            (Element::Done, "dimmed"),
+            (Element::Age, "dimmed"),
+            (Element::Tags, "cyan"),
+            (Element::Star, "yellow"),
+            (Element::Notes, "dimmed"),
+            (Element::Recur, "dimmed"),
+            (Element::Progress, "dimmed"),
+            (Element::High, "bold red"),
+            (Element::Medium, "yellow"),
+            (Element::Low, "blue"),
+            (Element::Due, "yellow"),
+            (Element::Overdue, "red"),
+            (Element::Highlight, "bold bright yellow"),
+        ],
+        ThemeName::Mono => vec![
+            (Element::Title, "underline"),
+            (Element::Summary, "dimmed"),
+            (Element::Id, "dimmed"),
+            (Element::Done, "dimmed"),
+            (Element::Age, "dimmed"),
+            (Element::Notes, "dimmed"),
+            (Element::Recur, "dimmed"),
+            (Element::Progress, "dimmed"),
+            (Element::High, "bold"),
+            (Element::Overdue, "bold underline"),
+            (Element::Highlight, "bold underline"),
+        ],
+        ThemeName::Contrast => vec![
+            (Element::Title, "bold underline"),
+            (Element::Summary, "bright white"),
+            (Element::Id, "bright blue"),
+            (Element::Pending, "bright white"),
+            (Element::Done, "bright black"),
+            (Element::Age, "bright black"),
+            (Element::Tags, "bold bright cyan"),
+            (Element::Star, "bold bright yellow"),
+            (Element::Notes, "bright magenta"),
+            (Element::Recur, "bright magenta"),
+            (Element::Progress, "bright green"),
+            (Element::High, "bold bright red"),
+            (Element::Medium, "bold bright yellow"),
+            (Element::Low, "bright blue"),
+            (Element::Due, "bright yellow"),
+            (Element::Overdue, "bold bright red"),
+            (Element::Highlight, "bold underline bright yellow"),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use colored::Color;
+    use std::collections::BTreeMap;
+    use crate::config::ConfigValue;
+    use crate::theme::{detect_glyph_set, Element, Glyph, GlyphSet, Style, StyleOverrides, Theme, ThemeName};
+
+    #[test]
+    fn test_parse_style() {
+        let style: Style = "bold bright red".parse().unwrap();
+        assert_eq!(style, Style { color: Some(Color::BrightRed), bold: true, ..Style::default() });
+        assert_eq!(style.to_string(), "bold bright red");
+
+        let style: Style = "#FF8800 underline".parse().unwrap();
+        assert_eq!(style.color, Some(Color::TrueColor { r: 255, g: 136, b: 0 }));
+        assert_eq!(style.to_string(), "underline #ff8800");
+
+        assert_eq!("none".parse::<Style>().unwrap(), Style::default());
+        assert_eq!("blinking".parse::<Style>().unwrap_err().to_string(), "unknown color or attribute 'blinking'");
+    }
+
+    #[test]
+    fn test_theme_with_overrides() {
+        let table = BTreeMap::from([
+            ("colors".to_string(), ConfigValue::Table(BTreeMap::from([("tags".to_string(), "magenta".into())]))),
+            ("glyphs".to_string(), ConfigValue::Table(BTreeMap::from([("done".to_string(), "[x]".into())]))),
+            ("title".to_string(), "Tasks:".into()),
+            ("show_age".to_string(), false.into()),
+        ]);
+        let overrides = StyleOverrides::from_table(table.clone()).unwrap();
+        assert_eq!(ConfigValue::from(overrides.clone()), ConfigValue::Table(table));
+
+        let theme = Theme::new(ThemeName::Default, GlyphSet::Unicode, &overrides);
+        assert_eq!(theme.title, "Tasks:");
+        assert!(!theme.show_age);
+        assert_eq!(theme.glyph(Glyph::Done), "[x]");
+        assert_eq!(theme.glyph(Glyph::Pending), "[ ]");
+        assert_eq!(theme.glyph(Glyph::Star), "★");
+        assert_eq!(theme.styles[&Element::Tags], "magenta".parse().unwrap());
+        assert_eq!(theme.styles[&Element::Id], "dimmed".parse().unwrap());
+
+        for name in [ThemeName::Default, ThemeName::Mono, ThemeName::Contrast] {
+            assert_eq!(name.to_string().parse::<ThemeName>().unwrap(), name);
+            Theme::new(name, GlyphSet::Auto, &StyleOverrides::default());
+        }
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        let error_of = |key: &str, value: ConfigValue| {
+            let error = StyleOverrides::from_table(BTreeMap::from([(key.to_string(), value)])).expect_err("The style should be rejected");
+            format!("{:#}", error)
+        };
+        let colors = |element: &str, style: &str| ConfigValue::Table(BTreeMap::from([(element.to_string(), style.into())]));
+
+        assert_eq!(error_of("colors", colors("tag", "red")), "invalid colors: unknown element 'tag'");
+        assert_eq!(error_of("colors", colors("tags", "rosa")), "invalid color of 'tags': unknown color or attribute 'rosa'");
+        assert_eq!(error_of("glyphs", colors("check", "x")), "invalid glyphs: unknown glyph 'check'");
+        assert_eq!(error_of("show_age", "no".into()), "invalid show_age: expected a boolean, found a string");
+        assert_eq!(error_of("font", "mono".into()), "unknown style option 'font'");
+    }
+
+    #[test]
+    fn test_glyph_sets() {
+        assert_eq!(detect_glyph_set(Some("xterm-256color"), Some("en_US.UTF-8")), GlyphSet::Unicode);
+        assert_eq!(detect_glyph_set(Some("xterm-256color"), Some("de_DE.utf8")), GlyphSet::Unicode);
+        assert_eq!(detect_glyph_set(Some("xterm-256color"), Some("C")), GlyphSet::Ascii);
+        assert_eq!(detect_glyph_set(Some("dumb"), Some("en_US.UTF-8")), GlyphSet::Ascii);
+        assert_eq!(detect_glyph_set(Some("linux"), Some("en_US.UTF-8")), GlyphSet::Ascii);
+        assert_eq!(GlyphSet::Nerd.resolve(), GlyphSet::Nerd);
+
+        let overrides = StyleOverrides { glyphs: [(Glyph::Star, "!".to_string())].into(), ..StyleOverrides::default() };
+        let theme = Theme::new(ThemeName::Default, GlyphSet::Ascii, &overrides);
+        let symbols: Vec<&str> = Glyph::ALL.iter().map(|glyph| theme.glyph(*glyph)).collect();
+        assert_eq!(symbols, vec!["[x]", "[ ]", "!", "+", "~"]);
+        assert!(Glyph::ALL.iter().all(|glyph| glyph.symbol(GlyphSet::Ascii).is_ascii()));
+    }
+}
@@ -1,92 +1,455 @@
 use serde::{Deserialize, Serialize};
+use clap::ValueEnum;
 use anyhow::{anyhow, Context, Result};
-use std::collections::BTreeSet;
-use std::io::{Read, Write};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
 use std::fs::File;
-use std::path::Path;
-use chrono::{DateTime, Utc};
-use crate::cli::{BackupAction, DeleteOptions, DeleteOption, Pattern, SortBy};
-use crate::render::render_todo_list;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use crate::cli::{BackupAction, BoardAction, DeleteOptions, DeleteOption, NoteAction, OutputFormat, Pattern, SortBy, TagAction, ViewAction};
+use crate::due::parse_due_date;
+use crate::parse::{normalize_tag, parse_description};
+use crate::recurrence::Recurrence;
+use crate::render::{render_board_list, render_history, render_tag_list, render_search_results, render_todo_detail, render_todo_list, render_view_list};
 use crate::utils::*;
-use crate::config::{Config, load_config_from_lua};
-use crate::modify_todos;
+use crate::config::{set_config_value, Config};
+use crate::commands::ScriptApi;
+use crate::hooks::HookEvent;
+use crate::output::{format_backups, format_todos, format_views, BackupRecord, NoResults, ViewRecord};
+use crate::query::Query;
+use crate::search::Searcher;
+use crate::view::{check_view_name, View};
+use crate::journal::{command_line, Journal};
+use crate::patch::Patch;
+use crate::migration::{migrate_todo_list, SCHEMA_VERSION};
+use crate::storage::Storage;
+
+/// Name of the board that is created when no other board exists
+pub const DEFAULT_BOARD: &str = "default";
+
+/// Enum representing the priority of a Todo item, ordered from most to least urgent
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// High priority, also accepted as `h`
+    #[value(alias = "h")]
+    High,
+
+    /// Medium priority, also accepted as `med` or `m`
+    #[value(alias = "med", alias = "m")]
+    Medium,
+
+    /// Low priority, also accepted as `l`
+    #[value(alias = "l")]
+    Low,
+
+    /// No priority
+    #[default]
+    None,
+}
+
+impl Priority {
+    /// Parses an inline priority token such as `!high`, `!med` or `!l`
+    pub fn from_token(word: &str) -> Option<Self> {
+        let level = word.strip_prefix('!')?;
+        match Priority::from_str(level, true) {
+            Ok(Priority::None) | Err(_) => None,
+            Ok(priority) => Some(priority),
+        }
+    }
 
-// TODO: Implement boards  
+    /// The label used to display the priority, or `None` if no priority is set
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Priority::High => Some("!high"),
+            Priority::Medium => Some("!med"),
+            Priority::Low => Some("!low"),
+            Priority::None => None,
+        }
+    }
+}
 
 /// Struct representing a Todo item
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Todo {
     pub(crate) id: usize,
     pub(crate) desc: String,
     pub(crate) is_complete: bool,
     pub(crate) is_starred: bool,
     pub(crate) timestamp: DateTime<Utc>,
-    // TODO: Tags 
-    // TODO: Notes 
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) notes: String,
+    #[serde(default)]
+    pub(crate) completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) due: Option<NaiveDate>,
+    #[serde(default)]
+    pub(crate) priority: Priority,
+    #[serde(default)]
+    pub(crate) parent: Option<usize>,
+    #[serde(default)]
+    pub(crate) recurrence: Option<Recurrence>,
+}
+
+impl Todo {
+    /// Create the next occurrence of a recurring todo, due on the next date of its rule.
+    ///
+    /// The next date is counted from the due date, or from today if the todo has no due date
+    /// or is overdue. The returned todo still needs a new ID. Fails if the next date is beyond the last date.
+    fn next_occurrence(&self, recurrence: Recurrence, today: NaiveDate) -> Result<Todo> {
+        let base = self.due.map_or(today, |due| due.max(today));
+        let due = recurrence.next_after(base)
+            .ok_or_else(|| anyhow!("The next occurrence of todo {} is too far in the future", self.id))?;
+        Ok(Todo {
+            is_complete: false,
+            completed_at: None,
+            timestamp: Utc::now(),
+            due: Some(due),
+            recurrence: Some(recurrence),
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+impl Todo {
+    /// Create an open todo without tags, notes, due date or priority for tests
+    pub(crate) fn new(id: usize, desc: &str) -> Todo {
+        Todo {
+            id,
+            desc: desc.to_string(),
+            is_complete: false,
+            is_starred: false,
+            timestamp: Utc::now(),
+            tags: Vec::new(),
+            notes: String::new(),
+            completed_at: None,
+            due: None,
+            priority: Priority::None,
+            parent: None,
+            recurrence: None,
+        }
+    }
+}
+
+/// Struct representing a named board of Todo items with its own ID space
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Board {
+    pub(crate) todos: Vec<Todo>,
+    pub(crate) available_ids: BTreeSet<usize>,
+    /// The number of todos of the board that were not read, for a board holding only the todos a command touches
+    #[serde(skip)]
+    pub(crate) omitted: usize,
+}
+
+impl Board {
+    /// Retrieve the next available ID or create a new one
+    fn get_next_available_id(&mut self) -> usize {
+        if let Some(&next_id) = self.available_ids.iter().next() {
+            self.available_ids.remove(&next_id);
+            next_id
+        } else {
+            self.todos.len() + self.omitted + 1
+        }
+    }
+
+    /// Collect the IDs of all subtasks of a todo, including nested ones
+    fn descendant_ids(&self, id: usize) -> Vec<usize> {
+        let mut descendants = Vec::new();
+        let mut pending = vec![id];
+        while let Some(parent) = pending.pop() {
+            for todo in self.todos.iter().filter(|todo| todo.parent == Some(parent)) {
+                descendants.push(todo.id);
+                pending.push(todo.id);
+            }
+        }
+        descendants
+    }
+
+    /// Extend a list of IDs with the IDs of all their subtasks, without duplicates
+    pub(crate) fn with_descendants(&self, ids: Vec<usize>) -> Vec<usize> {
+        let mut all_ids = Vec::new();
+        for id in ids {
+            for id in std::iter::once(id).chain(self.descendant_ids(id)) {
+                if !all_ids.contains(&id) {
+                    all_ids.push(id);
+                }
+            }
+        }
+        all_ids
+    }
 }
 
-/// Struct representing a list of Todo items
-#[derive(Serialize, Deserialize, Default)]
+/// Struct representing all boards and the currently active one
+#[derive(Serialize, Deserialize)]
 pub struct TodoList {
-    todos: Vec<Todo>,
-    available_ids: BTreeSet<usize>,
+    version: u32,
+    pub(crate) boards: BTreeMap<String, Board>,
+    pub(crate) active_board: String,
+    #[serde(skip)]
+    pub(crate) config: Config,
+    #[serde(skip)]
+    journal: Journal,
+    /// The schema version the todo list was upgraded from when it was loaded, if it was outdated
+    #[serde(skip)]
+    pub(crate) migrated_from: Option<u32>,
+    /// The file the todo list is stored in
+    #[serde(skip)]
+    file_path: PathBuf,
+    /// The path the backups of the todo list are named after, see `Storage::companion_path`
+    #[serde(skip)]
+    companion_path: PathBuf,
+    /// The format todos, backups and views are listed in, if given by `--format`
     #[serde(skip)]
-    config: Config,
+    pub(crate) output_format: Option<OutputFormat>,
+    /// Whether the todo list changed since it was loaded, so it needs to be saved
+    #[serde(skip)]
+    pub(crate) is_modified: bool,
+}
+
+impl Default for TodoList {
+    /// Provides a todo list with a single empty default board
+    fn default() -> Self {
+        TodoList::from_board(Board::default())
+    }
 }
 
 impl TodoList {
+    /// Create a todo list holding the given board as its active default board
+    fn from_board(board: Board) -> Self {
+        TodoList::from_boards(BTreeMap::from([(DEFAULT_BOARD.to_string(), board)]), DEFAULT_BOARD.to_string())
+    }
+
+    /// Create a todo list holding the given boards, one of which is the active board
+    pub(crate) fn from_boards(boards: BTreeMap<String, Board>, active_board: String) -> Self {
+        TodoList {
+            version: SCHEMA_VERSION,
+            boards,
+            active_board,
+            config: Config::default(),
+            journal: Journal::default(),
+            migrated_from: None,
+            file_path: PathBuf::new(),
+            companion_path: PathBuf::new(),
+            output_format: None,
+            is_modified: false,
+        }
+    }
+
+    /// Get the active board
+    fn board(&self) -> &Board {
+        self.boards.get(&self.active_board)
+            .expect("active board must exist")
+    }
+
+    /// Get the active board mutably
+    fn board_mut(&mut self) -> &mut Board {
+        self.boards.get_mut(&self.active_board)
+            .expect("active board must exist")
+    }
+
+    /// Helper function to copy the boards and the active board before a command, so the changes of the command can be
+    /// recorded in the journal.
+    ///
+    /// # Returns
+    ///
+    /// `Option<(BTreeMap<String, Board>, String)>` - The copy, or `None` for commands that only read the todo list and
+    /// for `undo` and `redo`, which move entries of the journal instead of recording new ones.
+    fn snapshot_for(&self, pattern: &Pattern) -> Option<(BTreeMap<String, Board>, String)> {
+        let is_journaled = !pattern.is_read_only() && !matches!(pattern, Pattern::Undo { .. } | Pattern::Redo { .. });
+        is_journaled.then(|| (self.boards.clone(), self.active_board.clone()))
+    }
+
     /// Handle CLI commands
+    ///
+    /// Every command that changes the todo list is recorded in the journal so it can be undone. Only the todos and
+    /// boards a command changed are recorded, and commands that only read the todo list are neither copied nor compared.
     pub fn handle_cli(&mut self, pattern: Pattern) -> Result<()> {
+        let before = self.snapshot_for(&pattern);
+
         match pattern {
             Pattern::List => self.list(),
-            Pattern::Add { args } => self.add(args),
+            Pattern::Add { parent, args } => self.add(args, parent)?,
             Pattern::Edit { id, description } => self.edit(id, description)?,
-            Pattern::Filter { query } => self.filter(query)?,
-            Pattern::Done { args } => self.done(args)?,
+            Pattern::Filter { query, case_sensitive, ignore_case } => {
+                let case_sensitive = if case_sensitive { Some(true) } else if ignore_case { Some(false) } else { None };
+                self.filter(query, case_sensitive)?
+            }
+            Pattern::Done { cascade, args } => self.done(args, cascade)?,
             Pattern::Undone { args } => self.undone(args)?,
             Pattern::Star { args } => self.star(args)?,
-            Pattern::Rm { args } => self.rm(args)?,
+            Pattern::Priority { level, args } => self.set_priority(level, args)?,
+            Pattern::Recur { rule, args } => self.set_recurrence(&rule, args)?,
+            Pattern::Rm { cascade, args } => self.rm(args, cascade)?,
             Pattern::Reset => self.reset()?,
             Pattern::Sort { sort_by } => self.sort(sort_by),
             Pattern::Backup { name } => self.handle_backup(name)?,
+            Pattern::Board { action } => self.handle_board(action)?,
+            Pattern::Tag { action } => self.handle_tag(action)?,
+            Pattern::Search { query, limit } => self.search(&query.join(" "), limit)?,
+            Pattern::View { action, name } => self.handle_view(action, name)?,
+            Pattern::Show { id } => self.show(id)?,
+            Pattern::Note { action } => self.handle_note(action)?,
+            Pattern::Undo { steps } => self.undo(steps)?,
+            Pattern::Redo { steps } => self.redo(steps)?,
+            Pattern::History => self.history(),
+            Pattern::Init => self.init(),
+            Pattern::Config { .. } => unreachable!("Configuration commands are handled before the todo list is loaded"),
+            Pattern::Custom(args) => self.run_custom_command(args)?,
+        }
+
+        if let Some((boards, active_board)) = before {
+            if let Some(patch) = Patch::revert(&boards, &active_board, self) {
+                self.journal.record(command_line(), patch);
+                self.is_modified = true;
+            }
         }
         Ok(())
     }
 
     /// List all todo items
     pub fn list(&self) {
-        let todos_refs: Vec<&Todo> = self.todos.iter().collect();
-        render_todo_list(&todos_refs, &self.config)
+        let todos_refs: Vec<&Todo> = self.board().todos.iter().collect();
+        self.print_todos(&todos_refs);
+    }
+
+    /// The format todos, backups and views are listed in, which is text unless `--format` is given
+    fn output_format(&self) -> OutputFormat {
+        self.output_format.unwrap_or_default()
     }
 
-    /// Add new todo items
-    fn add(&mut self, args: Vec<String>) {
-        // Join arguments into a single string and split by "::" to handle multiple todo items
-        let items: Vec<String> = args.join(" ")
+    /// Print todos of the active board in the output format
+    fn print_todos(&self, todos: &[&Todo]) {
+        self.print_todos_as(todos, self.output_format());
+    }
+
+    /// Print todos of the active board in the given format
+    fn print_todos_as(&self, todos: &[&Todo], format: OutputFormat) {
+        match format {
+            OutputFormat::Text => render_todo_list(todos, &self.active_board, &self.config),
+            format => print!("{}", format_todos(todos, &self.active_board, format)),
+        }
+    }
+
+    /// Add new todo items, extracting inline `#tags`, `due:` dates, `every:` rules and `!priority` levels
+    /// from their descriptions.
+    ///
+    /// If a `parent` ID is given, the new todos are added as its subtasks.
+    fn add(&mut self, args: Vec<String>, parent: Option<usize>) -> Result<()> {
+        self.add_todos(args, parent)?;
+        self.list();
+        Ok(())
+    }
+
+    /// Helper function to add new todo items without listing the todos, returning the IDs of the new todos
+    fn add_todos(&mut self, args: Vec<String>, parent: Option<usize>) -> Result<Vec<usize>> {
+        let today = Local::now().date_naive();
+        if let Some(parent) = parent {
+            if !self.board().todos.iter().any(|todo| todo.id == parent) {
+                return Err(anyhow!("Parent todo item with ID {} not found", parent));
+            }
+        }
+
+        // Join arguments into a single string and split by "::" to handle multiple todo items, skipping blank ones
+        let items = args.join(" ")
             .split("::")
-            .map(|item| item.trim().to_string())
-            .filter(|item| !item.is_empty())
-            .collect();
+            .filter(|item| !item.trim().is_empty())
+            .map(|text| {
+                let item = parse_description(text);
+                if item.desc.is_empty() {
+                    return Err(anyhow!("The todo '{}' has an empty description", text.trim()));
+                }
+                let due = item.due.as_deref()
+                    .map(|due| parse_due_date(due, today))
+                    .transpose()?;
+                let recurrence = item.every.as_deref()
+                    .map(|every| Recurrence::parse(every, due.unwrap_or(today)))
+                    .transpose()?;
+                // Recurring todos without a due date are due on their first occurrence
+                let due = due.or_else(|| recurrence.as_ref().and_then(|r| r.first_on_or_after(today)));
+                Ok((item, due, recurrence))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if items.is_empty() {
+            return Err(anyhow!("Todo description must not be empty"));
+        }
 
-        for item_desc in items {
-            let id = self.get_next_available_id();
-            self.todos.push(Todo {
+        let hooks = self.config.hooks.clone();
+        let board_name = self.active_board.clone();
+        let board = self.board_mut();
+        let mut ids = Vec::new();
+        for (item, due, recurrence) in items {
+            let id = board.get_next_available_id();
+            let mut todo = Todo {
                 id,
-                desc: item_desc,
+                desc: item.desc,
                 is_complete: false,
                 is_starred: false,
                 timestamp: Utc::now(),
-            });
+                tags: item.tags,
+                notes: String::new(),
+                completed_at: None,
+                due,
+                priority: item.priority.unwrap_or_default(),
+                parent,
+                recurrence,
+            };
+            hooks.on_todo(HookEvent::Add, &mut todo, &board_name)?;
+            board.todos.push(todo);
+            ids.push(id);
         }
+        Ok(ids)
+    }
 
+    /// Edit the description of an existing todo item, applying any inline `#tags`, `due:` date, `every:` rule
+    /// and `!priority`.
+    ///
+    /// The description is kept if the new one only consists of tokens, and `due:none` clears the due date.
+    fn edit(&mut self, id: usize, description: Vec<String>) -> Result<()> {
+        self.edit_todo(id, description)?;
         self.list();
+        Ok(())
     }
 
-    /// Edit the description of an existing todo item 
-    fn edit(&mut self, id: usize, description: Vec<String>) -> Result<()> {
-        if let Some(todo) = self.todos.iter_mut().find(|todo| todo.id == id) {
-            todo.desc = description.join(" ");
-            self.list();
+    /// Helper function to edit the description of a todo item without listing the todos
+    pub(crate) fn edit_todo(&mut self, id: usize, description: Vec<String>) -> Result<()> {
+        if let Some(todo) = self.board_mut().todos.iter_mut().find(|todo| todo.id == id) {
+            let parsed = parse_description(&description.join(" "));
+            let today = Local::now().date_naive();
+            let due = match parsed.due.as_deref() {
+                Some(due) if due.eq_ignore_ascii_case("none") => Some(None),
+                Some(due) => Some(Some(parse_due_date(due, today)?)),
+                None => None,
+            };
+            let recurrence = match parsed.every.as_deref() {
+                Some(every) if every.eq_ignore_ascii_case("none") => Some(None),
+                Some(every) => {
+                    let anchor = due.flatten().or(todo.due).unwrap_or(today);
+                    Some(Some(Recurrence::parse(every, anchor)?))
+                }
+                None => None,
+            };
+
+            if !parsed.desc.is_empty() {
+                todo.desc = parsed.desc;
+            }
+            for tag in parsed.tags {
+                if !todo.tags.contains(&tag) {
+                    todo.tags.push(tag);
+                }
+            }
+            if let Some(due) = due {
+                todo.due = due;
+            }
+            if let Some(priority) = parsed.priority {
+                todo.priority = priority;
+            }
+            if let Some(recurrence) = recurrence {
+                todo.recurrence = recurrence;
+            }
             Ok(())
         } else {
             Err(anyhow!("Todo item with ID {} not found", id))
@@ -94,80 +457,209 @@ impl TodoList {
     }
 
     /// Filters the todo list based on a query string.
-    // TODO: Allow filtering for @Board; #Tag; Dates (regex?)
-    fn filter(&self, query: Vec<String>) -> Result<()> {
-        // Join the query list into a single string and split by "::" to handle multi-query
-        let queries: Vec<String> = query.join(" ")
-            .to_lowercase()
-            .split("::")
-            .map(|q| q.trim().to_string())
-            .collect();
-
-        // Filter the todo list based on the queries
-        let filtered_todos: Vec<&Todo> = self.todos.iter()
-            .filter(|todo| {
-                let desc = todo.desc.to_lowercase();
-                queries.iter().any(|q| desc.contains(q))
-            })
+    ///
+    /// The query language is described by `Query`. Words, phrases and regular expressions match case-sensitively if
+    /// `case_sensitive` is given, and as set by `filter_case_sensitive` in the configuration otherwise.
+    // TODO: Allow filtering for @Board
+    fn filter(&self, query: Vec<String>, case_sensitive: Option<bool>) -> Result<()> {
+        let query = query.join(" ");
+        let case_sensitive = case_sensitive.unwrap_or(self.config.filter_case_sensitive);
+        let parsed = Query::parse(&query, case_sensitive, Local::now().date_naive())?;
+
+        let filtered_todos: Vec<&Todo> = self.board().todos.iter()
+            .filter(|todo| parsed.matches(todo))
             .collect();
 
         if filtered_todos.is_empty() {
-            println!("No results found for query: {:?}", queries);
-            return Ok(());
+            match self.output_format() {
+                OutputFormat::Text => println!("No results found for query: {:?}", query),
+                _ => self.print_todos(&[]),
+            }
+            return Err(NoResults.into());
+        }
+
+        self.print_todos(filtered_todos.as_slice());
+        Ok(())
+    }
+
+    /// Search the todos of the active board by fuzzy matching, listing the best `limit` results first
+    fn search(&self, query: &str, limit: usize) -> Result<()> {
+        let (results, found) = Searcher::new(query).search(&self.board().todos, limit);
+
+        match self.output_format() {
+            OutputFormat::Text if results.is_empty() => println!("No results found for search: {:?}", query),
+            OutputFormat::Text => render_search_results(&results, found, &self.config),
+            format => {
+                let todos: Vec<&Todo> = results.iter().map(|result| result.todo).collect();
+                print!("{}", format_todos(&todos, &self.active_board, format));
+            }
+        }
+        if results.is_empty() {
+            return Err(NoResults.into());
         }
+        Ok(())
+    }
 
-        render_todo_list(filtered_todos.as_slice(), &self.config);
+    /// Mark todo items as done, including all of their subtasks if `cascade` is set.
+    ///
+    /// Completing a recurring todo adds its next occurrence, which takes over the recurrence rule.
+    fn done(&mut self, ids: Vec<usize>, cascade: bool) -> Result<()> {
+        self.complete_todos(ids, cascade)?;
+        self.list();
         Ok(())
     }
 
-    /// Mark todo items as done
-    fn done(&mut self, ids: Vec<usize>) -> Result<()> {
-        modify_todos!(self, ids, |todo: &mut Todo| {
+    /// Helper function to mark todo items as done without listing the todos
+    pub(crate) fn complete_todos(&mut self, ids: Vec<usize>, cascade: bool) -> Result<()> {
+        let today = Local::now().date_naive();
+        let hooks = self.config.hooks.clone();
+        let board_name = self.active_board.clone();
+        let board = self.board_mut();
+        let ids = if cascade { board.with_descendants(ids) } else { ids };
+        for todo in board.todos.iter_mut().filter(|todo| ids.contains(&todo.id) && !todo.is_complete) {
+            hooks.on_todo(HookEvent::Done, todo, &board_name)?;
+        }
+        // The next occurrences are created first, so a todo that cannot recur leaves all todos as they are
+        let next_occurrences = board.todos.iter()
+            .filter(|todo| ids.contains(&todo.id) && !todo.is_complete)
+            .filter_map(|todo| Some(todo.next_occurrence(todo.recurrence.clone()?, today)))
+            .collect::<Result<Vec<_>>>()?;
+        modify_todos!(board, ids, |todo: &mut Todo| {
+            if !todo.is_complete {
+                todo.completed_at = Some(Utc::now());
+                todo.recurrence = None;
+            }
             todo.is_complete = true;
         });
-        self.list();
+        for mut todo in next_occurrences {
+            todo.id = board.get_next_available_id();
+            board.todos.push(todo);
+        }
         Ok(())
     }
 
     /// Mark todo items as not done 
     fn undone(&mut self, ids: Vec<usize>) -> Result<()> {
-        modify_todos!(self, ids, |todo: &mut Todo| {
+        self.reopen_todos(ids)?;
+        self.list();
+        Ok(())
+    }
+
+    /// Helper function to mark todo items as not done without listing the todos
+    pub(crate) fn reopen_todos(&mut self, ids: Vec<usize>) -> Result<()> {
+        let board = self.board_mut();
+        modify_todos!(board, ids, |todo: &mut Todo| {
             todo.is_complete = false;
+            todo.completed_at = None;
         });
-        self.list();
         Ok(())
     }
 
     /// Mark todo items as star 
     fn star(&mut self, ids: Vec<usize>) -> Result<()> {
-        modify_todos!(self, ids, |todo: &mut Todo| {
+        self.toggle_stars(ids)?;
+        self.list();
+        Ok(())
+    }
+
+    /// Helper function to toggle the star of todo items without listing the todos
+    pub(crate) fn toggle_stars(&mut self, ids: Vec<usize>) -> Result<()> {
+        let board = self.board_mut();
+        modify_todos!(board, ids, |todo: &mut Todo| {
             toggle_bool!(todo.is_starred);
         });
+        Ok(())
+    }
+
+    /// Set the priority of todo items
+    fn set_priority(&mut self, priority: Priority, ids: Vec<usize>) -> Result<()> {
+        let board = self.board_mut();
+        modify_todos!(board, ids, |todo: &mut Todo| {
+            todo.priority = priority;
+        });
         self.list();
         Ok(())
     }
 
-    /// Remove todo items by ID
-    fn rm(&mut self, ids: Vec<usize>) -> Result<()>{
+    /// Set or stop the recurrence rule of todo items.
+    ///
+    /// The rule `none` stops the recurrence. Todos without a due date become due on the first
+    /// occurrence of the rule.
+    fn set_recurrence(&mut self, rule: &str, ids: Vec<usize>) -> Result<()> {
+        let today = Local::now().date_naive();
+        let stop = rule.eq_ignore_ascii_case("none") || rule.eq_ignore_ascii_case("stop");
+        // Validate the rule before touching any todo
+        if !stop {
+            Recurrence::parse(rule, today)?;
+        }
+
+        let board = self.board_mut();
+        let mut result = Ok(());
+        modify_todos!(board, ids, |todo: &mut Todo| {
+            if stop {
+                todo.recurrence = None;
+                return;
+            }
+            match Recurrence::parse(rule, todo.due.unwrap_or(today)) {
+                Ok(recurrence) => {
+                    todo.due = todo.due.or_else(|| recurrence.first_on_or_after(today));
+                    todo.recurrence = Some(recurrence);
+                }
+                Err(e) => result = Err(e),
+            }
+        });
+        result?;
+        self.list();
+        Ok(())
+    }
+
+    /// Remove todo items by ID, including all of their subtasks if `cascade` is set.
+    ///
+    /// Removing a todo that still has subtasks without `cascade` fails, so no subtask is left
+    /// pointing to an ID that may be reused.
+    fn rm(&mut self, ids: Vec<usize>, cascade: bool) -> Result<()>{
+        self.remove_todos(ids, cascade)?;
+        self.list();
+        Ok(())
+    }
+
+    /// Helper function to remove todo items without listing the todos
+    pub(crate) fn remove_todos(&mut self, ids: Vec<usize>, cascade: bool) -> Result<()> {
+        let hooks = self.config.hooks.clone();
+        let board_name = self.active_board.clone();
+        let board = self.board_mut();
+        let ids = if cascade {
+            board.with_descendants(ids)
+        } else {
+            if let Some(parent) = ids.iter().find(|&&id| board.descendant_ids(id).iter().any(|child| !ids.contains(child))) {
+                return Err(anyhow!("Todo item with ID {} has subtasks. Use --cascade to remove them as well", parent));
+            }
+            ids
+        };
+        for todo in board.todos.iter_mut().filter(|todo| ids.contains(&todo.id)) {
+            hooks.on_todo(HookEvent::Remove, todo, &board_name)?;
+        }
         for id in ids {
-            if let Some(todo) = self.todos.iter().position(|todo| todo.id == id) {
-                self.available_ids.insert(self.todos.remove(todo).id);
+            if let Some(todo) = board.todos.iter().position(|todo| todo.id == id) {
+                let id = board.todos.remove(todo).id;
+                board.available_ids.insert(id);
             } else {
                 return Err(anyhow!("Todo item with ID {} not found", id));
             }
         };
-        self.list();
         Ok(())
     }
 
     /// Reset the todo list and create a backup file unless statet
     fn reset(&mut self) -> Result<()> {
+        self.config.hooks.on_reset()?;
         if self.config.backup_on_reset {
-            backup_todo_file().context("Backup deletion error")?;
+            self.create_backup().context("Backup deletion error")?;
         }
 
-        self.todos.clear();
-        self.available_ids.clear();
+        let board = self.board_mut();
+        board.todos.clear();
+        board.available_ids.clear();
 
         println!("Todo list has been reset.");
 
@@ -176,11 +668,7 @@ impl TodoList {
 
     /// Sort todo items by their completion status
     fn sort(&mut self, sort_by: Option<SortBy>) {
-        match sort_by {
-            Some(SortBy::Id) => self.todos.sort_by_key(|todo| todo.id),
-            Some(SortBy::Date) => self.todos.sort_by_key(|todo| todo.timestamp),
-            _ => self.todos.sort_by_key(|todo| todo.is_complete),
-        }
+        sort_todos(&mut self.board_mut().todos, sort_by);
         self.list();
     }
 
@@ -198,42 +686,45 @@ impl TodoList {
 
     /// Create a new backup
     fn create_backup(&self) -> Result<()> {
-        backup_todo_file().context("Error creating backup")?;
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize todo list")?;
+        backup_todo_file(&self.companion_path, &content).context("Error creating backup")?;
         Ok(())
     }
 
     /// Delete backups based on the specified option
     fn delete_backup(&self, delete_option: DeleteOptions) -> Result<()> {
         match delete_option.option {
-            DeleteOption::All => delete_backup_files().context("Error deleting all backups")?,
-            DeleteOption::Timestamp { timestamp } => delete_specific_backup_file(&timestamp).context(format!("Error deleting backup with timestamp {}", timestamp))?,
+            DeleteOption::All => delete_backup_files(&self.companion_path).context("Error deleting all backups")?,
+            DeleteOption::Timestamp { timestamp } => delete_specific_backup_file(&self.companion_path, &timestamp).context(format!("Error deleting backup with timestamp {}", timestamp))?,
         }
         Ok(())
     }
 
     /// Restore todo items from a backup
     fn restore_backup(&mut self, timestamp: &str, ids_to_restore: Vec<usize>) -> Result<()> {
-        let todo_list = read_todo_list_from_backup(timestamp)
+        let todo_list = read_todo_list_from_backup(&self.companion_path, timestamp)
             .context(format!("Error restoring backup from {}. The item may not exist in the specified backup.", timestamp))?;
 
+        let backup_board = todo_list.boards.get(&self.active_board)
+            .ok_or_else(|| anyhow!("Board '{}' not found in backup {}", self.active_board, timestamp))?;
+
         for id in ids_to_restore {
-            let todo = self.restore_single_todo_from_backup(&todo_list, id)
+            let todo = self.restore_single_todo_from_backup(backup_board, id)
                 .context(format!("Error restoring backup item with ID {}", id))?;
-            self.todos.push(todo);
+            self.board_mut().todos.push(todo);
         }
 
         Ok(())
     }
 
-    /// Restore a single todo item from a backup list 
-    fn restore_single_todo_from_backup(&mut self, todo_list: &TodoList, id: usize) -> Option<Todo> {
-        if let Some(backup_todo) = todo_list.todos.iter().find(|todo| todo.id == id) {
+    /// Restore a single todo item from a backup board 
+    fn restore_single_todo_from_backup(&mut self, backup_board: &Board, id: usize) -> Option<Todo> {
+        if let Some(backup_todo) = backup_board.todos.iter().find(|todo| todo.id == id) {
             Some(Todo {
-                id: self.get_next_available_id(),
-                desc: backup_todo.desc.clone(),
-                is_complete: backup_todo.is_complete,
-                is_starred: backup_todo.is_starred,
-                timestamp: backup_todo.timestamp,
+                id: self.board_mut().get_next_available_id(),
+                parent: None,
+                ..backup_todo.clone()
             })
         } else {
             eprintln!("Error: Todo item with ID {} not found in backup", id);
@@ -243,52 +734,450 @@ impl TodoList {
 
     /// Show the contents of a specific backup
     fn show_backup(&self, timestamp: &str) -> Result<()> {
-        let todo_list = read_todo_list_from_backup(timestamp)
+        let mut todo_list = read_todo_list_from_backup(&self.companion_path, timestamp)
             .context(format!("Error showing reading contents of {}", timestamp))?;
+        todo_list.output_format = self.output_format;
         todo_list.list();
         Ok(())
     }
 
-    /// List all available backups
+    /// List all available backups, oldest first
     fn list_backups(&self) -> Result<()> {
-        list_backup_files().context("Error listing backups")?;
+        let backups = list_backup_files(&self.companion_path).context("Error listing backups")?;
+        match self.output_format() {
+            OutputFormat::Text => backups.iter().for_each(|(timestamp, _)| println!("{}", timestamp)),
+            format => {
+                let records: Vec<BackupRecord> = backups.iter()
+                    .map(|(timestamp, path)| BackupRecord::new(*timestamp, path.clone()))
+                    .collect();
+                print!("{}", format_backups(&records, format));
+            }
+        }
         Ok(())
     }
 
-    /// Load todo list from a file
-    pub fn load_from_file(file_path: &Path) -> Result<Self> {
-        let mut todo_list = read_todo_list_from_file(file_path)?;
+    /// Show a single todo item with its notes and metadata
+    fn show(&self, id: usize) -> Result<()> {
+        let todo = self.board().todos.iter()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| anyhow!("Todo item with ID {} not found", id))?;
+        render_todo_detail(todo, &self.active_board, &self.config);
+        Ok(())
+    }
 
-        // Load configuration from Lua file
-        todo_list.config = load_config_from_lua()
-            .context("Failed to load configuration from Lua")?;
+    /// Handle note operations based on the provided action
+    fn handle_note(&mut self, note_action: NoteAction) -> Result<()> {
+        match note_action {
+            NoteAction::Add { id, text } => self.append_note(id, text)?,
+            NoteAction::Set { id, text } => self.set_notes(id, text)?,
+            NoteAction::Edit { id } => self.edit_notes(id)?,
+        }
+        Ok(())
+    }
 
-        Ok(todo_list)
+    /// Append a line to the notes of a todo item
+    fn append_note(&mut self, id: usize, text: Vec<String>) -> Result<()> {
+        let todo = self.board_mut().todos.iter_mut()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| anyhow!("Todo item with ID {} not found", id))?;
+
+        if !todo.notes.is_empty() {
+            todo.notes.push('\n');
+        }
+        todo.notes.push_str(&text.join(" "));
+
+        self.show(id)
     }
 
-    /// Save todo list to a file
-    pub fn save_to_file(&self, file_path: &Path) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self)
-            .context("Failed to serialize todo list")?;
+    /// Replace the notes of a todo item, clearing them if no text is given
+    fn set_notes(&mut self, id: usize, text: Vec<String>) -> Result<()> {
+        let todo = self.board_mut().todos.iter_mut()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| anyhow!("Todo item with ID {} not found", id))?;
+
+        todo.notes = text.join(" ");
+
+        self.show(id)
+    }
 
-        let mut file = File::create(file_path)
-            .context("Failed to create todo file")?;
+    /// Open the notes of a todo item in the user's editor
+    fn edit_notes(&mut self, id: usize) -> Result<()> {
+        let todo = self.board_mut().todos.iter_mut()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| anyhow!("Todo item with ID {} not found", id))?;
 
-        file.write_all(content.as_bytes())
-            .context("Failed to write todo file")?;
+        let notes = edit_text_in_editor(&todo.notes)
+            .context("Failed to edit notes")?;
+        todo.notes = notes.trim_end().to_string();
 
+        self.show(id)
+    }
+
+    /// Handle view operations based on the provided action, running the view `name` if there is no action
+    fn handle_view(&mut self, view_action: Option<ViewAction>, name: Option<String>) -> Result<()> {
+        match (view_action, name) {
+            (Some(ViewAction::Save { name, query, sort }), _) => self.save_view(&name, query.join(" "), sort)?,
+            (Some(ViewAction::Delete { name }), _) => self.delete_view(&name)?,
+            (None, Some(name)) => self.run_view(&name)?,
+            _ => self.list_views()?,
+        }
         Ok(())
     }
 
-    /// Retrieve the next available ID or create a new one
-    fn get_next_available_id(&mut self) -> usize {
-        if let Some(&next_id) = self.available_ids.iter().next() {
-            self.available_ids.remove(&next_id);
-            next_id
-        } else {
-            self.todos.len() + 1
+    /// Helper function to get the todos of the active board shown by a view, in the order of the view
+    fn view_todos(&self, view: &View) -> Result<Vec<&Todo>> {
+        let query = view.parse_query(self.config.filter_case_sensitive, Local::now().date_naive())?;
+        let mut todos: Vec<&Todo> = self.board().todos.iter()
+            .filter(|todo| query.matches(todo))
+            .collect();
+        if view.sort.is_some() {
+            sort_todos(&mut todos, view.sort);
         }
-    } 
+        Ok(todos)
+    }
+
+    /// List the todos of a view in its format, unless `--format` is given
+    fn run_view(&self, name: &str) -> Result<()> {
+        let view = self.config.views.get(name).ok_or_else(|| anyhow!("View '{}' not found", name))?;
+        let todos = self.view_todos(view).with_context(|| format!("Invalid view '{}'", name))?;
+        let format = self.output_format.or(view.format).unwrap_or_default();
+
+        if todos.is_empty() {
+            match format {
+                OutputFormat::Text => println!("No results found for view '{}': {:?}", name, view.query),
+                format => self.print_todos_as(&[], format),
+            }
+            return Err(NoResults.into());
+        }
+
+        self.print_todos_as(&todos, format);
+        Ok(())
+    }
+
+    /// List all views with the number of todos of the active board they show
+    fn list_views(&self) -> Result<()> {
+        let views = self.config.views.iter()
+            .map(|(name, view)| {
+                let count = self.view_todos(view).with_context(|| format!("Invalid view '{}'", name))?.len();
+                Ok((name.as_str(), view, count))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        match self.output_format() {
+            OutputFormat::Text => render_view_list(&views),
+            format => {
+                let records: Vec<ViewRecord> = views.iter()
+                    .map(|(name, view, count)| ViewRecord::new(name, view, *count))
+                    .collect();
+                print!("{}", format_views(&records, format));
+            }
+        }
+        Ok(())
+    }
+
+    /// Save a filter query as a view in the configuration file, together with the sort order and the `--format` given.
+    fn save_view(&mut self, name: &str, query: String, sort: Option<SortBy>) -> Result<()> {
+        check_view_name(name)?;
+        let view = View { query, sort, format: self.output_format };
+        view.parse_query(self.config.filter_case_sensitive, Local::now().date_naive())?;
+
+        let mut views = self.config.views.clone();
+        views.insert(name.to_string(), view);
+        let config_path = set_config_value("views", &views.clone().into())?;
+        self.config.views = views;
+        println!("Saved view '{}' in {:?}", name, config_path);
+        Ok(())
+    }
+
+    /// Delete a view from the configuration file
+    fn delete_view(&mut self, name: &str) -> Result<()> {
+        let mut views = self.config.views.clone();
+        if views.remove(name).is_none() {
+            return Err(anyhow!("View '{}' not found", name));
+        }
+        let config_path = set_config_value("views", &views.clone().into())?;
+        self.config.views = views;
+        println!("Deleted view '{}' from {:?}", name, config_path);
+        Ok(())
+    }
+
+    /// Handle tag operations based on the provided action
+    fn handle_tag(&mut self, tag_action: Option<TagAction>) -> Result<()> {
+        match tag_action {
+            Some(TagAction::Add { tag, args }) => self.add_tag(&tag, args)?,
+            Some(TagAction::Remove { tag, args }) => self.remove_tag(&tag, args)?,
+            _ => self.list_tags(),
+        }
+        Ok(())
+    }
+
+    /// List all tags of the active board with the number of todos carrying them
+    fn list_tags(&self) {
+        let mut tag_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in self.board().todos.iter().flat_map(|todo| &todo.tags) {
+            *tag_counts.entry(tag).or_default() += 1;
+        }
+        render_tag_list(&tag_counts);
+    }
+
+    /// Add a tag to todo items
+    fn add_tag(&mut self, tag: &str, ids: Vec<usize>) -> Result<()> {
+        let tag = normalize_tag(tag).ok_or_else(|| anyhow!("Invalid tag name: {}", tag))?;
+        let board = self.board_mut();
+        modify_todos!(board, ids, |todo: &mut Todo| {
+            if !todo.tags.contains(&tag) {
+                todo.tags.push(tag.clone());
+            }
+        });
+        self.list();
+        Ok(())
+    }
+
+    /// Remove a tag from todo items
+    fn remove_tag(&mut self, tag: &str, ids: Vec<usize>) -> Result<()> {
+        let tag = normalize_tag(tag).ok_or_else(|| anyhow!("Invalid tag name: {}", tag))?;
+        let board = self.board_mut();
+        modify_todos!(board, ids, |todo: &mut Todo| {
+            todo.tags.retain(|t| t != &tag);
+        });
+        self.list();
+        Ok(())
+    }
+
+    /// Handle board operations based on the provided action
+    fn handle_board(&mut self, board_action: Option<BoardAction>) -> Result<()> {
+        match board_action {
+            Some(BoardAction::Create { name }) => self.create_board(name)?,
+            Some(BoardAction::Rename { name, new_name }) => self.rename_board(&name, new_name)?,
+            Some(BoardAction::Delete { name }) => self.delete_board(&name)?,
+            Some(BoardAction::Switch { name }) => self.switch_board(name)?,
+            Some(BoardAction::Move { board, args }) => self.move_todos(&board, args)?,
+            _ => self.list_boards(),
+        }
+        Ok(())
+    }
+
+    /// List all boards and mark the active one
+    fn list_boards(&self) {
+        render_board_list(&self.boards, &self.active_board);
+    }
+
+    /// Create a new empty board
+    fn create_board(&mut self, name: String) -> Result<()> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(anyhow!("Board name must not be empty"));
+        }
+        if self.boards.contains_key(&name) {
+            return Err(anyhow!("Board '{}' already exists", name));
+        }
+
+        self.boards.insert(name, Board::default());
+        self.list_boards();
+        Ok(())
+    }
+
+    /// Rename an existing board, keeping it active if it was
+    fn rename_board(&mut self, name: &str, new_name: String) -> Result<()> {
+        let new_name = new_name.trim().to_string();
+        if new_name.is_empty() {
+            return Err(anyhow!("Board name must not be empty"));
+        }
+        if self.boards.contains_key(&new_name) {
+            return Err(anyhow!("Board '{}' already exists", new_name));
+        }
+
+        let board = self.boards.remove(name)
+            .ok_or_else(|| anyhow!("Board '{}' not found", name))?;
+        self.boards.insert(new_name.clone(), board);
+
+        if self.active_board == name {
+            self.active_board = new_name;
+        }
+
+        self.list_boards();
+        Ok(())
+    }
+
+    /// Delete a board and all of its todos
+    fn delete_board(&mut self, name: &str) -> Result<()> {
+        if !self.boards.contains_key(name) {
+            return Err(anyhow!("Board '{}' not found", name));
+        }
+        if self.active_board == name {
+            return Err(anyhow!("Cannot delete the active board '{}'. Switch to another board first.", name));
+        }
+
+        self.boards.remove(name);
+        self.list_boards();
+        Ok(())
+    }
+
+    /// Switch the active board
+    fn switch_board(&mut self, name: String) -> Result<()> {
+        if !self.boards.contains_key(&name) {
+            return Err(anyhow!("Board '{}' not found", name));
+        }
+
+        self.active_board = name;
+        self.list();
+        Ok(())
+    }
+
+    /// Move todo items from the active board to another board.
+    ///
+    /// Subtask links are kept between moved todos and dropped between moved and remaining todos.
+    fn move_todos(&mut self, target: &str, ids: Vec<usize>) -> Result<()> {
+        if target == self.active_board {
+            return Err(anyhow!("Todos are already on board '{}'", target));
+        }
+        if !self.boards.contains_key(target) {
+            return Err(anyhow!("Board '{}' not found", target));
+        }
+
+        let source = self.board_mut();
+        let mut moved = Vec::new();
+        for id in ids {
+            if let Some(index) = source.todos.iter().position(|todo| todo.id == id) {
+                let todo = source.todos.remove(index);
+                source.available_ids.insert(todo.id);
+                moved.push(todo);
+            } else {
+                return Err(anyhow!("Todo item with ID {} not found", id));
+            }
+        }
+
+        let moved_ids: Vec<usize> = moved.iter().map(|todo| todo.id).collect();
+        for todo in source.todos.iter_mut() {
+            if todo.parent.is_some_and(|parent| moved_ids.contains(&parent)) {
+                todo.parent = None;
+            }
+        }
+
+        let target_board = self.boards.get_mut(target)
+            .ok_or_else(|| anyhow!("Board '{}' not found", target))?;
+        let mut new_ids = BTreeMap::new();
+        for todo in moved.iter_mut() {
+            let new_id = target_board.get_next_available_id();
+            new_ids.insert(todo.id, new_id);
+            todo.id = new_id;
+        }
+        for mut todo in moved {
+            todo.parent = todo.parent.and_then(|parent| new_ids.get(&parent).copied());
+            target_board.todos.push(todo);
+        }
+
+        self.list();
+        Ok(())
+    }
+
+    /// Revert the last operations recorded in the journal
+    fn undo(&mut self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            let entry = self.journal.undo(|patch| patch.capture(&self.boards, &self.active_board))
+                .ok_or_else(|| anyhow!("Nothing left to undo"))?;
+            entry.patch.apply(self);
+            self.is_modified = true;
+            println!("Undone: {}", entry.command);
+        }
+        self.list();
+        Ok(())
+    }
+
+    /// Reapply the last undone operations
+    fn redo(&mut self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            let entry = self.journal.redo(|patch| patch.capture(&self.boards, &self.active_board))
+                .ok_or_else(|| anyhow!("Nothing left to redo"))?;
+            entry.patch.apply(self);
+            self.is_modified = true;
+            println!("Redone: {}", entry.command);
+        }
+        self.list();
+        Ok(())
+    }
+
+    /// List the operations that can be undone and redone
+    fn history(&self) {
+        render_history(self.journal.undo_entries(), self.journal.redo_entries());
+    }
+
+    /// Run a command defined in `config.lua`, giving it access to the todo list
+    fn run_custom_command(&mut self, mut args: Vec<String>) -> Result<()> {
+        let name = args.remove(0);
+        self.config.check_command(&name)?;
+        let commands = self.config.commands.clone();
+        commands.run(&name, args, self)
+    }
+
+    /// Announce a newly created project todo list, which is written when the todo list is saved
+    fn init(&mut self) {
+        self.is_modified = true;
+        println!("Created an empty project todo list at {:?}", self.file_path);
+    }
+
+    /// Load the todo list from a storage, together with the journal belonging to it
+    pub fn load(storage: &mut dyn Storage, config: Config) -> Result<Self> {
+        let mut todo_list = storage.load()?;
+
+        todo_list.journal = Journal::load_from_file(&get_journal_file_path(storage.companion_path()))
+            .context("Failed to load operation journal")?;
+
+        todo_list.config = config;
+        todo_list.file_path = storage.path().to_path_buf();
+        todo_list.companion_path = storage.companion_path().to_path_buf();
+
+        Ok(todo_list)
+    }
+
+    /// Whether the todo list changed or was upgraded since it was loaded, so it needs to be saved
+    pub fn needs_save(&self) -> bool {
+        self.is_modified || self.migrated_from.is_some()
+    }
+
+    /// Save the todo list to a storage, together with the journal belonging to it
+    pub fn save(&self, storage: &mut dyn Storage) -> Result<()> {
+        storage.save(self)?;
+
+        self.journal.save_to_file(&get_journal_file_path(storage.companion_path()))
+            .context("Failed to save operation journal")?;
+
+        Ok(())
+    }
+
+}
+
+impl ScriptApi for TodoList {
+    fn board_name(&self) -> &str {
+        &self.active_board
+    }
+
+    fn todos(&self) -> &[Todo] {
+        &self.board().todos
+    }
+
+    fn todo_mut(&mut self, id: usize) -> Option<&mut Todo> {
+        self.board_mut().todos.iter_mut().find(|todo| todo.id == id)
+    }
+
+    fn add_from_description(&mut self, description: &str) -> Result<Vec<usize>> {
+        self.add_todos(vec![description.to_string()], None)
+    }
+
+    fn mark_done(&mut self, id: usize) -> Result<()> {
+        self.complete_todos(vec![id], false)
+    }
+
+    fn remove_todo(&mut self, id: usize) -> Result<()> {
+        self.remove_todos(vec![id], false)
+    }
+
+    fn render(&self, ids: &[usize]) {
+        let todos: Vec<&Todo> = ids.iter()
+            .filter_map(|id| self.board().todos.iter().find(|todo| todo.id == *id))
+            .collect();
+        self.print_todos(&todos);
+    }
 }
 
 /// Helper function to read and parse a `TodoList` from a file.
@@ -311,7 +1200,7 @@ impl TodoList {
 /// * The file cannot be opened.
 /// * The file contents cannot be read.
 /// * The contents cannot be parsed as a `TodoList` due to JSON format issues.
-fn read_todo_list_from_file(file_path: &Path) -> Result<TodoList> {
+pub(crate) fn read_todo_list_from_file(file_path: &Path) -> Result<TodoList> {
     if !file_path.exists() {
         return Ok(TodoList::default());
     }
@@ -321,9 +1210,46 @@ fn read_todo_list_from_file(file_path: &Path) -> Result<TodoList> {
 
     file.read_to_string(&mut content).context("Failed to read todo file")?;
 
-    let todo_list: TodoList = serde_json::from_str(&content)
+    let value: serde_json::Value = serde_json::from_str(&content)
         .context("Failed to parse todo JSON")?;
 
+    parse_todo_list(value)
+}
+
+/// Sort todos by the given criteria, or open todos before done todos if no criteria is given.
+///
+/// The sort is stable, so todos that compare equal keep their order.
+pub(crate) fn sort_todos<T: Borrow<Todo>>(todos: &mut [T], sort_by: Option<SortBy>) {
+    match sort_by {
+        Some(SortBy::Id) => todos.sort_by_key(|todo| todo.borrow().id),
+        Some(SortBy::Date) => todos.sort_by_key(|todo| todo.borrow().timestamp),
+        Some(SortBy::Due) => todos.sort_by_key(|todo| (todo.borrow().due.is_none(), todo.borrow().due)),
+        Some(SortBy::Priority) => todos.sort_by_key(|todo| todo.borrow().priority),
+        _ => todos.sort_by_key(|todo| todo.borrow().is_complete),
+    }
+}
+
+/// Helper function to build a `TodoList` from its JSON representation.
+///
+/// Representations written by older versions are upgraded to the current schema version before they are parsed.
+///
+/// # Arguments
+///
+/// `value` - The JSON representation of the todo list.
+///
+/// # Returns
+///
+/// `Result<TodoList>` - On success, returns the parsed `TodoList`, or an error if the JSON does not describe a todo list
+/// or was written by a newer version.
+fn parse_todo_list(mut value: serde_json::Value) -> Result<TodoList> {
+    let version = migrate_todo_list(&mut value)?;
+
+    let mut todo_list: TodoList = serde_json::from_value(value)
+        .context("Failed to parse todo JSON")?;
+
+    if version < SCHEMA_VERSION {
+        todo_list.migrated_from = Some(version);
+    }
     Ok(todo_list)
 }
 
@@ -334,6 +1260,7 @@ fn read_todo_list_from_file(file_path: &Path) -> Result<TodoList> {
 ///
 /// # Arguments
 ///
+/// `todo_file_path` - The path to the todo file the backup belongs to.
 /// `timestamp` - A string slice that represents the timestamp of the backup file to be read.
 ///
 /// # Returns
@@ -348,56 +1275,74 @@ fn read_todo_list_from_file(file_path: &Path) -> Result<TodoList> {
 /// * The backup file path cannot be constructed.
 /// * The backup file cannot be read or parsed, as described in the documentation for 
 ///   `read_todo_list_from_file`.
-fn read_todo_list_from_backup(timestamp: &str) -> Result<TodoList> {
-    let backup_path = get_backup_file_path(timestamp)?;
+fn read_todo_list_from_backup(todo_file_path: &Path, timestamp: &str) -> Result<TodoList> {
+    let backup_path = get_backup_file_path(todo_file_path, timestamp)?;
     read_todo_list_from_file(&backup_path)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::todo::TodoList;
+    use crate::cli::{Pattern, SortBy};
+    use crate::config::Config;
+    use crate::recurrence::Recurrence;
+    use crate::storage::JsonStorage;
+    use crate::todo::{read_todo_list_from_file, Board, Priority, TodoList, DEFAULT_BOARD};
+    use chrono::NaiveDate;
     use std::collections::BTreeSet;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     // Helper function to create a TodoList with predefined todos
     fn create_todo_list_with_items() -> TodoList {
         let mut todo_list = TodoList::default();
-        todo_list.add(vec!["First task".to_string()]);
-        todo_list.add(vec!["Second task".to_string()]);
+        todo_list.add(vec!["First task".to_string()], None).expect("Failed to add todo");
+        todo_list.add(vec!["Second task".to_string()], None).expect("Failed to add todo");
         todo_list
     }
 
+    // Helper function to create an empty directory for test files, so tests never touch the real data directory
+    fn create_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Failed to create test directory");
+        dir
+    }
+
     #[test]
     fn test_add_todos_generates_unique_ids() {
         let mut todo_list = TodoList::default();
-        todo_list.add(vec!["First task".to_string()]);
-        todo_list.add(vec!["Second task".to_string()]);
-        todo_list.add(vec!["Third task".to_string()]);
+        todo_list.add(vec!["First task".to_string()], None).expect("Failed to add todo");
+        todo_list.add(vec!["Second task".to_string()], None).expect("Failed to add todo");
+        todo_list.add(vec!["Third task".to_string()], None).expect("Failed to add todo");
         
-        assert_eq!(todo_list.todos.len(), 3);
-        let ids: BTreeSet<_> = todo_list.todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(todo_list.board().todos.len(), 3);
+        let ids: BTreeSet<_> = todo_list.board().todos.iter().map(|todo| todo.id).collect();
         assert_eq!(ids.len(), 3); // All IDs should be unique
     }
 
     #[test]
     fn test_todo_list_reset() {
+        let test_dir = create_test_dir("reset");
         let mut todo_list = create_todo_list_with_items();
-        assert_eq!(todo_list.todos.len(), 2);
+        todo_list.companion_path = test_dir.join("todos.json");
+        assert_eq!(todo_list.board().todos.len(), 2);
         
         let res = todo_list.reset();
         assert!(res.is_ok());
-        assert_eq!(todo_list.todos.len(), 0);
-        assert_eq!(todo_list.available_ids.len(), 0);
+        assert_eq!(todo_list.board().todos.len(), 0);
+        assert_eq!(todo_list.board().available_ids.len(), 0);
+        assert_eq!(std::fs::read_dir(test_dir.join("backup")).expect("Backup directory missing").count(), 1);
+
+        std::fs::remove_dir_all(test_dir).expect("Failed to delete test directory");
     }
 
     #[test]
     fn test_mark_todos_as_done() {
         let mut todo_list = create_todo_list_with_items();
-        let ids: Vec<usize> = todo_list.todos.iter().map(|todo| todo.id).collect();
+        let ids: Vec<usize> = todo_list.board().todos.iter().map(|todo| todo.id).collect();
         
-        todo_list.done(ids.clone()).expect("Failed to mark todos as done");
+        todo_list.done(ids.clone(), false).expect("Failed to mark todos as done");
 
-        for todo in todo_list.todos {
+        for todo in &todo_list.board().todos {
             assert!(todo.is_complete, "Todo item with ID {} was not marked as done", todo.id);
         }
     }
@@ -405,11 +1350,11 @@ mod tests {
     #[test]
     fn test_mark_todos_as_star() {
         let mut todo_list = create_todo_list_with_items();
-        let ids: Vec<usize> = todo_list.todos.iter().map(|todo| todo.id).collect();
+        let ids: Vec<usize> = todo_list.board().todos.iter().map(|todo| todo.id).collect();
 
         todo_list.star(ids.clone()).expect("Failed to mark todos as star");
 
-        for todo in todo_list.todos {
+        for todo in &todo_list.board().todos {
             assert!(todo.is_starred, "Todo item with ID {} was not marked as star", todo.id);
         }
     }
@@ -417,22 +1362,22 @@ mod tests {
     #[test]
     fn test_remove_todo() {
         let mut todo_list = create_todo_list_with_items();
-        let id_to_remove = todo_list.todos[0].id;
+        let id_to_remove = todo_list.board().todos[0].id;
 
-        todo_list.rm(vec![id_to_remove]).expect("Failed to remove todo");
+        todo_list.rm(vec![id_to_remove], false).expect("Failed to remove todo");
 
-        assert!(todo_list.todos.iter().all(|todo| todo.id != id_to_remove), "Todo with ID {} was not removed", id_to_remove);
+        assert!(todo_list.board().todos.iter().all(|todo| todo.id != id_to_remove), "Todo with ID {} was not removed", id_to_remove);
     }
 
     #[test]
     fn test_edit_todo() {
         let mut todo_list = create_todo_list_with_items();
-        let id_to_edit = todo_list.todos[0].id;
+        let id_to_edit = todo_list.board().todos[0].id;
         let new_desc = vec!["Updated task description".to_string()];
 
         todo_list.edit(id_to_edit, new_desc.clone()).expect("Failed to edit todo");
 
-        assert_eq!(todo_list.todos[0].desc, new_desc.join(" "));
+        assert_eq!(todo_list.board().todos[0].desc, new_desc.join(" "));
     }
 
     #[test]
@@ -440,7 +1385,7 @@ mod tests {
         let mut todo_list = create_todo_list_with_items();
         let non_existent_id = 999;
 
-        let result = todo_list.rm(vec![non_existent_id]);
+        let result = todo_list.rm(vec![non_existent_id], false);
         assert!(result.is_err(), "Removing non-existent todo should fail");
     }
 
@@ -460,12 +1405,13 @@ mod tests {
         let todo_list = create_todo_list_with_items();
 
         // Save the list to a file
-        todo_list.save_to_file(file_path).expect("Failed to save todo list");
+        todo_list.save(&mut JsonStorage::new(file_path.to_path_buf())).expect("Failed to save todo list");
 
         // Load the list from the file
-        let loaded_todo_list = TodoList::load_from_file(file_path).expect("Failed to load todo list");
+        let loaded_todo_list = TodoList::load(&mut JsonStorage::new(file_path.to_path_buf()), Config::default()).expect("Failed to load todo list");
 
-        assert_eq!(loaded_todo_list.todos.len(), todo_list.todos.len());
+        assert_eq!(loaded_todo_list.board().todos.len(), todo_list.board().todos.len());
+        assert_eq!(loaded_todo_list.migrated_from, None);
 
         // Clean up test file
         std::fs::remove_file(file_path).expect("Failed to delete test file");
@@ -476,17 +1422,333 @@ mod tests {
         let mut todo_list = TodoList::default();
 
         // Add some todos
-        todo_list.add(vec!["First task".to_string()]);
-        todo_list.add(vec!["Second task".to_string()]);
+        todo_list.add(vec!["First task".to_string()], None).expect("Failed to add todo");
+        todo_list.add(vec!["Second task".to_string()], None).expect("Failed to add todo");
 
         // Remove the first todo
-        let id_to_remove = todo_list.todos[0].id;
-        todo_list.rm(vec![id_to_remove]).expect("Failed to remove todo");
+        let id_to_remove = todo_list.board().todos[0].id;
+        todo_list.rm(vec![id_to_remove], false).expect("Failed to remove todo");
 
         // Add a new todo, which should reuse the removed ID
-        todo_list.add(vec!["Third task".to_string()]);
+        todo_list.add(vec!["Third task".to_string()], None).expect("Failed to add todo");
         
-        assert_eq!(todo_list.todos.len(), 2);
-        assert!(todo_list.todos.iter().any(|todo| todo.id == id_to_remove), "ID was not reused");
+        assert_eq!(todo_list.board().todos.len(), 2);
+        assert!(todo_list.board().todos.iter().any(|todo| todo.id == id_to_remove), "ID was not reused");
+    }
+
+    #[test]
+    fn test_boards_have_separate_id_spaces() {
+        let mut todo_list = create_todo_list_with_items();
+        todo_list.create_board("work".to_string()).expect("Failed to create board");
+        todo_list.switch_board("work".to_string()).expect("Failed to switch board");
+        todo_list.add(vec!["Work task".to_string()], None).expect("Failed to add todo");
+
+        assert_eq!(todo_list.board().todos.len(), 1);
+        assert_eq!(todo_list.board().todos[0].id, 1);
+        assert_eq!(todo_list.boards[DEFAULT_BOARD].todos.len(), 2);
+    }
+
+    #[test]
+    fn test_move_todos_between_boards() {
+        let mut todo_list = create_todo_list_with_items();
+        todo_list.create_board("work".to_string()).expect("Failed to create board");
+
+        todo_list.move_todos("work", vec![1]).expect("Failed to move todo");
+
+        assert_eq!(todo_list.board().todos.len(), 1);
+        assert!(todo_list.board().available_ids.contains(&1));
+        assert_eq!(todo_list.boards["work"].todos[0].desc, "First task");
+        assert_eq!(todo_list.boards["work"].todos[0].id, 1);
+    }
+
+    #[test]
+    fn test_rename_and_delete_board() {
+        let mut todo_list = TodoList::default();
+        todo_list.create_board("home".to_string()).expect("Failed to create board");
+        todo_list.rename_board(DEFAULT_BOARD, "inbox".to_string()).expect("Failed to rename board");
+
+        assert_eq!(todo_list.active_board, "inbox");
+        assert!(todo_list.delete_board("inbox").is_err(), "Deleting the active board should fail");
+
+        todo_list.delete_board("home").expect("Failed to delete board");
+        assert_eq!(todo_list.boards.len(), 1);
+    }
+
+    #[test]
+    fn test_load_legacy_todo_file() {
+        let file_path = Path::new("test_legacy_todos.json");
+        let board = Board::default();
+        std::fs::write(file_path, serde_json::to_string(&board).unwrap()).expect("Failed to write legacy file");
+
+        let loaded_todo_list = read_todo_list_from_file(file_path).expect("Failed to load legacy todo list");
+        assert_eq!(loaded_todo_list.active_board, DEFAULT_BOARD);
+        assert_eq!(loaded_todo_list.migrated_from, Some(0));
+
+        std::fs::remove_file(file_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_add_extracts_tags() {
+        let mut todo_list = TodoList::default();
+        todo_list.add(vec!["Deploy #work staging".to_string()], None).expect("Failed to add todo");
+
+        assert_eq!(todo_list.board().todos[0].desc, "Deploy staging");
+        assert_eq!(todo_list.board().todos[0].tags, vec!["work".to_string()]);
+
+        let error = todo_list.add(vec!["#work".to_string()], None).expect_err("A todo without description should be rejected");
+        assert_eq!(error.to_string(), "The todo '#work' has an empty description");
+        assert!(todo_list.add(vec!["::".to_string()], None).is_err(), "Adding no todo should fail");
+        assert_eq!(todo_list.board().todos.len(), 1);
+    }
+
+    #[test]
+    fn test_add_and_remove_tag() {
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.add_tag("#Home", vec![1, 2]).expect("Failed to add tag");
+        todo_list.add_tag("home", vec![1]).expect("Failed to add tag");
+        assert_eq!(todo_list.board().todos[0].tags, vec!["home".to_string()]);
+
+        todo_list.remove_tag("home", vec![2]).expect("Failed to remove tag");
+        assert!(todo_list.board().todos[1].tags.is_empty());
+
+        assert!(todo_list.add_tag("not valid", vec![1]).is_err(), "Invalid tag names should be rejected");
+    }
+
+    #[test]
+    fn test_append_and_set_notes() {
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.append_note(1, vec!["First line".to_string()]).expect("Failed to append note");
+        todo_list.append_note(1, vec!["Second".to_string(), "line".to_string()]).expect("Failed to append note");
+        assert_eq!(todo_list.board().todos[0].notes, "First line\nSecond line");
+
+        todo_list.set_notes(1, Vec::new()).expect("Failed to clear notes");
+        assert!(todo_list.board().todos[0].notes.is_empty());
+
+        assert!(todo_list.append_note(999, vec!["Missing".to_string()]).is_err(), "Noting a non-existent todo should fail");
+    }
+
+    #[test]
+    fn test_done_records_completion_time() {
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.done(vec![1], false).expect("Failed to mark todo as done");
+        assert!(todo_list.board().todos[0].completed_at.is_some());
+
+        todo_list.undone(vec![1]).expect("Failed to mark todo as not done");
+        assert!(todo_list.board().todos[0].completed_at.is_none());
+    }
+
+    #[test]
+    fn test_add_and_edit_due_date() {
+        let mut todo_list = TodoList::default();
+        todo_list.add(vec!["Send invoice due:2026-11-03".to_string()], None).expect("Failed to add todo");
+
+        assert_eq!(todo_list.board().todos[0].desc, "Send invoice");
+        assert_eq!(todo_list.board().todos[0].due, NaiveDate::from_ymd_opt(2026, 11, 3));
+
+        todo_list.edit(1, vec!["due:none".to_string()]).expect("Failed to edit todo");
+        assert_eq!(todo_list.board().todos[0].desc, "Send invoice");
+        assert_eq!(todo_list.board().todos[0].due, None);
+
+        assert!(todo_list.add(vec!["Invalid due:someday".to_string()], None).is_err(), "Invalid due dates should be rejected");
+        assert_eq!(todo_list.board().todos.len(), 1);
+    }
+
+    #[test]
+    fn test_sort_by_due_date() {
+        let mut todo_list = TodoList::default();
+        todo_list.add(vec!["No due date::Later due:2026-12-01::Sooner due:2026-11-01".to_string()], None).expect("Failed to add todos");
+
+        todo_list.sort(Some(SortBy::Due));
+
+        let descs: Vec<&str> = todo_list.board().todos.iter().map(|todo| todo.desc.as_str()).collect();
+        assert_eq!(descs, vec!["Sooner", "Later", "No due date"]);
+    }
+
+    #[test]
+    fn test_add_with_priority_and_sort() {
+        let mut todo_list = TodoList::default();
+        todo_list.add(vec!["Low task !low::Plain task::Urgent task !high".to_string()], None).expect("Failed to add todos");
+
+        assert_eq!(todo_list.board().todos[2].desc, "Urgent task");
+        assert_eq!(todo_list.board().todos[2].priority, Priority::High);
+
+        todo_list.set_priority(Priority::Medium, vec![2]).expect("Failed to set priority");
+        todo_list.sort(Some(SortBy::Priority));
+
+        let ids: Vec<usize> = todo_list.board().todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_load_migrates_starred_todos_to_high_priority() {
+        let file_path = Path::new("test_starred_todos.json");
+        let content = r#"{"todos":[
+            {"id":1,"desc":"Starred","is_complete":false,"is_starred":true,"timestamp":"2024-08-01T10:00:00Z"},
+            {"id":2,"desc":"Plain","is_complete":false,"is_starred":false,"timestamp":"2024-08-01T10:00:00Z"}
+        ],"available_ids":[]}"#;
+        std::fs::write(file_path, content).expect("Failed to write legacy file");
+
+        let loaded_todo_list = read_todo_list_from_file(file_path).expect("Failed to load legacy todo list");
+        assert_eq!(loaded_todo_list.board().todos[0].priority, Priority::High);
+        assert_eq!(loaded_todo_list.board().todos[1].priority, Priority::None);
+
+        std::fs::remove_file(file_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_add_subtasks() {
+        let mut todo_list = create_todo_list_with_items();
+        todo_list.add(vec!["Child one::Child two".to_string()], Some(1)).expect("Failed to add subtasks");
+
+        assert!(todo_list.board().todos.iter().filter(|todo| todo.id > 2).all(|todo| todo.parent == Some(1)));
+        assert!(todo_list.add(vec!["Orphan".to_string()], Some(999)).is_err(), "Adding to a non-existent parent should fail");
+    }
+
+    #[test]
+    fn test_cascading_done_and_rm() {
+        let mut todo_list = create_todo_list_with_items();
+        todo_list.add(vec!["Child".to_string()], Some(1)).expect("Failed to add subtask");
+        todo_list.add(vec!["Grandchild".to_string()], Some(3)).expect("Failed to add subtask");
+
+        todo_list.done(vec![1], true).expect("Failed to mark todos as done");
+        assert!(todo_list.board().todos.iter().filter(|todo| todo.id != 2).all(|todo| todo.is_complete));
+
+        assert!(todo_list.rm(vec![1], false).is_err(), "Removing a parent without cascade should fail");
+        todo_list.rm(vec![1], true).expect("Failed to remove todos");
+        assert_eq!(todo_list.board().todos.len(), 1);
+        assert_eq!(todo_list.board().available_ids.len(), 3);
+    }
+
+    #[test]
+    fn test_move_keeps_subtask_links() {
+        let mut todo_list = create_todo_list_with_items();
+        todo_list.add(vec!["Child".to_string()], Some(2)).expect("Failed to add subtask");
+        todo_list.create_board("work".to_string()).expect("Failed to create board");
+
+        todo_list.move_todos("work", vec![2, 3]).expect("Failed to move todos");
+
+        let work = &todo_list.boards["work"];
+        assert_eq!(work.todos[1].parent, Some(work.todos[0].id));
+    }
+
+    #[test]
+    fn test_done_recurring_todo_spawns_next_occurrence() {
+        let mut todo_list = TodoList::default();
+        todo_list.add(vec!["Weekly report every:3d due:2099-01-01".to_string()], None).expect("Failed to add todo");
+
+        todo_list.done(vec![1], false).expect("Failed to mark todo as done");
+
+        let todos = &todo_list.board().todos;
+        assert_eq!(todos.len(), 2);
+        assert!(todos[0].is_complete && todos[0].recurrence.is_none());
+        assert_eq!(todos[1].desc, "Weekly report");
+        assert!(!todos[1].is_complete);
+        assert_eq!(todos[1].due, NaiveDate::from_ymd_opt(2099, 1, 4));
+        assert_eq!(todos[1].recurrence, Some(Recurrence::Every(3)));
+    }
+
+    #[test]
+    fn test_done_recurring_todo_beyond_the_last_date_fails() {
+        let mut todo_list = TodoList::default();
+        let error = todo_list.add(vec!["Someday every:999999999d".to_string()], None).expect_err("The interval should be rejected");
+        assert!(error.to_string().contains("Intervals can be at most 3660 days"));
+
+        todo_list.add(vec!["Someday every:3d".to_string()], None).expect("Failed to add todo");
+        todo_list.board_mut().todos[0].recurrence = Some(Recurrence::Every(999_999_999));
+        let error = todo_list.done(vec![1], false).expect_err("The next occurrence should be out of range");
+        assert_eq!(error.to_string(), "The next occurrence of todo 1 is too far in the future");
+        assert_eq!(todo_list.board().todos.len(), 1);
+        assert!(!todo_list.board().todos[0].is_complete);
+    }
+
+    #[test]
+    fn test_stop_recurrence() {
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.set_recurrence("daily", vec![1]).expect("Failed to set recurrence");
+        assert_eq!(todo_list.board().todos[0].recurrence, Some(Recurrence::Daily));
+        assert!(todo_list.board().todos[0].due.is_some());
+
+        todo_list.set_recurrence("none", vec![1]).expect("Failed to stop recurrence");
+        todo_list.done(vec![1], false).expect("Failed to mark todo as done");
+        assert_eq!(todo_list.board().todos.len(), 2);
+
+        assert!(todo_list.set_recurrence("sometimes", vec![2]).is_err(), "Invalid rules should be rejected");
+    }
+
+    #[test]
+    fn test_undo_and_redo_operations() {
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.handle_cli(Pattern::Done { cascade: false, args: vec![1] }).expect("Failed to mark todo as done");
+        todo_list.handle_cli(Pattern::Rm { cascade: false, args: vec![2] }).expect("Failed to remove todo");
+        todo_list.handle_cli(Pattern::List).expect("Failed to list todos");
+        assert_eq!(todo_list.journal.undo_entries().len(), 2, "Only changes should be recorded");
+
+        todo_list.handle_cli(Pattern::Undo { steps: 2 }).expect("Failed to undo");
+        assert_eq!(todo_list.board().todos.len(), 2);
+        assert!(!todo_list.board().todos[0].is_complete);
+
+        todo_list.handle_cli(Pattern::Redo { steps: 1 }).expect("Failed to redo");
+        assert!(todo_list.board().todos[0].is_complete);
+        assert_eq!(todo_list.board().todos.len(), 2);
+
+        assert!(todo_list.handle_cli(Pattern::Redo { steps: 2 }).is_err(), "Redoing more than was undone should fail");
+    }
+
+    #[test]
+    fn test_read_only_commands_are_not_snapshotted() {
+        let mut todo_list = create_todo_list_with_items();
+        let read_only = [
+            Pattern::List,
+            Pattern::Filter { query: vec!["First".to_string()], case_sensitive: false, ignore_case: false },
+            Pattern::Search { query: vec!["task".to_string()], limit: 10 },
+            Pattern::Show { id: 1 },
+            Pattern::History,
+        ];
+        for pattern in read_only {
+            assert!(todo_list.snapshot_for(&pattern).is_none());
+            todo_list.handle_cli(pattern).expect("Failed to run read-only command");
+        }
+        assert!(!todo_list.needs_save());
+        assert!(todo_list.journal.undo_entries().is_empty());
+
+        assert!(todo_list.snapshot_for(&Pattern::Done { cascade: false, args: vec![1] }).is_some());
+    }
+
+    #[test]
+    fn test_save_keeps_previous_version() {
+        let file_path = Path::new("test_previous_todos.json");
+        let previous_path = Path::new("test_previous_todos.json.bak");
+        let mut todo_list = create_todo_list_with_items();
+
+        todo_list.save(&mut JsonStorage::new(file_path.to_path_buf())).expect("Failed to save todo list");
+        todo_list.add(vec!["Third task".to_string()], None).expect("Failed to add todo");
+        todo_list.save(&mut JsonStorage::new(file_path.to_path_buf())).expect("Failed to save todo list");
+
+        let previous = TodoList::load(&mut JsonStorage::new(previous_path.to_path_buf()), Config::default()).expect("Failed to load previous version");
+        assert_eq!(previous.board().todos.len(), 2);
+        assert!(!Path::new("test_previous_todos.json.tmp").exists(), "Temporary file was not cleaned up");
+
+        std::fs::remove_file(file_path).expect("Failed to delete test file");
+        std::fs::remove_file(previous_path).expect("Failed to delete test file");
+    }
+
+    #[test]
+    fn test_load_corrupted_file_points_to_previous_version() {
+        let test_dir = create_test_dir("corrupted");
+        let file_path = test_dir.join("todos.json");
+        let previous_path = test_dir.join("todos.json.bak");
+        create_todo_list_with_items().save(&mut JsonStorage::new(file_path.clone())).expect("Failed to save todo list");
+        std::fs::copy(&file_path, &previous_path).expect("Failed to copy test file");
+        std::fs::write(&file_path, "{\"boards\": {").expect("Failed to corrupt test file");
+
+        let error = TodoList::load(&mut JsonStorage::new(file_path), Config::default()).err().expect("Loading a corrupted file should fail");
+        assert!(format!("{:#}", error).contains("todos.json.bak"));
+
+        std::fs::remove_dir_all(test_dir).expect("Failed to delete test directory");
     }
 }
@@ -1,31 +1,151 @@
 use anyhow::{anyhow, Context, Ok, Result};
-use std::fs;
-use std::path::PathBuf;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::cli::ConfigFormat;
 
-/// Get the path to the todo file.
+/// Get the path to the data directory holding the todo list.
 ///
-/// If the directory does not exist, it attempts to create it.
+/// The directory is taken from the `TODO_DIR` environment variable, the `data_dir` configuration option or the
+/// application data directory, in this order. If the directory does not exist, it attempts to create it.
+///
+/// # Arguments
+///
+/// `configured_dir` - The data directory set in the configuration, if any.
 ///
 /// # Returns
 ///
-/// `Result<PathBuf>` - The full path to the `todos.json` file, or an error if the path could not be determined or created.
-pub fn get_todo_file_path() -> Result<PathBuf> {
-    let mut path = get_app_dir().context("Could not locate or create the application data directory for storing todo files")?;
-    path.push("todos.json");
-    Ok(path)
+/// `Result<PathBuf>` - The full path to the data directory, or an error if the directory could not be determined or created.
+pub fn get_data_dir(configured_dir: Option<&Path>) -> Result<PathBuf> {
+    let data_dir = match env::var_os("TODO_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match configured_dir {
+            Some(dir) => expand_home_dir(dir)?,
+            None => return get_app_dir().context("Could not locate or create the application data directory for storing todo files"),
+        },
+    };
+
+    fs::create_dir_all(&data_dir)
+        .with_context(|| format!("Failed to create data directory {:?}", data_dir))?;
+    Ok(data_dir)
 }
 
-/// Get the path to the backup directory.
+/// Get the path to the todo file in a data directory.
 ///
-/// If the directory does not exist, it attempts to create it.
+/// # Returns
+///
+/// `PathBuf` - The full path to the `todos.json` file.
+pub fn get_todo_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("todos.json")
+}
+
+/// Get the path to the SQLite database used by the `sqlite` storage backend in a data directory.
+///
+/// # Returns
+///
+/// `PathBuf` - The full path to the `todos.db` file.
+pub fn get_database_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("todos.db")
+}
+
+/// Get the path the lock, the journal, the previous version and the backups of a project todo file are named after.
+///
+/// These files do not belong into the repository, so they are kept in the data directory instead, in a directory named
+/// after the project directory and a hash of its full path, e.g. `projects/website-3f0c2a9d5e1b7c84/todos.json`. If the
+/// directory does not exist, it attempts to create it.
+///
+/// # Arguments
+///
+/// `data_dir` - The data directory holding the global todo list.
+/// `project_file` - The path to the project todo file.
+///
+/// # Returns
+///
+/// `Result<PathBuf>` - The path the files belonging to the project todo file are named after, or an error if their
+/// directory could not be created.
+pub fn get_project_companion_path(data_dir: &Path, project_file: &Path) -> Result<PathBuf> {
+    let project_dir = project_file.parent().unwrap_or(Path::new("."));
+    let project_dir = fs::canonicalize(project_dir).unwrap_or_else(|_| project_dir.to_path_buf());
+    let name = project_dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "project".to_string());
+    // FNV-1a, as the hash has to stay the same across builds unlike the hasher of the standard library
+    let hash = project_dir.as_os_str().as_encoded_bytes().iter()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3));
+
+    let dir = data_dir.join("projects").join(format!("{}-{:016x}", name, hash));
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create project data directory {:?}", dir))?;
+    Ok(dir.join("todos.json"))
+}
+
+/// Replace a leading `~` in a path with the home directory of the user.
+fn expand_home_dir(path: &Path) -> Result<PathBuf> {
+    match path.strip_prefix("~") {
+        Result::Ok(rest) => {
+            let home = dirs::home_dir().ok_or_else(|| anyhow!("Home directory not found"))?;
+            Ok(home.join(rest))
+        }
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
+
+/// Get the path to the operation journal belonging to a todo file.
+///
+/// The journal is stored next to the todo file, so `todos.json` uses `todos.journal.json`.
+///
+/// # Arguments
+///
+/// `todo_file_path` - The path to the todo file the journal belongs to.
+///
+/// # Returns
+///
+/// `PathBuf` - The full path to the journal file.
+pub fn get_journal_file_path(todo_file_path: &Path) -> PathBuf {
+    todo_file_path.with_extension("journal.json")
+}
+
+/// Get the path to the previous version of a file, which is kept when the file is overwritten.
+///
+/// The previous version is stored next to the file, so `todos.json` uses `todos.json.bak`.
+pub fn get_previous_version_file_path(file_path: &Path) -> PathBuf {
+    append_to_file_name(file_path, ".bak")
+}
+
+/// Get the path to the lock file guarding a todo file against concurrent invocations.
+///
+/// The lock file is stored next to the todo file, so `todos.json` uses `todos.json.lock`.
+pub fn get_lock_file_path(todo_file_path: &Path) -> PathBuf {
+    append_to_file_name(todo_file_path, ".lock")
+}
+
+/// Append a suffix to the file name of a path, keeping its extension, e.g. `todos.json` -> `todos.json.tmp`.
+fn append_to_file_name(file_path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name: OsString = file_path.as_os_str().to_owned();
+    file_name.push(suffix);
+    PathBuf::from(file_name)
+}
+
+/// Get the path to the backup directory of a todo file.
+///
+/// Backups are kept in a `backup` directory next to the todo file. If the directory does not exist, it attempts to create it.
+///
+/// # Arguments
+///
+/// `todo_file_path` - The path to the todo file the backups belong to.
 ///
 /// # Returns
 ///
 /// `Result<PathBuf>` - The full path to the backup directory, or an error if the directory could not be created.
-fn get_backup_dir_path() -> Result<PathBuf> {
-    let mut path = get_app_dir()?;
-    path.push("backup");
+fn get_backup_dir_path(todo_file_path: &Path) -> Result<PathBuf> {
+    let path = todo_file_path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .join("backup");
     fs::create_dir_all(&path).context("Failed to create backup directory")?;
     Ok(path)
 }
@@ -64,13 +184,14 @@ pub fn get_config_dir() -> Result<PathBuf> {
 ///
 /// # Arguments
 ///
+/// `todo_file_path` - The path to the todo file the backup belongs to.
 /// `timestamp` - A string slice representing the timestamp of the desired backup file.
 ///
 /// # Returns
 ///
 /// `Result<PathBuf>` - The full path to the backup file, or an error if the file does not exist.
-pub fn get_backup_file_path(timestamp: &str) -> Result<PathBuf> {
-    let backup_file = get_backup_dir_path()?.join(format!("todos_backup_{}.json", timestamp));
+pub fn get_backup_file_path(todo_file_path: &Path, timestamp: &str) -> Result<PathBuf> {
+    let backup_file = get_backup_dir_path(todo_file_path)?.join(format!("todos_backup_{}.json", timestamp));
 
     if backup_file.exists() {
         Ok(backup_file)
@@ -79,17 +200,24 @@ pub fn get_backup_file_path(timestamp: &str) -> Result<PathBuf> {
     }
 }
 
-/// Get the path to the configuration file.
+/// Get the path to the configuration file of a format.
 ///
-/// This function returns the file path for the `config.lua` file within the application's configuration directory.
-/// If the directory does not exist, it attempts to create it.
+/// This function returns the file path for the `config.lua` or `config.toml` file within the application's configuration
+/// directory. If the directory does not exist, it attempts to create it.
+///
+/// # Arguments
+///
+/// `format` - The format of the configuration file.
 ///
 /// # Returns
 ///
-/// `Result<PathBuf>` - The full path to the `config.lua` file, or an error if the path could not be determined or created.
-pub fn get_config_file_path() -> Result<PathBuf> {
+/// `Result<PathBuf>` - The full path to the configuration file, or an error if the path could not be determined or created.
+pub fn get_config_file_path(format: ConfigFormat) -> Result<PathBuf> {
     let mut path = get_config_dir()?;
-    path.push("config.lua");
+    path.push(match format {
+        ConfigFormat::Lua => "config.lua",
+        ConfigFormat::Toml => "config.toml",
+    });
     Ok(path)
 }
 
@@ -99,11 +227,15 @@ pub fn get_config_file_path() -> Result<PathBuf> {
 /// This function deletes all backup files in the backup directory that follow the naming convention `todos_backup_*.json`.
 /// It skips any files that do not match this pattern.
 ///
+/// # Arguments
+///
+/// `todo_file_path` - The path to the todo file the backups belong to.
+///
 /// # Returns
 ///
 /// `Result<()>` - Returns `Ok(())` if all matching backup files are successfully deleted, or an error if the directory cannot be read.
-pub fn delete_backup_files() -> Result<()> {
-    let backup_dir = get_backup_dir_path()?;
+pub fn delete_backup_files(todo_file_path: &Path) -> Result<()> {
+    let backup_dir = get_backup_dir_path(todo_file_path)?;
 
     for entry in fs::read_dir(backup_dir).context("Failed to read backup directory")? {
         let entry = entry?;
@@ -122,67 +254,101 @@ pub fn delete_backup_files() -> Result<()> {
 ///
 /// # Arguments
 ///
+/// `todo_file_path` - The path to the todo file the backup belongs to.
 /// `timestamp` - A string slice representing the timestamp of the backup file to delete.
 ///
 /// # Returns
 ///
 /// `Result<()>` - Returns `Ok(())` if the backup file is successfully deleted, or an error if it cannot be found or deleted.
-pub fn delete_specific_backup_file(timestamp: &str) -> Result<()> {
-    let backup_file = get_backup_file_path(timestamp)?;
+pub fn delete_specific_backup_file(todo_file_path: &Path, timestamp: &str) -> Result<()> {
+    let backup_file = get_backup_file_path(todo_file_path, timestamp)?;
     fs::remove_file(&backup_file).with_context(|| format!("Failed to delete backup file {} at: {:?}. Please check if the file exists.", timestamp, backup_file))?;
     Ok(())
 }
 
-/// Backup the current todo file.
+/// Backup the current todo list.
+///
+/// This function writes the JSON representation of the todo list to the backup directory with a timestamped filename,
+/// regardless of the storage backend the todo list is kept in. It returns the path to the created backup file.
+///
+/// # Arguments
 ///
-/// This function creates a backup of the current `todos.json` file by copying it to the backup directory with a timestamped filename. 
-/// It returns the path to the created backup file.
+/// `todo_file_path` - The path to the todo file the backup belongs to.
+/// `content` - The todo list serialized as JSON.
 ///
 /// # Returns
 ///
 /// `Result<PathBuf>` - The full path to the newly created backup file, or an error if the backup could not be completed.
-pub fn backup_todo_file() -> Result<PathBuf> {
-    let todo_path = get_todo_file_path()?;
-    if !todo_path.exists() {
-        return Err(anyhow!("Todo file does not exist. Please ensure that the todo list has been created before attempting to back it up."));
-    }
-
-    let backup_dir = get_backup_dir_path()?;
+pub fn backup_todo_file(todo_file_path: &Path, content: &str) -> Result<PathBuf> {
+    let backup_dir = get_backup_dir_path(todo_file_path)?;
     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
     let backup_path = backup_dir.join(format!("todos_backup_{}.json", timestamp));
 
-    fs::copy(&todo_path, &backup_path)
-        .with_context(|| format!("Failed to rename todo file to backup path: {:?}", backup_path))?;
+    fs::write(&backup_path, content)
+        .with_context(|| format!("Failed to write backup file: {:?}", backup_path))?;
 
     Ok(backup_path)
 }
 
-/// List all backup files and print their timestamps.
+/// Get the path to the most recent backup file of a todo file.
+///
+/// # Returns
 ///
-/// This function lists all the backup files in the backup directory by extracting and printing the timestamps from their filenames. 
+/// `Result<Option<PathBuf>>` - The full path to the backup file with the highest timestamp, `None` if there are no backups,
+/// or an error if the backup directory cannot be read.
+pub fn get_latest_backup_file_path(todo_file_path: &Path) -> Result<Option<PathBuf>> {
+    let backup_dir = get_backup_dir_path(todo_file_path)?;
+    let mut latest: Option<(u64, PathBuf)> = None;
+
+    for entry in fs::read_dir(backup_dir).context("Failed to read backup directory")? {
+        let path = entry?.path();
+        let timestamp = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(trim_backup_file_name)
+            .and_then(|timestamp| timestamp.parse::<u64>().ok());
+
+        if let Some(timestamp) = timestamp {
+            if latest.as_ref().is_none_or(|(latest_timestamp, _)| timestamp > *latest_timestamp) {
+                latest = Some((timestamp, path));
+            }
+        }
+    }
+
+    Ok(latest.map(|(_, path)| path))
+}
+
+/// List all backup files with their timestamps.
+///
+/// This function lists all the backup files in the backup directory by extracting the timestamps from their filenames.
 /// It skips files that do not match the expected naming convention.
 ///
+/// # Arguments
+///
+/// `todo_file_path` - The path to the todo file the backups belong to.
+///
 /// # Returns
 ///
-/// `Result<()>` - Returns `Ok(())` if the files are successfully listed, or an error if the directory cannot be read.
-pub fn list_backup_files() -> Result<()> {
-    let backup_dir = get_backup_dir_path()?;
+/// `Result<Vec<(u64, PathBuf)>>` - The timestamps and paths of the backup files, oldest first, or an error if the
+/// directory cannot be read.
+pub fn list_backup_files(todo_file_path: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let backup_dir = get_backup_dir_path(todo_file_path)?;
+    let mut backups = Vec::new();
 
     for entry in fs::read_dir(backup_dir).context("Failed to read backup directory")? {
         let entry = entry?;
         let path = entry.path();
         if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
             if file_name.starts_with("todos_backup_") {
-                if let Some(timestamp) = trim_backup_file_name(file_name) {
-                    println!("{}", timestamp);
-                } else {
-                    eprint!("The backup file name format is incorrect")
+                match trim_backup_file_name(file_name).and_then(|timestamp| timestamp.parse().ok()) {
+                    Some(timestamp) => backups.push((timestamp, path)),
+                    None => eprintln!("The backup file name format is incorrect: {}", file_name),
                 }
             }
         }
     }
 
-    Ok(())
+    backups.sort();
+    Ok(backups)
 }
 
 /// Trim the prefix and suffix of a backup file name to extract the timestamp.
@@ -199,3 +365,108 @@ pub fn list_backup_files() -> Result<()> {
 fn trim_backup_file_name(input: &str) -> Option<&str> {
     input.strip_prefix("todos_backup_").and_then(|s| s.strip_suffix(".json")) 
 }
+
+/// Open a text in the user's editor and return the edited text.
+///
+/// The editor is taken from the `VISUAL` or `EDITOR` environment variable, falling back to `vi`.
+/// The text is written to a temporary file which is removed after the editor exits.
+///
+/// # Arguments
+///
+/// `initial` - The text the editor is opened with.
+///
+/// # Returns
+///
+/// `Result<String>` - The contents of the file after the editor exited, or an error if the editor could not be run
+/// or exited unsuccessfully.
+pub fn edit_text_in_editor(initial: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut editor_args = editor.split_whitespace();
+    let program = editor_args.next().ok_or_else(|| anyhow!("No editor configured"))?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let file_path = env::temp_dir().join(format!("todo_notes_{}_{}.md", std::process::id(), timestamp));
+    fs::write(&file_path, initial).context("Failed to write temporary notes file")?;
+
+    let status = Command::new(program)
+        .args(editor_args)
+        .arg(&file_path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", editor));
+
+    let content = fs::read_to_string(&file_path).context("Failed to read temporary notes file");
+    let _ = fs::remove_file(&file_path);
+
+    if !status?.success() {
+        return Err(anyhow!("Editor '{}' exited with an error", editor));
+    }
+
+    content
+}
+
+/// Write a file atomically, so a crash or a full disk never leaves it truncated.
+///
+/// The content is written to a temporary file in the same directory, flushed to disk and then renamed over
+/// the original file. If `previous_path` is given, the previous version of the file is kept there.
+///
+/// # Arguments
+///
+/// `file_path` - The path to the file to write.
+/// `content` - The new content of the file.
+/// `previous_path` - Where to keep the previous version of the file, if at all.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the file was replaced, or an error if any step failed. On error the original file is left untouched.
+pub fn write_file_atomically(file_path: &Path, content: &[u8], previous_path: Option<&Path>) -> Result<()> {
+    let temp_path = append_to_file_name(file_path, ".tmp");
+
+    let write_temp_file = || -> Result<()> {
+        let mut file = File::create(&temp_path).context("Failed to create temporary file")?;
+        file.write_all(content).context("Failed to write temporary file")?;
+        file.sync_all().context("Failed to flush temporary file to disk")?;
+        Ok(())
+    };
+    if let Err(e) = write_temp_file() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if let Some(previous_path) = previous_path.filter(|_| file_path.exists()) {
+        fs::copy(file_path, previous_path)
+            .context("Failed to keep the previous version of the file")?;
+    }
+
+    fs::rename(&temp_path, file_path)
+        .with_context(|| format!("Failed to replace {:?}", file_path))?;
+
+    // Make sure the rename itself is persisted
+    #[cfg(unix)]
+    if let Some(dir) = file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir).and_then(|dir| dir.sync_all()).context("Failed to flush directory to disk")?;
+    }
+
+    Ok(())
+}
+
+/// Ask the user a yes/no question on the terminal.
+///
+/// If standard input is not a terminal, the question is not asked and the answer is no.
+///
+/// # Returns
+///
+/// `Result<bool>` - Whether the user answered yes, or an error if the answer could not be read.
+pub fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).context("Failed to read answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use clap::{CommandFactory, ValueEnum};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use crate::cli::{Cli, OutputFormat, SortBy};
+use crate::config::{ConfigValue, FromConfigValue};
+use crate::query::Query;
+
+/// Struct representing a saved filter query with the order and format its todos are listed in.
+///
+/// In the configuration, a view is a table with the keys `query`, `sort` and `format`, where only `query` is required,
+/// or just the query as a string.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct View {
+    /// The filter query selecting the todos of the view
+    pub query: String,
+    /// The order the todos are listed in, the order of the board if not set
+    pub sort: Option<SortBy>,
+    /// The format the todos are listed in unless `--format` is given
+    pub format: Option<OutputFormat>,
+}
+
+impl View {
+    /// Build a view from its table in the `views` table of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `table` - The keys and values of the view.
+    ///
+    /// # Returns
+    ///
+    /// `Result<View>` - The view, or an error naming the first unknown key or invalid value.
+    pub fn from_table(table: BTreeMap<String, ConfigValue>) -> Result<Self> {
+        let mut query = None;
+        let mut view = View { query: String::new(), sort: None, format: None };
+        for (key, value) in table {
+            match key.as_str() {
+                "query" => query = Some(String::from_config_value(value).context("invalid query")?),
+                "sort" => view.sort = Some(parse_value_enum(&String::from_config_value(value).context("invalid sort")?)?),
+                "format" => view.format = Some(parse_value_enum(&String::from_config_value(value).context("invalid format")?)?),
+                _ => return Err(anyhow!("unknown view option '{}'", key)),
+            }
+        }
+        view.query = query.ok_or_else(|| anyhow!("missing query"))?;
+        Ok(view)
+    }
+
+    /// Parse the query of the view.
+    ///
+    /// # Arguments
+    ///
+    /// `case_sensitive` - Whether words, phrases and regular expressions match case-sensitively.
+    /// `today` - The date relative dates and ages are resolved against.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Query>` - The query, or an error pointing at the column of the first problem.
+    pub fn parse_query(&self, case_sensitive: bool, today: NaiveDate) -> Result<Query> {
+        Ok(Query::parse(&self.query, case_sensitive, today)?)
+    }
+}
+
+impl From<View> for ConfigValue {
+    fn from(view: View) -> Self {
+        let mut table = BTreeMap::new();
+        table.insert("query".to_string(), view.query.into());
+        if let Some(sort) = view.sort {
+            table.insert("sort".to_string(), value_enum_name(&sort).into());
+        }
+        if let Some(format) = view.format {
+            table.insert("format".to_string(), value_enum_name(&format).into());
+        }
+        ConfigValue::Table(table)
+    }
+}
+
+/// Check that a view can be run by its name, which must not be the name of a `view` subcommand.
+///
+/// # Returns
+///
+/// `Result<()>` - Returns `Ok(())` if the name can be used, or an error describing why it cannot.
+pub fn check_view_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('-') || name.chars().any(char::is_whitespace) {
+        return Err(anyhow!("Invalid view name '{}'", name));
+    }
+    let command = Cli::command();
+    let is_subcommand = command.find_subcommand("view")
+        .is_some_and(|view| view.get_subcommands().any(|action| action.get_name() == name || action.get_all_aliases().any(|alias| alias == name)));
+    if is_subcommand {
+        return Err(anyhow!("The view name '{}' is reserved for the 'view {}' command", name, name));
+    }
+    Ok(())
+}
+
+/// Helper function to parse a value of a command-line enum as written in the configuration
+fn parse_value_enum<T: ValueEnum>(value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let names: Vec<String> = T::value_variants().iter().map(value_enum_name).collect();
+        anyhow!("unknown value '{}'. Use {}", value, names.join(", "))
+    })
+}
+
+/// Get the name of a value of a command-line enum as accepted on the command line and in the configuration
+pub fn value_enum_name<T: ValueEnum>(value: &T) -> String {
+    value.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::cli::{OutputFormat, SortBy};
+    use crate::config::{ConfigValue, FromConfigValue};
+    use crate::view::{check_view_name, View};
+
+    #[test]
+    fn test_view_from_config_value() {
+        let mut table = BTreeMap::new();
+        table.insert("query".to_string(), ConfigValue::from("not done and age>14d"));
+        table.insert("sort".to_string(), ConfigValue::from("Priority"));
+        table.insert("format".to_string(), ConfigValue::from("csv"));
+        let view = View::from_config_value(ConfigValue::Table(table.clone())).unwrap();
+        assert_eq!(view, View { query: "not done and age>14d".to_string(), sort: Some(SortBy::Priority), format: Some(OutputFormat::Csv) });
+
+        let saved = ConfigValue::from(view.clone());
+        assert_eq!(View::from_config_value(saved).unwrap(), view);
+
+        let view = View::from_config_value(ConfigValue::from("#work")).unwrap();
+        assert_eq!(view, View { query: "#work".to_string(), sort: None, format: None });
+
+        table.insert("sort".to_string(), ConfigValue::from("size"));
+        let error = View::from_config_value(ConfigValue::Table(table)).expect_err("The sort order should be rejected");
+        assert_eq!(error.to_string(), "unknown value 'size'. Use id, date, due, priority, done");
+
+        let error = View::from_config_value(ConfigValue::from("(done")).expect_err("The query should be rejected");
+        assert!(error.to_string().starts_with("Invalid query at column 6"));
+    }
+
+    #[test]
+    fn test_check_view_name() {
+        assert!(check_view_name("stale").is_ok());
+        assert!(check_view_name("save").is_err());
+        assert!(check_view_name("D").is_err());
+        assert!(check_view_name("two words").is_err());
+    }
+}